@@ -0,0 +1,134 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use ruzstd::decoding::decodebuffer::Decodebuffer;
+use ruzstd::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+// (literals, offset, match_length) like they would come out of the sequence decoding
+fn make_sequences(content_size: usize) -> Vec<(Vec<u8>, usize, usize)> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0x5EED);
+    let mut seqs = vec![];
+    let mut produced = 0;
+    while produced < content_size {
+        let literals: Vec<u8> = (0..rng.gen_range(0, 32)).map(|_| rng.gen()).collect();
+        produced += literals.len();
+        let offset = rng.gen_range(1, usize::max(produced, 2));
+        let match_length = rng.gen_range(3, 128);
+        produced += match_length;
+        seqs.push((literals, offset, match_length));
+    }
+    seqs
+}
+
+fn execute(buf: &mut Decodebuffer, seqs: &[(Vec<u8>, usize, usize)]) -> usize {
+    for (literals, offset, match_length) in seqs {
+        buf.push(literals);
+        buf.repeat(*offset, *match_length).unwrap();
+    }
+    let out = buf.drain();
+    black_box(out.len())
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let content_size = 1024 * 1024;
+    let seqs = make_sequences(content_size);
+
+    let mut ring = Decodebuffer::new(content_size, false);
+    c.bench_function("decodebuffer ring", |b| {
+        b.iter(|| {
+            ring.reset(content_size, false).unwrap();
+            execute(&mut ring, &seqs)
+        })
+    });
+
+    // only literals and a small window, so the ringbuffer wraps around all the time
+    let literals: Vec<Vec<u8>> = seqs
+        .iter()
+        .map(|(literals, _, _)| literals.clone())
+        .collect();
+    let window_size = 64 * 1024;
+    let mut extend = Decodebuffer::new(window_size, false);
+    c.bench_function("decodebuffer ring extend", |b| {
+        b.iter(|| {
+            extend.reset(window_size, false).unwrap();
+            for literals in &literals {
+                extend.push(literals);
+                if extend.len() > 2 * window_size {
+                    black_box(extend.drain_to_window_size());
+                }
+            }
+            black_box(extend.drain().len())
+        })
+    });
+
+    let mut linear = Decodebuffer::new(content_size, true);
+    c.bench_function("decodebuffer single segment", |b| {
+        b.iter(|| {
+            linear.reset(content_size, true).unwrap();
+            execute(&mut linear, &seqs)
+        })
+    });
+
+    // z000000 is one of the single segment frames in the corpus
+    let frame = std::fs::read("./decodecorpus_files/z000000.zst").unwrap();
+    let mut frame_dec = FrameDecoder::new();
+    c.bench_function("decode single segment frame", |b| {
+        b.iter(|| {
+            let mut source = frame.as_slice();
+            frame_dec.reset(&mut source).unwrap();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::All)
+                .unwrap();
+            black_box(frame_dec.collect().unwrap())
+        })
+    });
+
+    // z000033 has the most sequences of all frames in the corpus
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    c.bench_function("decode sequence heavy frame", |b| {
+        b.iter(|| {
+            let mut source = frame.as_slice();
+            frame_dec.reset(&mut source).unwrap();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::All)
+                .unwrap();
+            black_box(frame_dec.collect().unwrap())
+        })
+    });
+
+    // a new decoder for every frame, so the ringbuffer either grows from nothing or is allocated once up front
+    let content_size = std::fs::read("./decodecorpus_files/z000033").unwrap().len();
+    c.bench_function("decode sequence heavy frame new decoder", |b| {
+        b.iter(|| {
+            let mut source = frame.as_slice();
+            let mut frame_dec = FrameDecoder::new();
+            frame_dec.reset(&mut source).unwrap();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::All)
+                .unwrap();
+            black_box(frame_dec.collect().unwrap())
+        })
+    });
+    c.bench_function("decode sequence heavy frame with initial capacity", |b| {
+        b.iter(|| {
+            let mut source = frame.as_slice();
+            let mut frame_dec = FrameDecoder::with_initial_capacity(content_size);
+            frame_dec.reset(&mut source).unwrap();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::All)
+                .unwrap();
+            black_box(frame_dec.collect().unwrap())
+        })
+    });
+
+    // tiny frames are where allocating a new decoder for every call shows the most
+    let frame = std::fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    c.bench_function("decompress tiny frame", |b| {
+        b.iter(|| black_box(ruzstd::decompress(&frame).unwrap()))
+    });
+    c.bench_function("decode_pooled tiny frame", |b| {
+        b.iter(|| black_box(ruzstd::decode_pooled(&frame).unwrap()))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,226 @@
+use crate::errors::{BlockDecoderError, EofContext, FrameDecoderError};
+use crate::frame::{self, SKIPPABLE_MAGIC_NUM_MAX, SKIPPABLE_MAGIC_NUM_MIN};
+use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Random access to the frames of a seekable source, e.g. a file with many zstd frames and skippable frames holding
+/// metadata. The frames are listed the first time they are needed by walking the frame and block headers and seeking
+/// over the block contents, after that every frame can be read on its own in any order.
+pub struct Archive<READ: Read + Seek> {
+    source: READ,
+    decoder: FrameDecoder,
+    entries: Option<Vec<ArchiveEntry>>,
+}
+
+/// A frame in an Archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Where the frame starts in the source
+    pub offset: u64,
+    /// How many bytes of the source the frame takes up, including its header and checksum
+    pub compressed_size: u64,
+    pub kind: ArchiveEntryKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveEntryKind {
+    /// A zstd frame. The decompressed size is the one declared in the frame header, frames that do not declare it
+    /// are scanned with frame::scan_decompressed_size. It is None if that is not possible because the frame needs a dictionary
+    Frame { decompressed_size: Option<u64> },
+    /// A skippable frame with its magic number, reading it returns its user data
+    Skippable { magic: u32 },
+}
+
+impl<READ: Read + Seek> Archive<READ> {
+    /// Nothing is read from the source until the frames are listed or read
+    pub fn new(source: READ) -> Archive<READ> {
+        Self::new_with_decoder(source, FrameDecoder::new())
+    }
+
+    /// Like new, but uses the given decoder for read_frame, e.g. one with dictionaries added
+    pub fn new_with_decoder(source: READ, decoder: FrameDecoder) -> Archive<READ> {
+        Archive {
+            source,
+            decoder,
+            entries: None,
+        }
+    }
+
+    /// All frames in the order they appear in the source. They are listed on the first call
+    pub fn entries(&mut self) -> Result<&[ArchiveEntry], FrameDecoderError> {
+        if self.entries.is_none() {
+            self.entries = Some(self.list_entries()?);
+        }
+        Ok(self.entries.as_deref().expect("listed above"))
+    }
+
+    /// Decodes the frame with the given index into entries(), or returns the user data if it is a skippable frame.
+    /// If the frame has a checksum that does not match its content this fails with InvalidDataError::ChecksumMismatch
+    ///
+    /// Panics if index is not smaller than the number of entries
+    pub fn read_frame(&mut self, index: usize) -> Result<Vec<u8>, FrameDecoderError> {
+        let entry = self.entries()?[index].clone();
+        self.source
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| read_error("seeking to the frame", e))?;
+        let mut frame_source = (&mut self.source).take(entry.compressed_size);
+
+        if let ArchiveEntryKind::Skippable { .. } = entry.kind {
+            let mut data = Vec::new();
+            frame_source
+                .read_to_end(&mut data)
+                .map_err(|e| read_error("reading the skippable frame", e))?;
+            return Ok(data.split_off(8));
+        }
+
+        self.decoder.reset(&mut frame_source)?;
+        self.decoder
+            .decode_blocks(&mut frame_source, BlockDecodingStrategy::All)?;
+        let mut data = Vec::new();
+        self.decoder
+            .collect_to_writer(&mut data)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        self.decoder.verify_checksum()?;
+        Ok(data)
+    }
+
+    pub fn inner(self) -> READ {
+        self.source
+    }
+
+    fn list_entries(&mut self) -> Result<Vec<ArchiveEntry>, FrameDecoderError> {
+        let end = self
+            .source
+            .seek(SeekFrom::End(0))
+            .map_err(|e| read_error("seeking to the end of the source", e))?;
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < end {
+            self.source
+                .seek(SeekFrom::Start(offset))
+                .map_err(|e| read_error("seeking to the next frame", e))?;
+            let entry = self.read_entry(offset, end)?;
+            offset += entry.compressed_size;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Walks the headers of the frame starting at offset, the source has to be positioned there.
+    ///
+    /// The size of the frame is the sum of what its block headers claim, the blocks themselves are not decoded.
+    /// A corrupt block header that claims more bytes than the source has left can not be told apart from a frame that
+    /// was cut off, so it is reported as UnexpectedEof with the bytes the headers claim are missing. A corrupt header
+    /// that stays inside of the source makes the next block header be read from the wrong place, which usually fails
+    /// as invalid data here or when the frame is read with read_frame
+    fn read_entry(&mut self, offset: u64, end: u64) -> Result<ArchiveEntry, FrameDecoderError> {
+        let missing_after = |size: u64, context: EofContext| {
+            let frame_end = offset.saturating_add(size);
+            if frame_end > end {
+                Err(FrameDecoderError::UnexpectedEof {
+                    context,
+                    bytes_missing: usize::try_from(frame_end - end).unwrap_or(usize::MAX),
+                })
+            } else {
+                Ok(())
+            }
+        };
+        missing_after(4, EofContext::FrameHeader)?;
+        let mut magic = [0u8; 4];
+        self.source
+            .read_exact(&mut magic)
+            .map_err(|e| read_error("reading the magic number", e))?;
+        let magic_num = u32::from_le_bytes(magic);
+
+        if (SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num) {
+            missing_after(8, EofContext::FrameHeader)?;
+            let mut size = [0u8; 4];
+            self.source
+                .read_exact(&mut size)
+                .map_err(|e| read_error("reading the size of a skippable frame", e))?;
+            let compressed_size = 8 + u64::from(u32::from_le_bytes(size));
+            missing_after(compressed_size, EofContext::BlockContent)?;
+            return Ok(ArchiveEntry {
+                offset,
+                compressed_size,
+                kind: ArchiveEntryKind::Skippable { magic: magic_num },
+            });
+        }
+
+        let (frame, header_size) = frame::read_frame_header((&magic[..]).chain(&mut self.source))?;
+        frame
+            .check_valid()
+            .map_err(FrameDecoderError::invalid_init)?;
+        let mut compressed_size = u64::from(header_size);
+        let mut block_dec = crate::decoding::block_decoder::new();
+        loop {
+            let (block_header, block_header_size) =
+                block_dec
+                    .read_block_header(&mut self.source)
+                    .map_err(BlockDecoderError::into_header_error)?;
+            compressed_size += u64::from(block_header_size) + u64::from(block_header.content_size);
+            missing_after(compressed_size, EofContext::BlockContent)?;
+            self.source
+                .seek(SeekFrom::Current(i64::from(block_header.content_size)))
+                .map_err(|e| read_error("seeking over a block", e))?;
+            if block_header.last_block {
+                break;
+            }
+        }
+        if frame.header.descriptor.content_checksum_flag() {
+            compressed_size += 4;
+            missing_after(compressed_size, EofContext::BlockContent)?;
+        }
+
+        let declared_size = if frame
+            .header
+            .descriptor
+            .frame_content_size_bytes()
+            .map_err(FrameDecoderError::invalid_init)?
+            == 0
+        {
+            None
+        } else {
+            Some(
+                frame
+                    .header
+                    .frame_content_size()
+                    .map_err(FrameDecoderError::invalid_init)?,
+            )
+        };
+        let decompressed_size = match declared_size {
+            Some(size) => Some(size),
+            None => self.scan_decompressed_size(offset, compressed_size)?,
+        };
+        Ok(ArchiveEntry {
+            offset,
+            compressed_size,
+            kind: ArchiveEntryKind::Frame { decompressed_size },
+        })
+    }
+
+    fn scan_decompressed_size(
+        &mut self,
+        offset: u64,
+        compressed_size: u64,
+    ) -> Result<Option<u64>, FrameDecoderError> {
+        self.source
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| read_error("seeking to the frame", e))?;
+        let mut frame = Vec::new();
+        (&mut self.source)
+            .take(compressed_size)
+            .read_to_end(&mut frame)
+            .map_err(|e| read_error("reading the frame", e))?;
+        match frame::scan_decompressed_size(&frame) {
+            Ok(size) => Ok(Some(size)),
+            Err(FrameDecoderError::DictNotProvided { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn read_error(action: &str, e: std::io::Error) -> FrameDecoderError {
+    FrameDecoderError::FailedToReadSource(format!("Error while {}: {}", action, e))
+}
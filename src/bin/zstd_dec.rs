@@ -0,0 +1,138 @@
+extern crate ruzstd;
+use ruzstd::frame::{SKIPPABLE_MAGIC_NUM_MAX, SKIPPABLE_MAGIC_NUM_MIN};
+use ruzstd::{FrameDecoder, StreamingDecoder};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+// the decoded output is usually much bigger than the input, so it gets the bigger buffer
+const INPUT_BUFFER_SIZE: usize = 128 * 1024;
+const OUTPUT_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+const DECODE_CHUNK_SIZE: usize = 1024 * 1024;
+
+const USAGE: &str = "Usage: zstd-dec [-d] [-c] [FILE]
+Decompresses FILE, or stdin if FILE is missing or -, to stdout.
+All frames in the input are decoded one after the other, skippable frames are skipped.
+-d and -c are accepted for compatibility with zstd -dc, the output always goes to stdout";
+
+fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+
+    let mut file_path = None;
+    for arg in &args {
+        match arg.as_str() {
+            "-d" | "-c" | "-dc" | "-cd" => {}
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-" => file_path = None,
+            flag if flag.starts_with('-') => {
+                eprintln!("Unknown flag: {}\n{}", flag, USAGE);
+                std::process::exit(1);
+            }
+            path => {
+                if file_path.is_some() {
+                    eprintln!("Only one file can be decompressed at a time\n{}", USAGE);
+                    std::process::exit(1);
+                }
+                file_path = Some(path);
+            }
+        }
+    }
+
+    let source: Box<dyn Read> = match file_path {
+        Some(path) => match File::open(path) {
+            Ok(f) => Box::new(f),
+            Err(e) => {
+                eprintln!("Could not open {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdin()),
+    };
+
+    let stdout = std::io::stdout();
+    let output = BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, stdout.lock());
+    if let Err(e) = decode_all_frames(BufReader::with_capacity(INPUT_BUFFER_SIZE, source), output) {
+        eprintln!("Error while decompressing: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Decodes all frames in input into output, reusing one FrameDecoder for all of them
+fn decode_all_frames(mut input: impl Read, mut output: impl Write) -> std::io::Result<()> {
+    let mut frame_decoder = FrameDecoder::new();
+    let mut chunk = vec![0u8; DECODE_CHUNK_SIZE];
+
+    loop {
+        let mut magic = [0u8; 4];
+        let bytes_read = read_full(&mut input, &mut magic)?;
+        if bytes_read == 0 {
+            break;
+        }
+        if bytes_read < magic.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "The input ended in the middle of a magic number",
+            ));
+        }
+
+        let magic_num = u32::from_le_bytes(magic);
+        if (SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num) {
+            skip_frame(&mut input)?;
+            continue;
+        }
+
+        // the magic number was already taken from the input, give it back to the decoder in front of the rest of the frame
+        let mut decoder =
+            StreamingDecoder::new_with_decoder((&magic[..]).chain(&mut input), frame_decoder)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        loop {
+            let bytes = decoder.read(&mut chunk)?;
+            if bytes == 0 {
+                break;
+            }
+            output.write_all(&chunk[..bytes])?;
+        }
+        frame_decoder = decoder.inner();
+    }
+
+    output.flush()
+}
+
+/// Skips the content of a skippable frame whose magic number was already read
+fn skip_frame(input: &mut impl Read) -> std::io::Result<()> {
+    let mut size = [0u8; 4];
+    if read_full(input, &mut size)? < size.len() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "The input ended in the header of a skippable frame",
+        ));
+    }
+    let size = u64::from(u32::from_le_bytes(size));
+    let skipped = std::io::copy(&mut input.take(size), &mut std::io::sink())?;
+    if skipped < size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!(
+                "The input ended {} bytes before the end of a skippable frame",
+                size - skipped
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Like read_exact but returns how many bytes were read if the input ends early
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match input.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(n) => bytes_read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(bytes_read)
+}
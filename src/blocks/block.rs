@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockType {
     Raw,
     RLE,
@@ -22,3 +23,16 @@ pub struct BlockHeader {
     pub decompressed_size: u32,
     pub content_size: u32,
 }
+
+/// The contents of a block without executing its sequences. See frame::inspect_blocks
+pub struct BlockInspection {
+    pub block_type: BlockType,
+    pub content_size: u32,
+    /// How many literals the block contains. For raw and RLE blocks this is the whole decoded block
+    pub literals_length: u32,
+    /// How many bytes the block decodes to (Block_Size of raw and RLE blocks, the literals plus all match lengths
+    /// of compressed blocks), e.g. to reserve the output space before executing the block
+    pub regenerated_size: u32,
+    /// The sequences of a compressed block. The offsets are the values as they are encoded, so 1-3 refer to the repeat offsets
+    pub sequences: Vec<super::sequence_section::Sequence>,
+}
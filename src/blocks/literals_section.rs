@@ -109,6 +109,7 @@ impl LiteralsSection {
         match self.ls_type {
             LiteralsSectionType::RLE | LiteralsSectionType::Raw => {
                 self.compressed_size = None;
+                self.num_streams = None;
                 match size_format {
                     0 | 2 => {
                         //size_format actually only uses one bit
@@ -140,7 +141,7 @@ impl LiteralsSection {
                     0 => {
                         self.num_streams = Some(1);
                     }
-                    1 | 2 | 3 => {
+                    1..=3 => {
                         self.num_streams = Some(4);
                     }
                     _ => panic!(
@@ -3,7 +3,7 @@ pub struct SequencesHeader {
     pub modes: Option<CompressionModes>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Sequence {
     pub ll: u32,
     pub ml: u32,
@@ -89,6 +89,11 @@ impl SequencesHeader {
                 &source[1..]
             }
             128..=254 => {
+                // 0 sequences can also be written with two bytes, then there are no compression modes after them
+                if source.len() >= 2 && source[0] == 128 && source[1] == 0 {
+                    self.num_sequences = 0;
+                    return Ok(2);
+                }
                 if source.len() < 3 {
                     return Err(format!(
                         "source must have at least {} bytes to parse header",
@@ -4,7 +4,7 @@ pub struct BitReader<'s> {
 }
 
 impl<'s> BitReader<'s> {
-    pub fn new(source: &'s [u8]) -> BitReader<'_> {
+    pub fn new(source: &'s [u8]) -> BitReader<'s> {
         BitReader { idx: 0, source }
     }
 
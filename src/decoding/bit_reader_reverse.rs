@@ -14,7 +14,7 @@ impl<'s> BitReaderReversed<'s> {
         self.idx + self.bits_in_container as isize
     }
 
-    pub fn new(source: &'s [u8]) -> BitReaderReversed<'_> {
+    pub fn new(source: &'s [u8]) -> BitReaderReversed<'s> {
         BitReaderReversed {
             idx: source.len() as isize * 8,
             source,
@@ -23,6 +23,33 @@ impl<'s> BitReaderReversed<'s> {
         }
     }
 
+    /// Tops the container up with as many whole bytes as fit. Away from the start of the source this is one unaligned
+    /// load of the 8 bytes in front of idx, so the container holds at least 57 bits after it.
+    /// Only the first few bytes of the source are read in smaller pieces.
+    #[inline(always)]
+    fn fill(&mut self) {
+        let want_to_read = (64 - self.bits_in_container) & !7;
+        if want_to_read == 0 {
+            return;
+        }
+        if self.idx < 64 {
+            self.refill_container();
+            return;
+        }
+        // idx only moves in whole bytes while the source is not used up, so this loads the bits [idx - 64, idx)
+        let byte_idx = self.idx as usize / 8;
+        let loaded = LittleEndian::read_u64(&self.source[byte_idx - 8..byte_idx]);
+        self.bit_container = if want_to_read == 64 {
+            loaded
+        } else {
+            (self.bit_container << want_to_read) | (loaded >> (64 - want_to_read))
+        };
+        self.bits_in_container += want_to_read;
+        self.idx -= want_to_read as isize;
+    }
+
+    /// The slow path of fill near the start of the source, where less than 8 bytes are left in front of idx
+    #[cold]
     fn refill_container(&mut self) {
         let want_to_read = 64 - self.bits_in_container;
         let can_read = if want_to_read as isize > self.idx {
@@ -97,7 +124,7 @@ impl<'s> BitReaderReversed<'s> {
 
         if (self.bits_in_container as isize) < n {
             while (self.bits_in_container <= 56) && (self.bits_in_container as isize) < n {
-                self.refill_container();
+                self.fill();
             }
             if (self.bits_in_container as isize) < n {
                 return Err(format!("Cant fullfill read of {} bytes on reversed bitreader even after refill. Would need a bigger container", n));
@@ -117,6 +144,35 @@ impl<'s> BitReaderReversed<'s> {
         Ok(value_masked)
     }
 
+    /// Makes sure at least n bits are in the container, so up to n bits can be read with get_bits_unchecked.
+    /// n must not be bigger than 56. Returns false if the source is almost used up, then get_bits has to be used
+    #[inline(always)]
+    pub fn ensure_bits(&mut self, n: u8) -> bool {
+        internal_debug_assert!(n <= 56);
+        if self.bits_in_container >= n {
+            return true;
+        }
+        if self.idx < 64 {
+            return false;
+        }
+        // with at least 64 bits left in the source fill leaves at least 57 bits in the container
+        self.fill();
+        true
+    }
+
+    /// Like get_bits but takes the bits from the container without any checks.
+    /// This is only correct if the bits were made available with ensure_bits before
+    #[inline(always)]
+    pub fn get_bits_unchecked(&mut self, n: u8) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        internal_debug_assert!(n <= self.bits_in_container);
+        let value = self.bit_container >> (self.bits_in_container - n);
+        self.bits_in_container -= n;
+        value & ((1 << n) - 1)
+    }
+
     pub fn reset(&mut self, new_source: &'s [u8]) {
         self.idx = new_source.len() as isize * 8;
         self.source = new_source;
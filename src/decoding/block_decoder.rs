@@ -2,11 +2,16 @@ use super::super::blocks::block::BlockHeader;
 use super::super::blocks::block::BlockType;
 use super::super::blocks::literals_section::LiteralsSection;
 use super::super::blocks::literals_section::LiteralsSectionType;
+use super::super::blocks::sequence_section::Sequence;
 use super::super::blocks::sequence_section::SequencesHeader;
 use super::literals_section_decoder::decode_literals;
 use super::sequence_section_decoder::decode_sequences;
-use crate::decoding::scratch::DecoderScratch;
+#[cfg(feature = "profiling")]
+use crate::decoding::profile::Stopwatch;
+use crate::decoding::scratch::{DecoderScratch, FSEScratch};
 use crate::decoding::sequence_execution::execute_sequences;
+use crate::decoding::source::read_until_full;
+use crate::errors::{BlockDecoderError, EofContext};
 use std::io::Read;
 
 pub struct BlockDecoder {
@@ -28,19 +33,39 @@ pub fn new() -> BlockDecoder {
     }
 }
 
-const ABSOLUTE_MAXIMUM_BLOCK_SIZE: u32 = 128 * 1024;
+pub(crate) const ABSOLUTE_MAXIMUM_BLOCK_SIZE: u32 = 128 * 1024;
 
 impl BlockDecoder {
     pub fn decode_block_content(
         &mut self,
         header: &BlockHeader,
         workspace: &mut DecoderScratch, //reuse this as often as possible. Not only if the trees are reused but also reuse the allocations when building new trees
+        source: impl Read,
+    ) -> Result<u64, BlockDecoderError> {
+        // compressed blocks are profiled stage by stage in decompress_block
+        #[cfg(feature = "profiling")]
+        if matches!(header.block_type, BlockType::Raw | BlockType::RLE) {
+            let stopwatch = Stopwatch::start();
+            let result = self.decode_block_body(header, workspace, source);
+            stopwatch.stop(&mut workspace.profile.raw_rle_time);
+            if result.is_ok() {
+                workspace.profile.raw_rle_bytes += u64::from(header.decompressed_size);
+            }
+            return result;
+        }
+        self.decode_block_body(header, workspace, source)
+    }
+
+    fn decode_block_body(
+        &mut self,
+        header: &BlockHeader,
+        workspace: &mut DecoderScratch,
         mut source: impl Read,
-    ) -> Result<u64, String> {
+    ) -> Result<u64, BlockDecoderError> {
         match self.internal_state {
             DecoderState::ReadyToDecodeNextBody => {/* Happy :) */},
-            DecoderState::Failed => return Err("Cant decode next block if failed along the way. Results will be nonsense".to_string()),
-            DecoderState::ReadyToDecodeNextHeader => return Err("Cant decode next block body, while expecting to decode the header of the previous block. Results will be nonsense".to_string()),
+            DecoderState::Failed => return Err("Cant decode next block if failed along the way. Results will be nonsense".to_string().into()),
+            DecoderState::ReadyToDecodeNextHeader => return Err("Cant decode next block body, while expecting to decode the header of the previous block. Results will be nonsense".to_string().into()),
         }
 
         match header.block_type {
@@ -50,12 +75,15 @@ impl BlockDecoder {
                 let full_reads = header.decompressed_size / BATCH_SIZE as u32;
                 let single_read_size = header.decompressed_size % BATCH_SIZE as u32;
 
-                match source.read_exact(&mut buf[0..1]) {
-                    Ok(_) => {
-                        self.internal_state = DecoderState::ReadyToDecodeNextHeader;
-                    }
-                    Err(_) => return Err("Error while reading the one RLE byte".to_string()),
+                let bytes_read = read_until_full(&mut source, &mut buf[0..1])
+                    .map_err(|e| BlockDecoderError::ReadFailed(format!("Error while reading the one RLE byte: {}", e)))?;
+                if bytes_read == 0 {
+                    return Err(BlockDecoderError::UnexpectedEof {
+                        context: EofContext::BlockContent,
+                        bytes_missing: 1,
+                    });
                 }
+                self.internal_state = DecoderState::ReadyToDecodeNextHeader;
 
                 for i in 1..BATCH_SIZE {
                     buf[i] = buf[0];
@@ -75,25 +103,21 @@ impl BlockDecoder {
                 let full_reads = header.decompressed_size / BATCH_SIZE as u32;
                 let single_read_size = header.decompressed_size % BATCH_SIZE as u32;
 
-                for _ in 0..full_reads {
-                    match source.read_exact(&mut buf[..]) {
-                        Ok(_) => {
-                            workspace.buffer.push(&buf[..]);
-                        }
-                        Err(_) => {
-                            return Err("Error while reading bytes of the raw block".to_string())
-                        }
-                    }
-                }
-
-                let smaller = &mut buf[..single_read_size as usize];
-                match source.read_exact(smaller) {
-                    Ok(_) => {
-                        workspace.buffer.push(smaller);
-                    }
-                    Err(_) => {
-                       return Err("Error while reading bytes of the raw block".to_string())
+                let mut bytes_done = 0;
+                for read_size in std::iter::repeat(BATCH_SIZE).take(full_reads as usize).chain(std::iter::once(single_read_size as usize)) {
+                    let chunk = &mut buf[..read_size];
+                    let bytes_read = read_until_full(&mut source, chunk)
+                        .map_err(|e| BlockDecoderError::ReadFailed(format!("Error while reading bytes of the raw block: {}", e)))?;
+                    if bytes_read < read_size {
+                        // keep what could be read, this is valid output even if the block is incomplete
+                        workspace.buffer.push(&chunk[..bytes_read]);
+                        return Err(BlockDecoderError::UnexpectedEof {
+                            context: EofContext::BlockContent,
+                            bytes_missing: header.decompressed_size as usize - bytes_done - bytes_read,
+                        });
                     }
+                    workspace.buffer.push(chunk);
+                    bytes_done += read_size;
                 }
 
 
@@ -102,7 +126,7 @@ impl BlockDecoder {
             }
 
             BlockType::Reserved => {
-                Err("How did you even get this. The decoder should error out if it detects a reserved-type block".to_owned())
+                Err("How did you even get this. The decoder should error out if it detects a reserved-type block".to_owned().into())
             }
 
             BlockType::Compressed => {
@@ -120,14 +144,22 @@ impl BlockDecoder {
         header: &BlockHeader,
         workspace: &mut DecoderScratch, //reuse this as often as possible. Not only if the trees are reused but also reuse the allocations when building new trees
         mut source: impl Read,
-    ) -> Result<(), String> {
-        workspace
-            .block_content_buffer
-            .resize(header.content_size as usize, 0);
-
-        match source.read_exact(workspace.block_content_buffer.as_mut_slice()) {
-            Ok(_) => { /* happy */ }
-            Err(_) => return Err("Error while reading the block content".to_owned()),
+    ) -> Result<(), BlockDecoderError> {
+        let content_size = header.content_size as usize;
+        workspace.block_content_buffer.resize(content_size, 0);
+
+        let bytes_read = read_until_full(
+            &mut source,
+            workspace.block_content_buffer.as_mut_slice(),
+        )
+        .map_err(|e| {
+            BlockDecoderError::ReadFailed(format!("Error while reading the block content: {}", e))
+        })?;
+        if bytes_read < content_size {
+            return Err(BlockDecoderError::UnexpectedEof {
+                context: truncated_section(&workspace.block_content_buffer[..bytes_read]),
+                bytes_missing: content_size - bytes_read,
+            });
         }
 
         let raw = workspace.block_content_buffer.as_slice();
@@ -142,18 +174,7 @@ impl BlockDecoder {
             );
         }
 
-        let upper_limit_for_literals = match section.compressed_size {
-            Some(x) => x as usize,
-            None => match section.ls_type {
-                LiteralsSectionType::RLE => 1,
-                LiteralsSectionType::Raw => section.regenerated_size as usize,
-                _ => panic!("Bug in this library"),
-            },
-        };
-
-        if raw.len() < upper_limit_for_literals {
-            return Err(format!("Malformed section header. Says literals would be this long: {} but there are only {} bytes left", upper_limit_for_literals, raw.len()));
-        }
+        let upper_limit_for_literals = checked_literals_size(&section, raw.len())?;
 
         let raw_literals = &raw[..upper_limit_for_literals];
         if crate::VERBOSE {
@@ -161,19 +182,32 @@ impl BlockDecoder {
         }
 
         workspace.literals_buffer.clear(); //all literals of the previous block must have been used in the sequence execution anyways. just be defensive here
+        #[cfg(feature = "profiling")]
+        let stopwatch = Stopwatch::start();
         let bytes_used_in_literals_section = decode_literals(
             &section,
             &mut workspace.huf,
             raw_literals,
             &mut workspace.literals_buffer,
         )?;
-        assert!(
-            section.regenerated_size == workspace.literals_buffer.len() as u32,
-            "Wrong number of literals: {}, Should have been: {}",
-            workspace.literals_buffer.len(),
-            section.regenerated_size
-        );
-        assert!(bytes_used_in_literals_section == upper_limit_for_literals as u32);
+        #[cfg(feature = "profiling")]
+        {
+            stopwatch.stop(&mut workspace.profile.literals_time);
+            workspace.profile.literals_bytes += workspace.literals_buffer.len() as u64;
+        }
+        if section.regenerated_size as usize != workspace.literals_buffer.len() {
+            return Err(BlockDecoderError::Invalid(format!(
+                "Wrong number of literals: {}, Should have been: {}",
+                workspace.literals_buffer.len(),
+                section.regenerated_size
+            )));
+        }
+        if bytes_used_in_literals_section as usize != upper_limit_for_literals {
+            return Err(BlockDecoderError::Invalid(format!(
+                "Literals section used {} of its {} bytes",
+                bytes_used_in_literals_section, upper_limit_for_literals
+            )));
+        }
 
         let raw = &raw[upper_limit_for_literals..];
         if crate::VERBOSE {
@@ -190,8 +224,9 @@ impl BlockDecoder {
                 raw.len()
             );
         }
+        check_sequences_fill_block(&seq_section, raw)?;
 
-        assert!(
+        internal_debug_assert!(
             u32::from(bytes_in_literals_header)
                 + bytes_used_in_literals_section
                 + u32::from(bytes_in_sequence_header)
@@ -202,46 +237,145 @@ impl BlockDecoder {
             println!("Slice for sequences: {}", raw.len());
         }
 
+        #[cfg(feature = "profiling")]
+        let output_before = workspace.buffer.total_output();
         if seq_section.num_sequences != 0 {
+            #[cfg(feature = "profiling")]
+            let stopwatch = Stopwatch::start();
             decode_sequences(
                 &seq_section,
                 raw,
                 &mut workspace.fse,
                 &mut workspace.sequences,
             )?;
+            #[cfg(feature = "profiling")]
+            {
+                stopwatch.stop(&mut workspace.profile.sequences_time);
+                workspace.profile.sequences += workspace.sequences.len() as u64;
+            }
             if crate::VERBOSE {
                 println!("Executing sequences");
             }
+            #[cfg(feature = "profiling")]
+            let stopwatch = Stopwatch::start();
             execute_sequences(workspace)?;
+            #[cfg(feature = "profiling")]
+            stopwatch.stop(&mut workspace.profile.execution_time);
         } else {
+            #[cfg(feature = "profiling")]
+            let stopwatch = Stopwatch::start();
             workspace.buffer.push(&workspace.literals_buffer);
             workspace.sequences.clear();
+            #[cfg(feature = "profiling")]
+            stopwatch.stop(&mut workspace.profile.execution_time);
+        }
+        #[cfg(feature = "profiling")]
+        {
+            workspace.profile.execution_bytes += workspace.buffer.total_output() - output_before;
         }
 
         Ok(())
     }
 
-    pub fn read_block_header(&mut self, mut r: impl Read) -> Result<(BlockHeader, u8), String> {
+    /// Calculates how many bytes the block decodes to, without producing the output.
+    /// For compressed blocks this needs to decode the sequences because only their match lengths tell how big the output is.
+    /// The fse tables are carried over between the blocks of a frame just like during decoding.
+    pub fn regenerated_size(
+        &mut self,
+        header: &BlockHeader,
+        content: &[u8],
+        fse: &mut FSEScratch,
+        sequences: &mut Vec<Sequence>,
+    ) -> Result<u64, BlockDecoderError> {
+        let literals_length = self.inspect_block_content(header, content, fse, sequences)?;
+        let match_bytes: u64 = sequences.iter().map(|seq| u64::from(seq.ml)).sum();
+        Ok(u64::from(literals_length) + match_bytes)
+    }
+
+    /// Decodes the sequences of a block into `sequences` without executing them, so no window is needed.
+    /// Returns how many literals the block has. Raw and RLE blocks have no sequences, all of their bytes count as literals.
+    /// The fse tables are carried over between the blocks of a frame just like during decoding.
+    pub fn inspect_block_content(
+        &mut self,
+        header: &BlockHeader,
+        content: &[u8],
+        fse: &mut FSEScratch,
+        sequences: &mut Vec<Sequence>,
+    ) -> Result<u32, BlockDecoderError> {
+        self.internal_state = DecoderState::ReadyToDecodeNextHeader;
+        sequences.clear();
+
+        let content_size = header.content_size as usize;
+        if content.len() < content_size {
+            let context = match header.block_type {
+                BlockType::Compressed => truncated_section(content),
+                _ => EofContext::BlockContent,
+            };
+            return Err(BlockDecoderError::UnexpectedEof {
+                context,
+                bytes_missing: content_size - content.len(),
+            });
+        }
+
+        match header.block_type {
+            BlockType::Raw | BlockType::RLE => Ok(header.decompressed_size),
+            BlockType::Reserved => Err(
+                "Reserved block occured. This is considered corruption by the documentation"
+                    .to_owned()
+                    .into(),
+            ),
+            BlockType::Compressed => {
+                let raw = &content[..content_size];
+
+                let mut section = LiteralsSection::new();
+                let bytes_in_literals_header = section.parse_from_header(raw)?;
+                let raw = &raw[bytes_in_literals_header as usize..];
+                let literals_size = checked_literals_size(&section, raw.len())?;
+                let raw = &raw[literals_size..];
+
+                let mut seq_section = SequencesHeader::new();
+                let bytes_in_sequence_header = seq_section.parse_from_header(raw)?;
+                let raw = &raw[bytes_in_sequence_header as usize..];
+                check_sequences_fill_block(&seq_section, raw)?;
+
+                if seq_section.num_sequences != 0 {
+                    decode_sequences(&seq_section, raw, fse, sequences)?;
+                }
+                Ok(section.regenerated_size)
+            }
+        }
+    }
+
+    pub fn read_block_header(
+        &mut self,
+        mut r: impl Read,
+    ) -> Result<(BlockHeader, u8), BlockDecoderError> {
         //match self.internal_state {
         //    DecoderState::ReadyToDecodeNextHeader => {/* Happy :) */},
         //    DecoderState::Failed => return Err(format!("Cant decode next block if failed along the way. Results will be nonsense")),
         //    DecoderState::ReadyToDecodeNextBody => return Err(format!("Cant decode next block header, while expecting to decode the body of the previous block. Results will be nonsense")),
         //}
 
-        match r.read_exact(&mut self.header_buffer[0..3]) {
-            Ok(_) => {}
-            Err(_) => return Err("Error while reading the block header".to_string()),
+        let bytes_read = read_until_full(&mut r, &mut self.header_buffer[0..3]).map_err(|e| {
+            BlockDecoderError::ReadFailed(format!("Error while reading the block header: {}", e))
+        })?;
+        if bytes_read < 3 {
+            return Err(BlockDecoderError::UnexpectedEof {
+                context: EofContext::BlockHeader,
+                bytes_missing: 3 - bytes_read,
+            });
         }
 
         let btype = match self.block_type() {
             Ok(t) => match t {
                 BlockType::Reserved => return Err(
                     "Reserved block occured. This is considered corruption by the documentation"
-                        .to_string(),
+                        .to_string()
+                        .into(),
                 ),
                 _ => t,
             },
-            Err(m) => return Err(m),
+            Err(m) => return Err(m.into()),
         };
 
         let block_size = self.block_content_size()?;
@@ -317,3 +451,66 @@ impl BlockDecoder {
             | (u32::from(self.header_buffer[2]) << 13)
     }
 }
+
+/// Tells which section of a compressed block was cut off, given the bytes of the block that could be read
+fn truncated_section(available: &[u8]) -> EofContext {
+    let mut section = LiteralsSection::new();
+    let header_size = match section.parse_from_header(available) {
+        Ok(size) => size as usize,
+        // not even the literals section header is complete
+        Err(_) => return EofContext::Literals,
+    };
+    if available.len() < header_size + literals_size(&section) {
+        EofContext::Literals
+    } else {
+        EofContext::Sequences
+    }
+}
+
+/// The sequences bitstream takes up the rest of the block and decode_sequences checks that all of it was used.
+/// Without sequences there is no bitstream, so there must not be any bytes after the sequences header
+fn check_sequences_fill_block(
+    seq_section: &SequencesHeader,
+    rest: &[u8],
+) -> Result<(), BlockDecoderError> {
+    if seq_section.num_sequences == 0 && !rest.is_empty() {
+        return Err(BlockDecoderError::Invalid(format!(
+            "Block has no sequences but {} bytes after the sequences header",
+            rest.len()
+        )));
+    }
+    Ok(())
+}
+
+/// How many bytes of the block the literals section takes up after its header
+fn literals_size(section: &LiteralsSection) -> usize {
+    match section.compressed_size {
+        Some(x) => x as usize,
+        None => match section.ls_type {
+            LiteralsSectionType::RLE => 1,
+            _ => section.regenerated_size as usize,
+        },
+    }
+}
+
+/// Checks that the literals section fits into the rest of the block and that it does not regenerate
+/// more than the maximum block size. Returns how many bytes the literals take up after the header
+pub(crate) fn checked_literals_size(
+    section: &LiteralsSection,
+    available: usize,
+) -> Result<usize, BlockDecoderError> {
+    if section.regenerated_size > ABSOLUTE_MAXIMUM_BLOCK_SIZE {
+        return Err(BlockDecoderError::LiteralsRegeneratedSizeTooBig {
+            size: section.regenerated_size as usize,
+            max: ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize,
+        });
+    }
+    let size = literals_size(section);
+    if size > available {
+        return Err(BlockDecoderError::LiteralsSectionTooBig {
+            declared: size,
+            available,
+        });
+    }
+    Ok(size)
+}
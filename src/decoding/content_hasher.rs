@@ -0,0 +1,50 @@
+use std::hash::Hasher;
+use twox_hash::XxHash64;
+
+/// Hashes the decoded content of a frame, see FrameDecoder::use_content_hasher.
+/// The checksum of a frame is the lower 32 bits of the XXH64 hash with seed 0 over its content, so an implementation
+/// has to calculate exactly that for the checksums to match. By default the decoder uses the XxHash64 of twox_hash
+pub trait ContentHasher {
+    /// Called with the decoded bytes in order, in chunks of any size
+    fn update(&mut self, data: &[u8]);
+    /// The hash over all bytes passed to update so far
+    fn finish(&self) -> u64;
+}
+
+impl ContentHasher for XxHash64 {
+    fn update(&mut self, data: &[u8]) {
+        self.write(data);
+    }
+
+    fn finish(&self) -> u64 {
+        Hasher::finish(self)
+    }
+}
+
+/// The hasher of a Decodebuffer, the built-in one is kept unboxed so it can be copied for Decodebuffer::current_hash
+pub(crate) enum ContentHash {
+    Builtin(XxHash64),
+    Custom(Box<dyn ContentHasher + Send + Sync>),
+}
+
+impl ContentHash {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            ContentHash::Builtin(h) => h.write(data),
+            ContentHash::Custom(h) => h.update(data),
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        match self {
+            ContentHash::Builtin(h) => Hasher::finish(h),
+            ContentHash::Custom(h) => h.finish(),
+        }
+    }
+}
+
+impl Default for ContentHash {
+    fn default() -> Self {
+        ContentHash::Builtin(XxHash64::with_seed(0))
+    }
+}
@@ -1,17 +1,112 @@
-use std::collections::VecDeque;
-use std::hash::Hasher;
-use std::mem::{self, MaybeUninit};
-use std::{io, ptr, slice};
+use std::borrow::Cow;
+use std::io;
 
-use twox_hash::XxHash64;
+use super::content_hasher::ContentHash;
+use super::linearbuffer::LinearBuffer;
+use super::ringbuffer::RingBuffer;
 
 pub struct Decodebuffer {
-    buffer: VecDeque<u8>,
+    buffer: Storage,
     pub dict_content: Vec<u8>,
 
     pub window_size: usize,
     total_output_counter: u64,
-    pub hash: XxHash64,
+    pub(crate) hash: ContentHash,
+    /// Whether drained bytes are fed into the hash. Frames without a checksum dont need it
+    pub compute_hash: bool,
+    /// The ringbuffer is kept here while a single-segment frame is decoded, so its allocation survives until the next
+    /// multi-segment frame or until it is taken out with take_ring_buffer
+    spare_ring: Option<RingBuffer>,
+    zero_new_allocations: bool,
+}
+
+/// Multi-segment frames need to wrap around in a window of window_size bytes.
+/// Single-segment frames are never bigger than their window so they can be decoded into a flat buffer.
+enum Storage {
+    Ring(RingBuffer),
+    Linear(LinearBuffer),
+}
+
+impl Storage {
+    fn new(single_segment: bool) -> Storage {
+        if single_segment {
+            Storage::Linear(LinearBuffer::new())
+        } else {
+            Storage::Ring(RingBuffer::new())
+        }
+    }
+
+    fn is_single_segment(&self) -> bool {
+        matches!(self, Storage::Linear(_))
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Storage::Ring(b) => b.len(),
+            Storage::Linear(b) => b.len(),
+        }
+    }
+
+    fn reserve(&mut self, amount: usize) -> Result<(), String> {
+        match self {
+            Storage::Ring(b) => b.reserve(amount),
+            Storage::Linear(b) => b.reserve(amount),
+        }
+    }
+
+    fn extend(&mut self, data: &[u8]) {
+        match self {
+            Storage::Ring(b) => b.extend(data),
+            Storage::Linear(b) => b.extend(data),
+        }
+    }
+
+    fn extend_from_within(&mut self, start: usize, len: usize) {
+        match self {
+            Storage::Ring(b) => b.extend_from_within(start, len),
+            Storage::Linear(b) => b.extend_from_within(start, len),
+        }
+    }
+
+    fn extend_fill(&mut self, byte: u8, len: usize) {
+        match self {
+            Storage::Ring(b) => b.extend_fill(byte, len),
+            Storage::Linear(b) => b.extend_fill(byte, len),
+        }
+    }
+
+    fn last_byte(&self) -> Option<u8> {
+        let (slice1, slice2) = self.as_slices();
+        slice2.last().or_else(|| slice1.last()).copied()
+    }
+
+    fn drain(&mut self, amount: usize) {
+        match self {
+            Storage::Ring(b) => b.drain(amount),
+            Storage::Linear(b) => b.drain(amount),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Storage::Ring(b) => b.clear(),
+            Storage::Linear(b) => b.clear(),
+        }
+    }
+
+    fn contiguous(&self) -> Cow<'_, [u8]> {
+        match self {
+            Storage::Ring(b) => b.contiguous(),
+            Storage::Linear(b) => Cow::Borrowed(b.data_slices().0),
+        }
+    }
+
+    fn as_slices(&self) -> (&[u8], &[u8]) {
+        match self {
+            Storage::Ring(b) => b.data_slices(),
+            Storage::Linear(b) => b.data_slices(),
+        }
+    }
 }
 
 impl io::Read for Decodebuffer {
@@ -30,31 +125,139 @@ impl io::Read for Decodebuffer {
 }
 
 impl Decodebuffer {
-    pub fn new(window_size: usize) -> Decodebuffer {
+    pub fn new(window_size: usize, single_segment: bool) -> Decodebuffer {
         Decodebuffer {
-            buffer: VecDeque::new(),
+            buffer: Storage::new(single_segment),
             dict_content: Vec::new(),
             window_size,
             total_output_counter: 0,
-            hash: XxHash64::with_seed(0),
+            hash: ContentHash::default(),
+            compute_hash: true,
+            spare_ring: None,
+            zero_new_allocations: false,
         }
     }
 
-    pub fn reset(&mut self, window_size: usize) {
+    /// Gets the buffer ready for the next frame. The window is reserved right away, if that allocation fails
+    /// the buffer is left empty and the error is returned
+    pub fn reset(&mut self, window_size: usize, single_segment: bool) -> Result<(), String> {
         self.window_size = window_size;
+        if self.buffer.is_single_segment() != single_segment {
+            let new_storage = match self.spare_ring.take() {
+                Some(ring) if !single_segment => Storage::Ring(ring),
+                spare => {
+                    self.spare_ring = spare;
+                    Storage::new(single_segment)
+                }
+            };
+            if let Storage::Ring(ring) = std::mem::replace(&mut self.buffer, new_storage) {
+                self.spare_ring = Some(ring);
+            }
+        }
         self.buffer.clear();
-        self.buffer.reserve(self.window_size);
+        if let Storage::Ring(ring) = &mut self.buffer {
+            ring.set_zero_new_allocations(self.zero_new_allocations);
+        }
         self.dict_content.clear();
         self.total_output_counter = 0;
-        self.hash = XxHash64::with_seed(0);
+        self.hash = ContentHash::default();
+        self.buffer.reserve(self.window_size)
     }
 
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
 
+    /// Applies RingBuffer::set_zero_new_allocations to the ring buffers in use now and later. Set this before reset,
+    /// which already reserves the window. Single-segment frames use a Vec, whose spare capacity can not be read
+    pub fn set_zero_new_allocations(&mut self, zero: bool) {
+        self.zero_new_allocations = zero;
+        if let Storage::Ring(ring) = &mut self.buffer {
+            ring.set_zero_new_allocations(zero);
+        }
+        if let Some(ring) = &mut self.spare_ring {
+            ring.set_zero_new_allocations(zero);
+        }
+    }
+
+    /// Replaces the ringbuffer with one provided by the caller, e.g. from a pool, to reuse its allocation.
+    /// This drops all bytes in the buffer, so it should only be used before decoding a frame starts.
+    /// The buffer is not grown to the window here, reset does that
+    pub fn use_ring_buffer(&mut self, mut ring: RingBuffer) {
+        ring.clear();
+        ring.set_zero_new_allocations(self.zero_new_allocations);
+        match &mut self.buffer {
+            Storage::Ring(current) => *current = ring,
+            Storage::Linear(_) => self.spare_ring = Some(ring),
+        }
+    }
+
+    /// Takes the ringbuffer out, so its allocation can be reused somewhere else. It is returned empty.
+    /// This drops all bytes in the ringbuffer, so decoding can not continue until the next reset
+    pub fn take_ring_buffer(&mut self) -> RingBuffer {
+        let mut ring = match &mut self.buffer {
+            Storage::Ring(current) => std::mem::take(current),
+            Storage::Linear(_) => self.spare_ring.take().unwrap_or_default(),
+        };
+        ring.clear();
+        ring
+    }
+
+    /// Makes the rest of the frame decode into the flat buffer even if it is a multi-segment frame, and reserves
+    /// expected_len bytes. For callers that know the size of the content, so all of it can be kept anyway.
+    /// Drops all bytes in the buffer, so this should be used right after reset. The next reset switches back
+    pub fn use_flat_buffer(&mut self, expected_len: usize) -> Result<(), String> {
+        if let Storage::Ring(ring) = std::mem::replace(&mut self.buffer, Storage::new(true)) {
+            self.spare_ring = Some(ring);
+        }
+        self.buffer.clear();
+        self.buffer.reserve(expected_len)
+    }
+
+    /// How many bytes have been decoded into this buffer since the last reset, including the ones already drained
+    pub fn total_output(&self) -> u64 {
+        self.total_output_counter
+    }
+
+    /// The XXH64 hash over all bytes decoded so far, including the ones that have not been drained yet.
+    /// The running hash itself only contains drained bytes, so the bytes still in the buffer are hashed on a copy of it.
+    /// A custom ContentHasher can not be copied, so with one this is only available once everything was drained
+    pub fn current_hash(&self) -> Option<u64> {
+        match &self.hash {
+            ContentHash::Builtin(hash) => {
+                let mut hash = ContentHash::Builtin(*hash);
+                let (slice1, slice2) = self.buffer.as_slices();
+                hash.update(slice1);
+                hash.update(slice2);
+                Some(hash.finish())
+            }
+            ContentHash::Custom(hash) if self.is_empty() => Some(hash.finish()),
+            ContentHash::Custom(_) => None,
+        }
+    }
+
+    /// All bytes currently in the buffer (the window and everything not drained yet) as one slice.
+    /// This only copies if the data wraps around the end of the ringbuffer
+    pub fn contiguous(&self) -> Cow<'_, [u8]> {
+        self.buffer.contiguous()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.buffer.is_empty()
+        self.buffer.len() == 0
+    }
+
+    /// Copies the newest target.len() bytes in the buffer into target without draining them.
+    /// Panics if the buffer holds fewer bytes than that
+    pub fn copy_newest(&self, target: &mut [u8]) {
+        let (slice1, slice2) = self.buffer.as_slices();
+        let start = slice1.len() + slice2.len() - target.len();
+        if start >= slice1.len() {
+            target.copy_from_slice(&slice2[start - slice1.len()..]);
+        } else {
+            let (from1, from2) = target.split_at_mut(slice1.len() - start);
+            from1.copy_from_slice(&slice1[start..]);
+            from2.copy_from_slice(slice2);
+        }
     }
 
     pub fn push(&mut self, data: &[u8]) {
@@ -96,59 +299,30 @@ impl Decodebuffer {
                     self.buffer.len()
                 ));
             }
+        } else if offset == 1 {
+            // repeating the last byte is a run of that byte, fill it in one go instead of copying in chunks
+            let byte = self
+                .buffer
+                .last_byte()
+                .expect("offset is at most the buffer length");
+            self.buffer.extend_fill(byte, match_length);
         } else {
             let start_idx = self.buffer.len() - offset;
 
-            self.buffer.reserve(match_length);
+            self.buffer.reserve(match_length)?;
 
-            if start_idx + match_length > self.buffer.len() {
-                //need to copy byte by byte. can be optimized more but for now lets leave it like this
-                //TODO batch whats possible
-                for x in 0..match_length {
-                    self.buffer.push_back(self.buffer[start_idx + x]);
-                }
-            } else {
-                let mut buf = [MaybeUninit::<u8>::uninit(); 4096];
-
-                // can just copy parts of the existing buffer
-
-                let mut start_idx = start_idx;
-                let mut match_length = match_length;
-                while match_length > 0 {
-                    let filled = {
-                        let (slice1, slice2) = self.buffer.as_slices();
-
-                        let slice = if slice1.len() > start_idx {
-                            &slice1[start_idx..]
-                        } else {
-                            &slice2[start_idx - slice1.len()..]
-                        };
-                        let slice = &slice[..match_length.min(slice.len()).min(buf.len())];
-
-                        // TODO: replace with MaybeUninit::write_slice once it's stable.
-                        // SAFETY: we initialize a portion of `buf` and then we return a slice
-                        // of the initialized portion of `buf`.
-                        unsafe {
-                            debug_assert!(slice.len() <= buf.len());
-
-                            ptr::copy_nonoverlapping(
-                                slice.as_ptr().cast::<MaybeUninit<u8>>(),
-                                buf.as_mut_ptr(),
-                                slice.len(),
-                            );
-                            slice::from_raw_parts(buf.as_ptr().cast::<u8>(), slice.len())
-                        }
-                    };
-
-                    self.buffer.extend(filled);
-                    start_idx += filled.len();
-                    match_length -= filled.len();
-                }
+            // If the match overlaps with the bytes it produces, copy in chunks that are already filled.
+            // Every copy doubles the amount of bytes that can be copied in the next round.
+            let mut copied = 0;
+            while copied < match_length {
+                let available = self.buffer.len() - start_idx;
+                let chunk = usize::min(available, match_length - copied);
+                self.buffer.extend_from_within(start_idx, chunk);
+                copied += chunk;
             }
-
-            self.total_output_counter += match_length as u64;
         }
 
+        self.total_output_counter += match_length as u64;
         Ok(())
     }
 
@@ -196,13 +370,23 @@ impl Decodebuffer {
 
     //drain the buffer completely
     pub fn drain(&mut self) -> Vec<u8> {
-        let new_buffer = VecDeque::with_capacity(self.buffer.capacity());
-
-        let (slice1, slice2) = self.buffer.as_slices();
-        self.hash.write(slice1);
-        self.hash.write(slice2);
+        if self.compute_hash {
+            let (slice1, slice2) = self.buffer.as_slices();
+            self.hash.update(slice1);
+            self.hash.update(slice2);
+        }
 
-        mem::replace(&mut self.buffer, new_buffer).into()
+        match &mut self.buffer {
+            Storage::Linear(b) => b.take(),
+            Storage::Ring(b) => {
+                let (slice1, slice2) = b.data_slices();
+                let mut vec = Vec::with_capacity(slice1.len() + slice2.len());
+                vec.extend_from_slice(slice1);
+                vec.extend_from_slice(slice2);
+                b.clear();
+                vec
+            }
+        }
     }
 
     pub fn drain_to_writer(&mut self, mut sink: impl io::Write) -> io::Result<usize> {
@@ -237,14 +421,14 @@ impl Decodebuffer {
         }
 
         struct DrainGuard<'a> {
-            buffer: &'a mut VecDeque<u8>,
+            buffer: &'a mut Storage,
             amount: usize,
         }
 
-        impl<'a> Drop for DrainGuard<'a> {
+        impl Drop for DrainGuard<'_> {
             fn drop(&mut self) {
                 if self.amount != 0 {
-                    self.buffer.drain(..self.amount);
+                    self.buffer.drain(self.amount);
                 }
             }
         }
@@ -260,7 +444,9 @@ impl Decodebuffer {
 
         if n1 != 0 {
             let (written1, res1) = write_bytes(&slice1[..n1]);
-            self.hash.write(&slice1[..written1]);
+            if self.compute_hash {
+                self.hash.update(&slice1[..written1]);
+            }
             drain_guard.amount += written1;
 
             // Shut clippy up. I liked the explicit if better but oh well...
@@ -270,7 +456,9 @@ impl Decodebuffer {
             // Partial writes SHOULD never happen without res1 being an error, but lets just protect against it anyways.
             if written1 == n1 && n2 != 0 {
                 let (written2, res2) = write_bytes(&slice2[..n2]);
-                self.hash.write(&slice2[..written2]);
+                if self.compute_hash {
+                    self.hash.update(&slice2[..written2]);
+                }
                 drain_guard.amount += written2;
                 // Shut clippy up. I liked the explicit if better but oh well...
                 let _: () = res2?;
@@ -288,8 +476,18 @@ impl Decodebuffer {
 fn write_all_bytes(mut sink: impl io::Write, buf: &[u8]) -> (usize, io::Result<()>) {
     let mut written = 0;
     while written < buf.len() {
-        match sink.write(buf) {
+        match sink.write(&buf[written..]) {
+            Ok(0) => {
+                return (
+                    written,
+                    Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    )),
+                )
+            }
             Ok(w) => written += w,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
             Err(e) => return (written, Err(e)),
         }
     }
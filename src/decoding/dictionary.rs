@@ -12,6 +12,12 @@ pub struct Dictionary {
 }
 
 impl Dictionary {
+    /// The raw content of the dictionary that follows the entropy tables and the offset history.
+    /// This is what primes the window of frames that use this dictionary
+    pub fn content(&self) -> &[u8] {
+        &self.dict_content
+    }
+
     /// parses the dictionary and set the tables
     /// it returns the dict_id for checking with the frame's dict_id
     pub fn decode_dict(raw: &[u8]) -> Result<Dictionary, String> {
@@ -0,0 +1,99 @@
+/// Flat buffer used for single-segment frames. Those frames never need more than Frame_Content_Size bytes of window,
+/// so the output can be kept in one Vec and back-references are plain index arithmetic without any wrapping.
+pub struct LinearBuffer {
+    buf: Vec<u8>,
+    // bytes before head have already been drained
+    head: usize,
+}
+
+impl LinearBuffer {
+    pub fn new() -> Self {
+        LinearBuffer {
+            buf: Vec::new(),
+            head: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len() - self.head
+    }
+
+    pub fn reserve(&mut self, amount: usize) -> Result<(), String> {
+        self.buf
+            .try_reserve(amount)
+            .map_err(|e| format!("Can not reserve {} more bytes: {}", amount, e))
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Appends len copies of byte
+    pub fn extend_fill(&mut self, byte: u8, len: usize) {
+        self.buf.resize(self.buf.len() + len, byte);
+    }
+
+    pub fn drain(&mut self, amount: usize) {
+        let amount = usize::min(self.len(), amount);
+        self.head += amount;
+
+        if self.head == self.buf.len() {
+            // everything was drained, reuse the allocation from the start
+            self.clear();
+        } else if self.head > self.buf.capacity() / 2 {
+            // stop the drained prefix from taking up most of the allocation
+            self.buf.drain(..self.head);
+            self.head = 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.head = 0;
+    }
+
+    pub fn data_slices(&self) -> (&[u8], &[u8]) {
+        (&self.buf[self.head..], &[])
+    }
+
+    /// Same semantics as RingBuffer::extend_from_within
+    pub fn extend_from_within(&mut self, start: usize, len: usize) {
+        if start + len > self.len() {
+            panic!("This is illegal!");
+        }
+        let start = self.head + start;
+        self.buf.extend_from_within(start..start + len);
+    }
+
+    /// Take all bytes out of the buffer. If nothing has been drained yet this does not copy
+    pub fn take(&mut self) -> Vec<u8> {
+        if self.head == 0 {
+            std::mem::take(&mut self.buf)
+        } else {
+            let vec = self.buf[self.head..].to_vec();
+            self.clear();
+            vec
+        }
+    }
+}
+
+#[test]
+fn smoke() {
+    let mut lb = LinearBuffer::new();
+
+    lb.extend(b"abcdefghijklmnop");
+    assert_eq!(lb.data_slices().0, b"abcdefghijklmnop");
+
+    lb.extend_from_within(4, 6);
+    assert_eq!(lb.data_slices().0, b"abcdefghijklmnopefghij");
+
+    lb.drain(6);
+    assert_eq!(lb.data_slices().0, b"ghijklmnopefghij");
+    assert_eq!(lb.len(), 16);
+
+    lb.extend_from_within(4, 10);
+    assert_eq!(lb.data_slices().0, b"ghijklmnopefghijklmnopefgh");
+
+    assert_eq!(lb.take(), b"ghijklmnopefghijklmnopefgh");
+    assert_eq!(lb.len(), 0);
+}
@@ -1,9 +1,37 @@
 use super::super::blocks::literals_section::LiteralsSection;
 use super::super::blocks::literals_section::LiteralsSectionType;
 use super::bit_reader_reverse::BitReaderReversed;
+use super::block_decoder::checked_literals_size;
 use super::scratch::HuffmanScratch;
+use crate::errors::BlockDecoderError;
 use crate::huff0::HuffmanDecoder;
 
+/// Decodes a whole literals section, from the start of its header on, like it is found at the start of a compressed block.
+/// This is the huffman stage of the decoder on its own, e.g. for testing it in isolation.
+///
+/// Returns the literals and how many bytes of raw belong to the section, the sequences section starts there.
+/// Treeless sections reuse the huffman table that an earlier section built in scratch
+pub fn decode_literals_section(
+    raw: &[u8],
+    scratch: &mut HuffmanScratch,
+) -> Result<(Vec<u8>, usize), BlockDecoderError> {
+    let mut section = LiteralsSection::new();
+    let header_size = section.parse_from_header(raw)?;
+    let raw = &raw[header_size as usize..];
+    let literals_size = checked_literals_size(&section, raw.len())?;
+
+    let mut literals = Vec::new();
+    decode_literals(&section, scratch, &raw[..literals_size], &mut literals)?;
+    if literals.len() != section.regenerated_size as usize {
+        return Err(BlockDecoderError::Invalid(format!(
+            "Literals section decoded to {} bytes but its header says {}",
+            literals.len(),
+            section.regenerated_size
+        )));
+    }
+    Ok((literals, header_size as usize + literals_size))
+}
+
 pub fn decode_literals(
     section: &LiteralsSection,
     scratch: &mut HuffmanScratch,
@@ -16,7 +44,10 @@ pub fn decode_literals(
             Ok(section.regenerated_size)
         }
         LiteralsSectionType::RLE => {
-            target.resize(target.len() + section.regenerated_size as usize, source[0]);
+            let byte = *source
+                .first()
+                .ok_or_else(|| "RLE literals section is missing the byte to repeat".to_owned())?;
+            target.resize(target.len() + section.regenerated_size as usize, byte);
             Ok(1)
         }
         LiteralsSectionType::Compressed | LiteralsSectionType::Treeless => {
@@ -52,12 +83,17 @@ fn decompress_literals(
             if crate::VERBOSE {
                 println!("Built huffman table using {} bytes", bytes_read);
             }
-        }
-        LiteralsSectionType::Treeless => {
-            if scratch.table.max_num_bits == 0 {
-                return Err("Tried to reuse huffman table but it was never initialized".to_owned());
+            if bytes_read as usize >= source.len() {
+                return Err(format!(
+                    "Huffman tree description takes up {} of the {} bytes of the literals section, nothing is left for the streams",
+                    bytes_read,
+                    source.len()
+                ));
             }
         }
+        LiteralsSectionType::Treeless if scratch.table.max_num_bits == 0 => {
+            return Err("Tried to reuse huffman table but it was never initialized".to_owned());
+        }
         _ => { /* nothing to do, huffman tree has been provided by previous block */ }
     }
 
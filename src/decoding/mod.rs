@@ -1,11 +1,17 @@
 pub mod bit_reader;
 pub mod bit_reader_reverse;
 pub mod block_decoder;
+pub mod content_hasher;
 pub mod decodebuffer;
+#[cfg(feature = "dictionary")]
 pub mod dictionary;
+mod linearbuffer;
 pub mod literals_section_decoder;
+#[cfg(feature = "profiling")]
+pub mod profile;
+pub mod ringbuffer;
 #[allow(dead_code)]
 pub mod scratch;
 pub mod sequence_execution;
 pub mod sequence_section_decoder;
-mod ringbuffer;
\ No newline at end of file
+pub mod source;
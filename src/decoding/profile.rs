@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+/// Where the time went while decoding the current frame, see FrameDecoder::profile.
+/// Only collected with the "profiling" feature, without it none of the clocks are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeProfile {
+    /// Decoding the literals sections of compressed blocks, which is the huffman decoding for compressed literals
+    pub literals_time: Duration,
+    /// How many literals the literals sections decoded to
+    pub literals_bytes: u64,
+    /// Decoding the sequences of compressed blocks with their fse tables
+    pub sequences_time: Duration,
+    /// How many sequences were decoded
+    pub sequences: u64,
+    /// Executing the sequences, which copies the literals and the matches into the window.
+    /// Blocks without sequences only copy their literals
+    pub execution_time: Duration,
+    /// How many bytes compressed blocks decoded to
+    pub execution_bytes: u64,
+    /// Copying raw blocks and filling rle blocks
+    pub raw_rle_time: Duration,
+    /// How many bytes raw and rle blocks decoded to
+    pub raw_rle_bytes: u64,
+}
+
+impl DecodeProfile {
+    /// The time of all stages together
+    pub fn total_time(&self) -> Duration {
+        self.literals_time + self.sequences_time + self.execution_time + self.raw_rle_time
+    }
+
+    /// How many bytes were decoded, from compressed blocks and from raw and rle blocks
+    pub fn total_bytes(&self) -> u64 {
+        self.execution_bytes + self.raw_rle_bytes
+    }
+}
+
+/// Measures the time until it is stopped, to add it to one of the stages
+pub(crate) struct Stopwatch(Instant);
+
+impl Stopwatch {
+    pub(crate) fn start() -> Stopwatch {
+        Stopwatch(Instant::now())
+    }
+
+    pub(crate) fn stop(self, time: &mut Duration) {
+        *time += self.0.elapsed();
+    }
+}
@@ -1,83 +1,176 @@
-use std::{alloc::Layout, ptr::slice_from_raw_parts};
-
+use std::{
+    alloc::Layout,
+    borrow::Cow,
+    ptr::{slice_from_raw_parts, NonNull},
+};
+
+/// A growable FIFO byte buffer that wraps around the end of its allocation, which the decoder uses for the window.
+///
+/// Bytes are appended with extend (or io::Write) and removed from the front with drain. Because the content can wrap
+/// around, it is exposed as two slices by data_slices, the first one holding the older bytes.
+///
+/// The unsafe code of this buffer is run under Miri in CI, by its own tests and by some decodes that use it.
 pub struct RingBuffer {
     buf: *mut u8,
     layout: Layout,
     cap: usize,
     head: usize,
     tail: usize,
+    zero_new_allocations: bool,
 }
 
 impl RingBuffer {
+    /// Creates an empty buffer, this does not allocate
     pub fn new() -> Self {
         RingBuffer {
-            buf: std::ptr::null_mut(),
+            // not null, even empty slices made from it must have a non-null, aligned pointer
+            buf: NonNull::dangling().as_ptr(),
             layout: Layout::new::<u8>(),
             cap: 0,
             head: 0,
             tail: 0,
+            zero_new_allocations: false,
         }
     }
 
+    /// If set, allocations made when the buffer grows are zeroed, so they never hold what the memory contained
+    /// before. The bytes outside of data_slices are never read either way, this is for callers that do not want
+    /// leftover heap contents in their buffers at all. Off by default because it costs a memset of every allocation
+    pub fn set_zero_new_allocations(&mut self, zero: bool) {
+        self.zero_new_allocations = zero;
+    }
+
+    /// How many bytes fit into the allocation without growing it
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// How many bytes are in the buffer
     pub fn len(&self) -> usize {
         let (x, y) = self.data_slice_lengths();
         x + y
     }
 
-    pub fn reserve(&mut self, amount: usize) {
+    pub fn is_empty(&self) -> bool {
+        self.head == self.tail
+    }
+
+    /// Makes sure amount more bytes can be appended without growing the allocation again.
+    /// Returns an error instead of panicking or aborting if the capacity would overflow or the allocation fails,
+    /// the buffer is left untouched then
+    pub fn reserve(&mut self, amount: usize) -> Result<(), String> {
         if self.cap - self.len() > amount {
-            return;
+            return Ok(());
         }
 
         // TODO make this the next biggest 2^x?
-        let new_cap = usize::max(self.cap * 2, self.cap + amount + 1);
-        let new_layout = Layout::array::<u8>(new_cap).unwrap();
-        let new_buf = unsafe { std::alloc::alloc(new_layout) };
-
-        if new_buf != std::ptr::null_mut() {
-            if self.cap > 0 {
-                let ((s1_ptr, s1_len), (s2_ptr, s2_len)) = self.data_slice_parts();
-                unsafe {
-                    new_buf.copy_from_nonoverlapping(s1_ptr, s1_len);
-                    new_buf.add(s1_len).copy_from_nonoverlapping(s2_ptr, s2_len);
-                    std::alloc::dealloc(self.buf, self.layout);
-                }
-                self.tail = s1_len + s2_len;
-                self.head = 0;
+        let needed_cap = self
+            .cap
+            .checked_add(amount)
+            .and_then(|cap| cap.checked_add(1))
+            .ok_or_else(|| format!("Can not reserve {} more bytes, capacity overflow", amount))?;
+        let new_cap = usize::max(self.cap.saturating_mul(2), needed_cap);
+        let new_layout = Layout::array::<u8>(new_cap).map_err(|_| {
+            format!(
+                "Can not reserve {} more bytes, a capacity of {} is too big",
+                amount, new_cap
+            )
+        })?;
+        // SAFETY: new_layout has a non-zero size because new_cap is at least 1
+        let new_buf = unsafe {
+            if self.zero_new_allocations {
+                std::alloc::alloc_zeroed(new_layout)
+            } else {
+                std::alloc::alloc(new_layout)
             }
-            self.buf = new_buf;
-            self.layout = new_layout;
-            self.cap = new_cap;
+        };
+
+        if new_buf.is_null() {
+            return Err(format!(
+                "Can not reserve {} more bytes, the allocation of {} bytes failed",
+                amount, new_cap
+            ));
         }
-        eprintln!("reserve cap {} head {} tail {}", self.cap, self.head, self.tail);
+
+        if self.cap > 0 {
+            let ((s1_ptr, s1_len), (s2_ptr, s2_len)) = self.data_slice_parts();
+            unsafe {
+                new_buf.copy_from_nonoverlapping(s1_ptr, s1_len);
+                new_buf.add(s1_len).copy_from_nonoverlapping(s2_ptr, s2_len);
+                std::alloc::dealloc(self.buf, self.layout);
+            }
+            self.tail = s1_len + s2_len;
+            self.head = 0;
+        }
+        self.buf = new_buf;
+        self.layout = new_layout;
+        self.cap = new_cap;
+        Ok(())
     }
 
+    /// Appends data to the end of the buffer, growing it if needed.
+    /// Panics if growing fails, like Vec::extend_from_slice. Use reserve first to handle that as an error
     pub fn extend(&mut self, data: &[u8]) {
         let len = data.len();
         let ptr = data.as_ptr();
 
-        self.reserve(len);
+        self.grow(len);
+        let ((f1_ptr, f1_len), (f2_ptr, f2_len)) = self.free_slice_parts();
+        internal_debug_assert!(f1_len + f2_len >= len, "{} + {} < {}", f1_len, f2_len, len);
+
+        if len <= f1_len {
+            // fast path, the data fits in front of the end of the allocation (or the head)
+            unsafe {
+                f1_ptr.copy_from_nonoverlapping(ptr, len);
+            }
+        } else {
+            let in_f2 = len - f1_len;
+            unsafe {
+                f1_ptr.copy_from_nonoverlapping(ptr, f1_len);
+                f2_ptr.copy_from_nonoverlapping(ptr.add(f1_len), in_f2);
+            }
+        }
+        self.tail = (self.tail + len) % self.cap;
+    }
+
+    /// Appends len copies of byte, like a memset into the free space. Panics if growing fails, like extend
+    pub fn extend_fill(&mut self, byte: u8, len: usize) {
+        self.grow(len);
         let ((f1_ptr, f1_len), (f2_ptr, f2_len)) = self.free_slice_parts();
-        debug_assert!(f1_len + f2_len >= len, "{} + {} < {}", f1_len, f2_len, len);
+        internal_debug_assert!(f1_len + f2_len >= len, "{} + {} < {}", f1_len, f2_len, len);
 
         let in_f1 = usize::min(len, f1_len);
         let in_f2 = len - in_f1;
 
-        debug_assert!(in_f1 + in_f2 == len);
-
         unsafe {
-            f1_ptr.copy_from_nonoverlapping(ptr, in_f1);
-            f2_ptr.copy_from_nonoverlapping(ptr.add(in_f1), in_f2);
+            f1_ptr.write_bytes(byte, in_f1);
+            f2_ptr.write_bytes(byte, in_f2);
         }
         self.tail = (self.tail + len) % self.cap;
-        eprintln!("extend cap {} head {} tail {}", self.cap, self.head, self.tail);
     }
 
+    /// reserve for the operations that can not return an error
+    fn grow(&mut self, amount: usize) {
+        if let Err(e) = self.reserve(amount) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Removes the oldest amount bytes from the buffer, or all bytes if there are fewer
     pub fn drain(&mut self, amount: usize) {
+        if self.cap == 0 {
+            return;
+        }
         let amount = usize::min(self.len(), amount);
         self.head = (self.head + amount) % self.cap;
     }
 
+    /// Forget all bytes in the buffer but keep the allocation for reuse
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.tail = 0;
+    }
+
     fn data_slice_lengths(&self) -> (usize, usize) {
         let len_after_head;
         let len_to_tail;
@@ -101,8 +194,18 @@ impl RingBuffer {
             (self.buf, len_to_tail),
         )
     }
+
+    /// The bytes in the buffer from oldest to newest. The second slice is only non-empty if the data wraps around.
+    ///
+    /// This is the way to look at the content without copying it. The slices borrow the buffer, so they have to be
+    /// dropped before any call that changes it (extend, drain, reserve, clear, ...): those can move the data or
+    /// reallocate, after which the slices would point at the wrong bytes or freed memory.
     pub fn data_slices(&self) -> (&[u8], &[u8]) {
         let (s1, s2) = self.data_slice_parts();
+        // SAFETY: both parts lie inside of the allocation because head and tail are smaller than cap, or they are empty
+        // slices of the dangling pointer if nothing was allocated yet. Every byte between head and tail was written by
+        // extend, extend_fill or extend_from_within, so they are initialized. Nothing can write to them while
+        // the returned slices borrow self, all writes need &mut self
         unsafe {
             let s1 = &*slice_from_raw_parts(s1.0, s1.1);
             let s2 = &*slice_from_raw_parts(s2.0, s2.1);
@@ -110,6 +213,19 @@ impl RingBuffer {
         }
     }
 
+    /// All bytes in the buffer as one slice. This only copies if the data wraps around the end of the allocation
+    pub fn contiguous(&self) -> Cow<'_, [u8]> {
+        let (s1, s2) = self.data_slices();
+        if s2.is_empty() {
+            Cow::Borrowed(s1)
+        } else {
+            let mut vec = Vec::with_capacity(s1.len() + s2.len());
+            vec.extend_from_slice(s1);
+            vec.extend_from_slice(s2);
+            Cow::Owned(vec)
+        }
+    }
+
     fn free_slice_lengths(&self) -> (usize, usize) {
         let len_to_head;
         let len_after_tail;
@@ -125,6 +241,37 @@ impl RingBuffer {
         (len_to_head, len_after_tail)
     }
 
+    /// Asserts the invariants all the index arithmetic relies on
+    #[cfg(test)]
+    fn check_invariants(&self) {
+        if self.cap == 0 {
+            assert_eq!((self.head, self.tail), (0, 0));
+            return;
+        }
+        assert!(
+            self.head < self.cap,
+            "head {} >= cap {}",
+            self.head,
+            self.cap
+        );
+        assert!(
+            self.tail < self.cap,
+            "tail {} >= cap {}",
+            self.tail,
+            self.cap
+        );
+        assert_eq!(self.layout.size(), self.cap);
+
+        let (s1, s2) = self.data_slices();
+        assert_eq!(self.len(), s1.len() + s2.len());
+        assert_eq!(self.is_empty(), s1.is_empty() && s2.is_empty());
+        // head == tail means empty, so one byte always stays free
+        assert!(self.len() < self.cap);
+
+        let (free1, free2) = self.free_slice_lengths();
+        assert_eq!(free1 + free2 + self.len(), self.cap);
+    }
+
     fn free_slice_parts(&self) -> ((*mut u8, usize), (*mut u8, usize)) {
         let (len_to_head, len_after_tail) = self.free_slice_lengths();
 
@@ -134,17 +281,20 @@ impl RingBuffer {
         )
     }
 
-    fn extend_from_within(&mut self, start: usize, len: usize) {
+    /// Appends len bytes starting from start (counted from the oldest byte in the buffer) to the end of the buffer.
+    /// The source range must already be filled, so overlapping repeats have to be split up by the caller.
+    /// Panics if growing fails, like extend
+    pub fn extend_from_within(&mut self, start: usize, len: usize) {
         if start + len > self.len() {
             panic!("This is illegal!");
         }
 
-        self.reserve(len);
+        self.grow(len);
 
         // data slices in raw parts
         let ((s1_ptr, s1_len), (s2_ptr, s2_len)) = self.data_slice_parts();
 
-        debug_assert!(len <= s1_len + s2_len, "{} > {} + {}", len, s1_len, s2_len);
+        internal_debug_assert!(len <= s1_len + s2_len, "{} > {} + {}", len, s1_len, s2_len);
 
         // calc the actually wanted slices in raw parts
         let start_in_s1 = usize::min(s1_len, start);
@@ -152,23 +302,30 @@ impl RingBuffer {
         let m1_ptr = unsafe { s1_ptr.add(start_in_s1) };
         let m1_len = end_in_s1 - start_in_s1;
 
-        debug_assert!(end_in_s1 <= s1_len);
-        debug_assert!(start_in_s1 <= s1_len);
+        internal_debug_assert!(end_in_s1 <= s1_len);
+        internal_debug_assert!(start_in_s1 <= s1_len);
 
         let start_in_s2 = start.saturating_sub(s1_len);
         let end_in_s2 = start_in_s2 + (len - m1_len);
         let m2_ptr = unsafe { s2_ptr.add(start_in_s2) };
         let m2_len = end_in_s2 - start_in_s2;
 
-        debug_assert!(start_in_s2 <= s2_len);
-        debug_assert!(end_in_s2 <= s2_len);
+        internal_debug_assert!(start_in_s2 <= s2_len);
+        internal_debug_assert!(end_in_s2 <= s2_len);
 
-        debug_assert_eq!(len, m1_len + m2_len);
+        internal_debug_assert!(len == m1_len + m2_len);
 
         // the free slices, must hold: f1_len + f2_len >= m1_len + m2_len
         let ((f1_ptr, f1_len), (f2_ptr, f2_len)) = self.free_slice_parts();
 
-        debug_assert!(f1_len + f2_len >= m1_len + m2_len);
+        // checked in release builds too, because the copies below write len bytes into the free slices.
+        // Together with the check of start + len at the top this keeps the copies in bounds
+        assert!(
+            f1_len + f2_len >= len,
+            "Not enough free space after reserve: {} < {}",
+            f1_len + f2_len,
+            len
+        );
 
         // calc how many from where bytes go where
         let m1_in_f1 = usize::min(m1_len, f1_len);
@@ -176,14 +333,36 @@ impl RingBuffer {
         let m2_in_f1 = usize::min(f1_len - m1_in_f1, m2_len);
         let m2_in_f2 = m2_len - m2_in_f1;
 
-        debug_assert_eq!(m1_len, m1_in_f1 + m1_in_f2);
-        debug_assert_eq!(m2_len, m2_in_f1 + m2_in_f2);
-        debug_assert!(f1_len >= m1_in_f1 + m2_in_f1);
-        debug_assert!(f2_len >= m1_in_f2 + m2_in_f2);
-        debug_assert_eq!(len, m1_in_f1 + m2_in_f1 + m1_in_f2 + m2_in_f2);
-
-        debug_assert!((m1_in_f2 > 0) ^ (m2_in_f1 > 0) || (m1_in_f2 == 0 && m2_in_f1 == 0));
-
+        internal_debug_assert!(m1_len == m1_in_f1 + m1_in_f2);
+        internal_debug_assert!(m2_len == m2_in_f1 + m2_in_f2);
+        internal_debug_assert!(f1_len >= m1_in_f1 + m2_in_f1);
+        internal_debug_assert!(f2_len >= m1_in_f2 + m2_in_f2);
+        internal_debug_assert!(len == m1_in_f1 + m2_in_f1 + m1_in_f2 + m2_in_f2);
+
+        internal_debug_assert!((m1_in_f2 > 0) ^ (m2_in_f1 > 0) || (m1_in_f2 == 0 && m2_in_f1 == 0));
+
+        // the sources are in the data slices and the destinations in the free slices, which never share a byte
+        internal_debug_assert!(!overlaps(m1_ptr, m1_in_f1, f1_ptr, m1_in_f1));
+        internal_debug_assert!(!overlaps(
+            m2_ptr,
+            m2_in_f1,
+            f1_ptr.wrapping_add(m1_in_f1),
+            m2_in_f1
+        ));
+        internal_debug_assert!(!overlaps(
+            m1_ptr.wrapping_add(m1_in_f1),
+            m1_in_f2,
+            f2_ptr,
+            m1_in_f2
+        ));
+        internal_debug_assert!(!overlaps(
+            m2_ptr.wrapping_add(m2_in_f1),
+            m2_in_f2,
+            f2_ptr.wrapping_add(m1_in_f2),
+            m2_in_f2
+        ));
+
+        // SAFETY: all ranges are inside of the allocation (see the checks above) and no source overlaps its destination
         unsafe {
             f1_ptr.copy_from_nonoverlapping(m1_ptr, m1_in_f1);
             f1_ptr
@@ -197,53 +376,357 @@ impl RingBuffer {
         }
 
         self.tail = (self.tail + len) % self.cap;
-        eprintln!("extend_within cap {} head {} tail {}", self.cap, self.head, self.tail);
+    }
+}
+
+/// Whether the len_a bytes at a share a byte with the len_b bytes at b. Empty ranges overlap nothing
+fn overlaps(a: *const u8, len_a: usize, b: *const u8, len_b: usize) -> bool {
+    let (a, b) = (a as usize, b as usize);
+    len_a > 0 && len_b > 0 && a < b + len_b && b < a + len_a
+}
+
+// SAFETY: The RingBuffer uniquely owns the allocation behind buf, like a Vec<u8> does. No other pointer to it
+// exists, so moving the RingBuffer to another thread moves the only access to the allocation with it
+unsafe impl Send for RingBuffer {}
+
+// SAFETY: There is no interior mutability, every method that writes to the allocation takes &mut self.
+// Through &self the bytes can only be read, so sharing references between threads is fine, same as for &Vec<u8>
+unsafe impl Sync for RingBuffer {}
+
+impl Default for RingBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Appends everything written, so a RingBuffer can be the target of FrameDecoder::collect_to_writer
+impl std::io::Write for RingBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+
+        // SAFETY: buf was allocated with exactly this layout in reserve() and is not used after this
+        unsafe {
+            std::alloc::dealloc(self.buf, self.layout);
+        }
     }
 }
 
 #[test]
 fn smoke() {
     let mut rb = RingBuffer::new();
+    rb.check_invariants();
 
     rb.extend(b"abcdefghijklmnop");
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"abcdefghijklmnop");
     assert_eq!(rb.data_slices().1, b"");
 
-
     rb.extend_from_within(4, 6);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"abcdefghijklmnopefghij");
     assert_eq!(rb.data_slices().1, b"");
 
-
     rb.drain(6);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"ghijklmnopefghij");
     assert_eq!(rb.data_slices().1, b"");
 
     rb.extend_from_within(4, 6);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"ghijklmnopefghijklmnop");
     assert_eq!(rb.data_slices().1, b"");
 
     rb.extend_from_within(4, 10);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"ghijklmnopefghijklmnopklmnop");
     assert_eq!(rb.data_slices().1, b"efgh");
 
     rb.extend(b"1");
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"ghijklmnopefghijklmnopklmnop");
     assert_eq!(rb.data_slices().1, b"efgh1");
 
     rb.drain(9);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"pefghijklmnopklmnop");
     assert_eq!(rb.data_slices().1, b"efgh1");
 
     rb.extend(b"234567890");
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"pefghijklmnopklmnop");
     assert_eq!(rb.data_slices().1, b"efgh1234567890");
 
     rb.drain(11);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"opklmnop");
     assert_eq!(rb.data_slices().1, b"efgh1234567890");
 
     rb.extend_from_within(12, 10);
+    rb.check_invariants();
     assert_eq!(rb.data_slices().0, b"opklmnop");
     assert_eq!(rb.data_slices().1, b"efgh12345678901234567890");
+
+    // extend wrapping around the end of the buffer
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    rb.extend(b"abcdefghij");
+    rb.check_invariants();
+    rb.drain(8);
+    rb.check_invariants();
+    rb.extend(b"12345");
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"ij1");
+    assert_eq!(rb.data_slices().1, b"2345");
+
+    // exactly filling the space up to the end of the buffer only uses the first free slice
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    rb.extend(b"abcdefghij");
+    rb.check_invariants();
+    rb.drain(5);
+    rb.check_invariants();
+    rb.extend(b"1");
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"fghij1");
+    assert_eq!(rb.data_slices().1, b"");
+    rb.extend(b"2");
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"fghij1");
+    assert_eq!(rb.data_slices().1, b"2");
+
+    // fill wrapping around the end of the buffer
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    rb.extend(b"abcdefghij");
+    rb.check_invariants();
+    rb.drain(8);
+    rb.check_invariants();
+    rb.extend_fill(b'x', 5);
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"ijx");
+    assert_eq!(rb.data_slices().1, b"xxxx");
+}
+
+#[test]
+fn extend_from_within_near_full() {
+    // every head position, fill level and source range in a buffer of 8 bytes, up to the copies that leave
+    // exactly the one byte free that always has to stay free. Small enough to run under miri
+    let mut rb = RingBuffer::new();
+    rb.reserve(7).unwrap();
+    let cap = rb.capacity();
+    assert_eq!(cap, 8);
+    for head in 0..cap {
+        for fill in 0..cap {
+            for start in 0..fill {
+                for len in 1..=usize::min(fill - start, cap - 1 - fill) {
+                    rb.clear();
+                    rb.extend_fill(0, head);
+                    rb.drain(head);
+                    let data: Vec<u8> = (1..=fill as u8).collect();
+                    rb.extend(&data);
+                    rb.check_invariants();
+
+                    rb.extend_from_within(start, len);
+                    rb.check_invariants();
+                    assert_eq!(
+                        rb.capacity(),
+                        cap,
+                        "grew for {} {} {} {}",
+                        head,
+                        fill,
+                        start,
+                        len
+                    );
+                    let mut expected = data.clone();
+                    expected.extend_from_slice(&data[start..start + len]);
+                    assert_eq!(rb.contiguous(), &expected[..]);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn reserve_exact_fit() {
+    // after reserve(amount) exactly amount bytes fit without growing, wherever the data starts
+    let cap = 16;
+    for head in 0..cap {
+        for fill in 0..cap {
+            let mut rb = RingBuffer::new();
+            rb.reserve(cap - 1).unwrap();
+            assert_eq!(rb.capacity(), cap);
+            let amount = cap - 1 - fill;
+            rb.extend_fill(0, head);
+            rb.drain(head);
+            rb.extend_fill(b'a', fill);
+            rb.reserve(amount).unwrap();
+            assert_eq!(rb.capacity(), cap);
+
+            let data: Vec<u8> = (0..amount as u8).collect();
+            rb.extend(&data);
+            rb.check_invariants();
+            assert_eq!(rb.capacity(), cap, "grew for {} {}", head, fill);
+            assert_eq!(rb.len(), cap - 1);
+            assert_eq!(&rb.contiguous()[fill..], &data[..]);
+
+            // one more byte has to grow it
+            rb.reserve(1).unwrap();
+            rb.check_invariants();
+            assert!(rb.capacity() > cap);
+        }
+    }
+}
+
+#[test]
+fn data_slices_after_operations() {
+    use std::collections::VecDeque;
+
+    // every operation that changes the buffer, compared against a VecDeque. The buffer starts small so it wraps
+    // around and grows while the data is wrapped. Small enough to run under miri
+    let mut rb = RingBuffer::new();
+    let mut expected = VecDeque::new();
+    let check = |rb: &RingBuffer, expected: &VecDeque<u8>| {
+        rb.check_invariants();
+        let (s1, s2) = rb.data_slices();
+        let (e1, e2) = expected.as_slices();
+        let mut joined = e1.to_vec();
+        joined.extend_from_slice(e2);
+        assert_eq!([s1, s2].concat(), joined);
+        assert!(s2.is_empty() || rb.tail < rb.head);
+        !s2.is_empty()
+    };
+    check(&rb, &expected);
+
+    let mut wrapped = false;
+    let mut next = 0u8;
+    for round in 0..40usize {
+        let data: Vec<u8> = (0..round % 7 + 1)
+            .map(|_| {
+                next = next.wrapping_add(1);
+                next
+            })
+            .collect();
+        rb.extend(&data);
+        expected.extend(&data);
+        wrapped |= check(&rb, &expected);
+
+        if round % 3 == 0 {
+            rb.extend_fill(b'f', round % 5);
+            expected.extend(std::iter::repeat(b'f').take(round % 5));
+            wrapped |= check(&rb, &expected);
+        }
+        if rb.len() > 2 {
+            let start = round % (rb.len() - 1);
+            let len = usize::min(rb.len() - start, 4);
+            rb.extend_from_within(start, len);
+            let copied: Vec<u8> = expected.range(start..start + len).copied().collect();
+            expected.extend(copied);
+            wrapped |= check(&rb, &expected);
+        }
+        let amount = usize::min(rb.len(), round % 9);
+        rb.drain(amount);
+        expected.drain(..amount);
+        wrapped |= check(&rb, &expected);
+        if round % 10 == 9 {
+            rb.reserve(round).unwrap();
+            wrapped |= check(&rb, &expected);
+        }
+    }
+    assert!(wrapped);
+
+    rb.clear();
+    expected.clear();
+    check(&rb, &expected);
+    rb.extend(b"after clear");
+    assert_eq!(rb.data_slices(), (&b"after clear"[..], &b""[..]));
+}
+
+#[test]
+fn zero_new_allocations() {
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    rb.set_zero_new_allocations(true);
+    rb.extend(b"abcdefghij");
+    rb.check_invariants();
+    rb.drain(8);
+    rb.check_invariants();
+    rb.reserve(100).unwrap();
+    rb.check_invariants();
+
+    // reading the whole allocation is only fine because it was zeroed, miri reports it otherwise
+    let allocation = unsafe { &*slice_from_raw_parts(rb.buf.cast_const(), rb.cap) };
+    assert_eq!(&allocation[..2], b"ij");
+    assert!(allocation[2..].iter().all(|&b| b == 0));
+    assert_eq!(rb.data_slices(), (&b"ij"[..], &b""[..]));
+}
+
+#[test]
+fn reserve_overflow() {
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    rb.extend(b"abc");
+    rb.check_invariants();
+    assert!(rb.reserve(usize::MAX).is_err());
+    assert!(rb.reserve(isize::MAX as usize).is_err());
+    // a failed reserve leaves the buffer untouched
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"abc");
+    rb.reserve(100).unwrap();
+    rb.check_invariants();
+    assert!(rb.capacity() > 103);
+}
+
+// miri reports an allocation this big as an error instead of returning null
+#[cfg_attr(miri, ignore)]
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn reserve_alloc_failure() {
+    let mut rb = RingBuffer::new();
+    rb.extend(b"abc");
+    // a valid layout, but far more than the address space of any current 64 bit machine
+    let err = rb.reserve(1 << 62).unwrap_err();
+    assert!(err.contains("allocation"), "{}", err);
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().0, b"abc");
+}
+
+#[test]
+fn contiguous() {
+    let mut rb = RingBuffer::new();
+    rb.check_invariants();
+    assert!(matches!(rb.contiguous(), Cow::Borrowed(b"")));
+
+    rb.extend(b"abcdefghijklmnop");
+    rb.check_invariants();
+    rb.extend_from_within(4, 6);
+    rb.check_invariants();
+    rb.drain(6);
+    rb.check_invariants();
+    assert!(matches!(
+        rb.contiguous(),
+        Cow::Borrowed(b"ghijklmnopefghij")
+    ));
+
+    rb.extend_from_within(4, 6);
+    rb.check_invariants();
+    rb.extend_from_within(4, 10);
+    rb.check_invariants();
+    assert_eq!(rb.data_slices().1, b"efgh");
+    match rb.contiguous() {
+        Cow::Owned(data) => assert_eq!(data, b"ghijklmnopefghijklmnopklmnopefgh"),
+        Cow::Borrowed(_) => panic!("Wrapped data can not be borrowed"),
+    }
 }
@@ -1,6 +1,9 @@
 use super::super::blocks::sequence_section::Sequence;
 use super::decodebuffer::Decodebuffer;
+#[cfg(feature = "dictionary")]
 use crate::decoding::dictionary::Dictionary;
+#[cfg(feature = "profiling")]
+use crate::decoding::profile::DecodeProfile;
 use crate::fse::FSETable;
 use crate::huff0::HuffmanTable;
 
@@ -9,14 +12,19 @@ pub struct DecoderScratch {
     pub fse: FSEScratch,
     pub buffer: Decodebuffer,
     pub offset_hist: [u32; 3],
+    /// The biggest offset of a match executed since the last reset
+    pub max_offset: u32,
 
     pub literals_buffer: Vec<u8>,
     pub sequences: Vec<Sequence>,
     pub block_content_buffer: Vec<u8>,
+    /// Where the time went since the last reset
+    #[cfg(feature = "profiling")]
+    pub profile: DecodeProfile,
 }
 
 impl DecoderScratch {
-    pub fn new(window_size: usize) -> DecoderScratch {
+    pub fn new(window_size: usize, single_segment: bool) -> DecoderScratch {
         DecoderScratch {
             huf: HuffmanScratch {
                 table: HuffmanTable::new(),
@@ -29,22 +37,29 @@ impl DecoderScratch {
                 match_lengths: FSETable::new(),
                 ml_rle: None,
             },
-            buffer: Decodebuffer::new(window_size),
+            buffer: Decodebuffer::new(window_size, single_segment),
             offset_hist: [1, 4, 8],
+            max_offset: 0,
 
             block_content_buffer: Vec::new(),
             literals_buffer: Vec::new(),
             sequences: Vec::new(),
+            #[cfg(feature = "profiling")]
+            profile: DecodeProfile::default(),
         }
     }
 
-    pub fn reset(&mut self, window_size: usize) {
+    /// Fails if the window of the next frame can not be allocated
+    pub fn reset(&mut self, window_size: usize, single_segment: bool) -> Result<(), String> {
         self.offset_hist = [1, 4, 8];
+        self.max_offset = 0;
         self.literals_buffer.clear();
         self.sequences.clear();
         self.block_content_buffer.clear();
-
-        self.buffer.reset(window_size);
+        #[cfg(feature = "profiling")]
+        {
+            self.profile = DecodeProfile::default();
+        }
 
         self.fse.literal_lengths.reset();
         self.fse.match_lengths.reset();
@@ -54,8 +69,11 @@ impl DecoderScratch {
         self.fse.of_rle = None;
 
         self.huf.table.reset();
+
+        self.buffer.reset(window_size, single_segment)
     }
 
+    #[cfg(feature = "dictionary")]
     pub fn use_dict(&mut self, dict: &Dictionary) {
         self.fse = dict.fse.clone();
         self.huf = dict.huf.clone();
@@ -65,6 +83,7 @@ impl DecoderScratch {
 
     /// parses the dictionary and set the tables
     /// it returns the dict_id for checking with the frame's dict_id
+    #[cfg(feature = "dictionary")]
     pub fn load_dict(&mut self, raw: &[u8]) -> Result<u32, String> {
         let dict = super::dictionary::Dictionary::decode_dict(raw)?;
 
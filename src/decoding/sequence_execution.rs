@@ -7,7 +7,6 @@ pub fn execute_sequences(scratch: &mut DecoderScratch) -> Result<(), String> {
 
     for idx in 0..scratch.sequences.len() {
         let seq = scratch.sequences[idx];
-        if crate::VERBOSE {}
         //println!("{}: {}", idx, seq);
 
         if seq.ll > 0 {
@@ -34,30 +33,47 @@ pub fn execute_sequences(scratch: &mut DecoderScratch) -> Result<(), String> {
             return Err("Illegal offset: 0 found".to_owned());
         }
         if seq.ml > 0 {
+            if scratch.buffer.window_size == 0 {
+                // single segment frames with a content size of 0 have no window. They can only contain literals
+                return Err(format!(
+                    "Sequence with a match (offset: {}, match length: {}) in a frame with a window size of 0",
+                    actual_offset, seq.ml
+                ));
+            }
             scratch
                 .buffer
                 .repeat(actual_offset as usize, seq.ml as usize)?;
+            scratch.max_offset = u32::max(scratch.max_offset, actual_offset);
         }
 
-        seq_sum += seq.ml;
-        seq_sum += seq.ll;
+        seq_sum = checked_seq_sum(seq_sum, seq.ml)?;
+        seq_sum = checked_seq_sum(seq_sum, seq.ll)?;
     }
     if literals_copy_counter < scratch.literals_buffer.len() {
         let rest_literals = &scratch.literals_buffer[literals_copy_counter..];
         scratch.buffer.push(rest_literals);
-        seq_sum += rest_literals.len() as u32;
+        seq_sum = checked_seq_sum(seq_sum, rest_literals.len() as u32)?;
     }
 
     let diff = scratch.buffer.len() - old_buffer_size;
-    assert!(
-        seq_sum as usize == diff,
-        "Seq_sum: {} is different from the difference in buffersize: {}",
-        seq_sum,
-        diff
-    );
+    if seq_sum as usize != diff {
+        return Err(format!(
+            "Seq_sum: {} is different from the difference in buffersize: {}",
+            seq_sum, diff
+        ));
+    }
     Ok(())
 }
 
+fn checked_seq_sum(sum: u32, length: u32) -> Result<u32, String> {
+    sum.checked_add(length).ok_or_else(|| {
+        format!(
+            "The sequences of the block decode to more than {} bytes",
+            u32::MAX
+        )
+    })
+}
+
 fn do_offset_history(offset_value: u32, lit_len: u32, scratch: &mut [u32; 3]) -> u32 {
     let actual_offset = if lit_len > 0 {
         match offset_value {
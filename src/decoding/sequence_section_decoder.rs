@@ -3,7 +3,30 @@ use super::super::blocks::sequence_section::Sequence;
 use super::super::blocks::sequence_section::SequencesHeader;
 use super::bit_reader_reverse::BitReaderReversed;
 use super::scratch::FSEScratch;
+use crate::errors::BlockDecoderError;
 use crate::fse::FSEDecoder;
+use std::convert::TryFrom;
+
+/// Decodes a whole sequences section, from the start of its header to the end of the block, without executing the
+/// sequences. This is the fse stage of the decoder on its own, e.g. for testing it in isolation.
+/// Repeat modes reuse the tables that an earlier section built in scratch
+pub fn decode_sequences_section(
+    raw: &[u8],
+    scratch: &mut FSEScratch,
+) -> Result<Vec<Sequence>, BlockDecoderError> {
+    let mut section = SequencesHeader::new();
+    let header_size = section.parse_from_header(raw)?;
+    let mut sequences = Vec::new();
+    if section.num_sequences != 0 {
+        decode_sequences(
+            &section,
+            &raw[header_size as usize..],
+            scratch,
+            &mut sequences,
+        )?;
+    }
+    Ok(sequences)
+}
 
 pub fn decode_sequences(
     section: &SequencesHeader,
@@ -67,24 +90,24 @@ fn decode_sequences_with_rle(
 
     for _seq_idx in 0..section.num_sequences {
         //get the codes from either the RLE byte or from the decoder
-        let ll_code = if scratch.ll_rle.is_some() {
-            scratch.ll_rle.unwrap()
+        let ll_code = if let Some(code) = scratch.ll_rle {
+            code
         } else {
             ll_dec.decode_symbol()
         };
-        let ml_code = if scratch.ml_rle.is_some() {
-            scratch.ml_rle.unwrap()
+        let ml_code = if let Some(code) = scratch.ml_rle {
+            code
         } else {
             ml_dec.decode_symbol()
         };
-        let of_code = if scratch.of_rle.is_some() {
-            scratch.of_rle.unwrap()
+        let of_code = if let Some(code) = scratch.of_rle {
+            code
         } else {
             of_dec.decode_symbol()
         };
 
-        let (ll_value, ll_num_bits) = lookup_ll_code(ll_code);
-        let (ml_value, ml_num_bits) = lookup_ml_code(ml_code);
+        let (ll_value, ll_num_bits) = lookup_ll_code(ll_code)?;
+        let (ml_value, ml_num_bits) = lookup_ml_code(ml_code)?;
 
         //println!("Sequence: {}", i);
         //println!("of stat: {}", of_dec.state);
@@ -110,8 +133,8 @@ fn decode_sequences_with_rle(
         }
 
         target.push(Sequence {
-            ll: ll_value + ll_add as u32,
-            ml: ml_value + ml_add as u32,
+            ll: add_extra_bits(ll_value, ll_add, "literal length")?,
+            ml: add_extra_bits(ml_value, ml_add, "match length")?,
             of: offset,
         });
 
@@ -172,24 +195,37 @@ fn decode_sequences_without_rle(
         let ml_code = ml_dec.decode_symbol();
         let of_code = of_dec.decode_symbol();
 
-        let (ll_value, ll_num_bits) = lookup_ll_code(ll_code);
-        let (ml_value, ml_num_bits) = lookup_ml_code(ml_code);
+        let (ll_value, ll_num_bits) = lookup_ll_code(ll_code)?;
+        let (ml_value, ml_num_bits) = lookup_ml_code(ml_code)?;
 
         if of_code >= 32 {
             return Err("Do not support offsets bigger than 1<<32".to_owned());
         }
 
-        let offset = (br.get_bits(of_code as usize)? as u32) + (1u32 << of_code);
-        let ml_add = br.get_bits(ml_num_bits as usize)?;
-        let ll_add = br.get_bits(ll_num_bits as usize)?;
+        // refill the bitreader once for all three values if possible
+        let extra_bits = of_code + ml_num_bits + ll_num_bits;
+        let (offset_bits, ml_add, ll_add) = if extra_bits <= 56 && br.ensure_bits(extra_bits) {
+            (
+                br.get_bits_unchecked(of_code),
+                br.get_bits_unchecked(ml_num_bits),
+                br.get_bits_unchecked(ll_num_bits),
+            )
+        } else {
+            (
+                br.get_bits(of_code as usize)?,
+                br.get_bits(ml_num_bits as usize)?,
+                br.get_bits(ll_num_bits as usize)?,
+            )
+        };
+        let offset = offset_bits as u32 + (1u32 << of_code);
 
         if offset == 0 {
             return Err("Read an offset == 0. That is an illegal value for offsets".to_owned());
         }
 
         target.push(Sequence {
-            ll: ll_value + ll_add as u32,
-            ml: ml_value + ml_add as u32,
+            ll: add_extra_bits(ll_value, ll_add, "literal length")?,
+            ml: add_extra_bits(ml_value, ml_add, "match length")?,
             of: offset,
         });
 
@@ -199,9 +235,17 @@ fn decode_sequences_without_rle(
             //    br.bits_remaining(),
             //    br.bits_remaining() / 8,
             //);
-            ll_dec.update_state(br)?;
-            ml_dec.update_state(br)?;
-            of_dec.update_state(br)?;
+            // at most 9 + 9 + 8 bits, so this can always be done with one refill before the end of the stream
+            let state_bits = ll_dec.state_bits() + ml_dec.state_bits() + of_dec.state_bits();
+            if br.ensure_bits(state_bits) {
+                ll_dec.update_state_unchecked(br);
+                ml_dec.update_state_unchecked(br);
+                of_dec.update_state_unchecked(br);
+            } else {
+                ll_dec.update_state(br)?;
+                ml_dec.update_state(br)?;
+                of_dec.update_state(br)?;
+            }
         }
 
         if br.bits_remaining() < 0 {
@@ -222,8 +266,8 @@ fn decode_sequences_without_rle(
     }
 }
 
-fn lookup_ll_code(code: u8) -> (u32, u8) {
-    match code {
+fn lookup_ll_code(code: u8) -> Result<(u32, u8), String> {
+    let value = match code {
         0..=15 => (u32::from(code), 0),
         16 => (16, 1),
         17 => (18, 1),
@@ -245,12 +289,13 @@ fn lookup_ll_code(code: u8) -> (u32, u8) {
         33 => (16384, 14),
         34 => (32768, 15),
         35 => (65536, 16),
-        _ => (0, 255),
-    }
+        _ => return Err(format!("Illegal literal length code: {}", code)),
+    };
+    Ok(value)
 }
 
-fn lookup_ml_code(code: u8) -> (u32, u8) {
-    match code {
+fn lookup_ml_code(code: u8) -> Result<(u32, u8), String> {
+    let value = match code {
         0..=31 => (u32::from(code) + 3, 0),
         32 => (35, 1),
         33 => (37, 1),
@@ -273,8 +318,23 @@ fn lookup_ml_code(code: u8) -> (u32, u8) {
         50 => (16387, 14),
         51 => (32771, 15),
         52 => (65539, 16),
-        _ => (0, 255),
-    }
+        _ => return Err(format!("Illegal match length code: {}", code)),
+    };
+    Ok(value)
+}
+
+/// The value of a literal or match length is the baseline of its code plus the extra bits read for it.
+/// Can not overflow with the codes of the format, but the extra bits come straight from the input
+fn add_extra_bits(baseline: u32, extra_bits: u64, name: &str) -> Result<u32, String> {
+    u32::try_from(extra_bits)
+        .ok()
+        .and_then(|extra| baseline.checked_add(extra))
+        .ok_or_else(|| {
+            format!(
+                "The {} overflows: baseline {} plus extra bits {}",
+                name, baseline, extra_bits
+            )
+        })
 }
 
 pub const LL_MAX_LOG: u8 = 9;
@@ -0,0 +1,117 @@
+use std::io::Read;
+
+/// Minimal source of bytes for the StreamingDecoder. This decouples the decoder from std::io::Read as a first step towards no_std.
+/// With the io-read feature (enabled by default) every std::io::Read is a ByteSource, a &[u8] is always one.
+pub trait ByteSource {
+    /// Reads up to buf.len() bytes into buf and returns how many were read. Returning 0 means the source has ended
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String>;
+}
+
+// The impls for &[u8] and &mut S can not exist next to this one: both types can implement Read, so the impls would
+// overlap, and coherence does not allow an impl to exclude them. That is why this is a feature of its own instead of
+// being part of a "std" feature, the crate needs std either way
+#[cfg(feature = "io-read")]
+impl<R: Read> ByteSource for R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        loop {
+            match Read::read(self, buf) {
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Error while reading from the source: {}", e)),
+            }
+        }
+    }
+}
+
+// with io-read these are covered by the impl for all std::io::Read
+#[cfg(not(feature = "io-read"))]
+impl ByteSource for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        let amount = usize::min(self.len(), buf.len());
+        let (data, rest) = self.split_at(amount);
+        buf[..amount].copy_from_slice(data);
+        *self = rest;
+        Ok(amount)
+    }
+}
+
+#[cfg(not(feature = "io-read"))]
+impl<S: ByteSource + ?Sized> ByteSource for &mut S {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        (**self).read(buf)
+    }
+}
+
+/// Reads the concatenation of several slices without copying them into one buffer first,
+/// e.g. packets that were received one by one
+pub struct SliceChain<'a> {
+    current: &'a [u8],
+    rest: &'a [&'a [u8]],
+    bytes_read: usize,
+}
+
+impl<'a> SliceChain<'a> {
+    pub fn new(slices: &'a [&'a [u8]]) -> Self {
+        SliceChain {
+            current: &[],
+            rest: slices,
+            bytes_read: 0,
+        }
+    }
+
+    /// How many bytes have been read from all slices together
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+impl Read for SliceChain<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // skip over the slices that are used up, including empty ones
+        while self.current.is_empty() {
+            match self.rest.split_first() {
+                Some((next, rest)) => {
+                    self.current = next;
+                    self.rest = rest;
+                }
+                None => return Ok(0),
+            }
+        }
+        // a read never spans two slices, read_until_full and friends keep calling until they have enough
+        let amount = usize::min(self.current.len(), buf.len());
+        let (data, rest) = self.current.split_at(amount);
+        buf[..amount].copy_from_slice(data);
+        self.current = rest;
+        self.bytes_read += amount;
+        Ok(amount)
+    }
+}
+
+/// Adapter so a ByteSource can be passed to the parts of the decoder that still read from a std::io::Read
+#[cfg(feature = "streaming")]
+pub(crate) struct ByteSourceReader<'a, S: ByteSource + ?Sized>(pub &'a mut S);
+
+#[cfg(feature = "streaming")]
+impl<S: ByteSource + ?Sized> Read for ByteSourceReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        ByteSource::read(self.0, buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    }
+}
+
+/// Like Read::read_exact but a source that ends early is not an error. Returns how many bytes were read,
+/// which is only smaller than buf.len() if the source ran out of bytes. This lets callers report how many bytes were missing.
+pub(crate) fn read_until_full(mut source: impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match source.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(n) => bytes_read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(bytes_read)
+}
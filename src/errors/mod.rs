@@ -1,31 +1,122 @@
+/// Coarse classification of the decoder errors. Running out of input and getting input that is not valid zstd data
+/// call for different handling, e.g. a stream that ended early might decode fine once more bytes arrive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The input ended before the frame was complete
+    UnexpectedEof,
+    /// The input is malformed or uses features this decoder does not support, more input will not help
+    InvalidData,
+    /// Errors of the source or the output, and wrong usage of the decoder
+    Other,
+}
+
+/// The errors of the FrameDecoder. Running out of input is always UnexpectedEof (or Truncated with
+/// TruncationPolicy::BestEffort) and malformed input is always InvalidData, so callers can match on these two directly.
+/// The remaining variants are errors of the source and wrong usage of the decoder
 #[derive(Debug)]
 pub enum FrameDecoderError {
-    FailedToReadBlockHeader(String),
-    FailedToReadBlockBody(String),
+    /// The source ended before the frame was complete. context says what was being read, a stream that ended early
+    /// might decode fine once more bytes arrive
+    UnexpectedEof {
+        context: EofContext,
+        bytes_missing: usize,
+    },
+    /// The input is malformed or uses features this decoder does not support, more input will not help
+    InvalidData(InvalidDataError),
     FailedToReadChecksum,
     NotYetInitialized,
-    FailedToInitialize(String),
     FailedToDrainDecodebuffer,
     TargetTooSmall,
-    DictNotProvided,
+    /// The frame needs a dictionary that was not provided, loaded are the ids of the ones that were added with
+    /// add_dict. Functions that decode without a FrameDecoder can not use dictionaries, for them loaded is empty
+    DictNotProvided {
+        dict_id: u32,
+        loaded: Vec<u32>,
+    },
+    /// The source ended in the middle of the frame while decoding with TruncationPolicy::BestEffort.
+    /// Everything decoded up to that point can still be collected
+    Truncated {
+        bytes_recovered: u64,
+    },
+    /// The source returned an error while the decoder was reading from it
+    FailedToReadSource(String),
+    /// The buffer for the window or the content of a frame could not be allocated
+    FailedToAllocate(String),
+}
+
+/// What was wrong with the input of a FrameDecoderError::InvalidData
+#[derive(Debug)]
+pub enum InvalidDataError {
+    /// The frame header or a dictionary could not be parsed, or the frame needs more than the decoder allows
+    Initialize(String),
+    BlockHeader(String),
+    BlockBody(String),
+    /// The frame was written in one of the formats used before zstd v1.0, which are not supported
+    LegacyFormat {
+        version: u8,
+    },
+    /// The input is not zstd data but starts with the magic bytes of another compression format, e.g. "gzip"
+    WrongFormat {
+        detected: &'static str,
+    },
+    /// The frame does not decode to the size it was expected to have. If it was too big, decoded is how much had
+    /// been decoded when that was noticed
+    ContentSizeMismatch {
+        expected: u64,
+        decoded: u64,
+    },
+    /// The checksum stored in the frame does not match the lower 32 bits of the hash of the decoded content
+    ChecksumMismatch {
+        expected: u32,
+        calculated: u32,
+    },
+}
+
+impl FrameDecoderError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            FrameDecoderError::UnexpectedEof { .. } | FrameDecoderError::Truncated { .. } => {
+                ErrorCategory::UnexpectedEof
+            }
+            FrameDecoderError::InvalidData(_) => ErrorCategory::InvalidData,
+            FrameDecoderError::FailedToReadChecksum
+            | FrameDecoderError::NotYetInitialized
+            | FrameDecoderError::FailedToDrainDecodebuffer
+            | FrameDecoderError::TargetTooSmall
+            | FrameDecoderError::DictNotProvided { .. }
+            | FrameDecoderError::FailedToReadSource(_)
+            | FrameDecoderError::FailedToAllocate(_) => ErrorCategory::Other,
+        }
+    }
+
+    /// Shorthands for the most common InvalidData errors, they fit into map_err
+    pub(crate) fn invalid_init(m: String) -> FrameDecoderError {
+        FrameDecoderError::InvalidData(InvalidDataError::Initialize(m))
+    }
+
+    pub(crate) fn invalid_block_header(m: String) -> FrameDecoderError {
+        FrameDecoderError::InvalidData(InvalidDataError::BlockHeader(m))
+    }
+
+    pub(crate) fn invalid_block_body(m: String) -> FrameDecoderError {
+        FrameDecoderError::InvalidData(InvalidDataError::BlockBody(m))
+    }
+}
+
+impl From<InvalidDataError> for FrameDecoderError {
+    fn from(e: InvalidDataError) -> Self {
+        FrameDecoderError::InvalidData(e)
+    }
 }
 
 impl std::fmt::Display for FrameDecoderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            FrameDecoderError::FailedToReadBlockBody(m) => {
-                write!(f, "Failed to parse/decode block body: {}", m)
-            }
-            FrameDecoderError::FailedToReadBlockHeader(m) => {
-                write!(f, "Failed to parse block header: {}", m)
-            }
+            FrameDecoderError::InvalidData(e) => write!(f, "{}", e),
             FrameDecoderError::FailedToReadChecksum => write!(f, "Failed to read checksum"),
             FrameDecoderError::NotYetInitialized => {
                 write!(f, "Decoder must initialized or reset before using it")
             }
-            FrameDecoderError::FailedToInitialize(m) => {
-                write!(f, "Decoder encountered error while initializing: {}", m)
-            }
             FrameDecoderError::FailedToDrainDecodebuffer => write!(
                 f,
                 "Decoder encountered error while draining the decodebuffer"
@@ -34,14 +125,83 @@ impl std::fmt::Display for FrameDecoderError {
                 f,
                 "Target must have at least as many bytes as the contentsize of the frame reports"
             ),
-            FrameDecoderError::DictNotProvided => write!(
+            FrameDecoderError::DictNotProvided { dict_id, loaded } => write!(
+                f,
+                "Frame needs the dictionary with id {} that wasnt provided by add_dict() or reset_with_dict(). Added are the dictionaries {:?}",
+                dict_id, loaded
+            ),
+            FrameDecoderError::UnexpectedEof {
+                context: EofContext::Checksum,
+                bytes_missing,
+            } => write!(
+                f,
+                "Frame has the checksum flag set but the checksum is missing {} of its 4 bytes",
+                bytes_missing
+            ),
+            FrameDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            } => write!(
+                f,
+                "Source ended while reading the {}. At least {} more bytes were needed",
+                context, bytes_missing
+            ),
+            FrameDecoderError::Truncated { bytes_recovered } => write!(
                 f,
-                "Frame header specified dictionary id that wasnt provided by add_dict() or reset_with_dict()"
+                "Frame was truncated, decoded {} bytes before the source ended",
+                bytes_recovered
+            ),
+            FrameDecoderError::FailedToReadSource(m) => {
+                write!(f, "Failed to read from the source: {}", m)
+            }
+            FrameDecoderError::FailedToAllocate(m) => {
+                write!(f, "Failed to allocate the buffer of the frame: {}", m)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidDataError::BlockBody(m) => {
+                write!(f, "Failed to parse/decode block body: {}", m)
+            }
+            InvalidDataError::BlockHeader(m) => {
+                write!(f, "Failed to parse block header: {}", m)
+            }
+            InvalidDataError::Initialize(m) => {
+                write!(f, "Decoder encountered error while initializing: {}", m)
+            }
+            InvalidDataError::LegacyFormat { version } => write!(
+                f,
+                "Frame uses the legacy zstd v0.{} format, only the format of zstd v1.0 and later is supported",
+                version
+            ),
+            InvalidDataError::WrongFormat { detected } => write!(
+                f,
+                "Input is not zstd compressed, it starts with the magic bytes of {}",
+                detected
+            ),
+            InvalidDataError::ContentSizeMismatch { expected, decoded } => write!(
+                f,
+                "Frame was expected to decode to {} bytes but decoded to {}",
+                expected, decoded
+            ),
+            InvalidDataError::ChecksumMismatch {
+                expected,
+                calculated,
+            } => write!(
+                f,
+                "Frame checksum {:08x} does not match the checksum {:08x} of the decoded content",
+                expected, calculated
             ),
         }
     }
 }
 
+impl std::error::Error for InvalidDataError {}
+
 // This is important for other errors to wrap this one.
 impl std::error::Error for FrameDecoderError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
@@ -49,3 +209,219 @@ impl std::error::Error for FrameDecoderError {
         None
     }
 }
+
+impl From<FrameHeaderError> for FrameDecoderError {
+    fn from(e: FrameHeaderError) -> Self {
+        match e {
+            FrameHeaderError::UnexpectedEof { bytes_missing } => FrameDecoderError::UnexpectedEof {
+                context: EofContext::FrameHeader,
+                bytes_missing,
+            },
+            FrameHeaderError::Invalid(m) => InvalidDataError::Initialize(m).into(),
+            FrameHeaderError::LegacyFormat { version } => {
+                InvalidDataError::LegacyFormat { version }.into()
+            }
+            FrameHeaderError::WrongFormat { detected } => {
+                InvalidDataError::WrongFormat { detected }.into()
+            }
+            FrameHeaderError::ReadFailed(m) => FrameDecoderError::FailedToReadSource(m),
+        }
+    }
+}
+
+/// Describes what the decoder was reading when the source ran out of bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EofContext {
+    FrameHeader,
+    BlockHeader,
+    /// The content of a raw or RLE block
+    BlockContent,
+    Literals,
+    Sequences,
+    /// The 4 bytes of the content checksum after the last block
+    Checksum,
+}
+
+impl std::fmt::Display for EofContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EofContext::FrameHeader => write!(f, "frame header"),
+            EofContext::BlockHeader => write!(f, "block header"),
+            EofContext::BlockContent => write!(f, "block content"),
+            EofContext::Literals => write!(f, "literals section"),
+            EofContext::Sequences => write!(f, "sequences section"),
+            EofContext::Checksum => write!(f, "checksum"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameHeaderError {
+    UnexpectedEof {
+        bytes_missing: usize,
+    },
+    Invalid(String),
+    /// The magic number belongs to one of the formats used before zstd v1.0
+    LegacyFormat {
+        version: u8,
+    },
+    /// The data starts with the magic bytes of another compression format, see frame::other_format
+    WrongFormat {
+        detected: &'static str,
+    },
+    /// The source returned an error
+    ReadFailed(String),
+}
+
+impl FrameHeaderError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            FrameHeaderError::UnexpectedEof { .. } => ErrorCategory::UnexpectedEof,
+            FrameHeaderError::Invalid(_)
+            | FrameHeaderError::LegacyFormat { .. }
+            | FrameHeaderError::WrongFormat { .. } => ErrorCategory::InvalidData,
+            FrameHeaderError::ReadFailed(_) => ErrorCategory::Other,
+        }
+    }
+}
+
+impl From<String> for FrameHeaderError {
+    fn from(m: String) -> Self {
+        FrameHeaderError::Invalid(m)
+    }
+}
+
+impl std::fmt::Display for FrameHeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameHeaderError::UnexpectedEof { bytes_missing } => write!(
+                f,
+                "Source ended while reading the frame header. At least {} more bytes were needed",
+                bytes_missing
+            ),
+            FrameHeaderError::Invalid(m) => write!(f, "Invalid frame header: {}", m),
+            FrameHeaderError::LegacyFormat { version } => write!(
+                f,
+                "Frame uses the legacy zstd v0.{} format which is not supported",
+                version
+            ),
+            FrameHeaderError::WrongFormat { detected } => write!(
+                f,
+                "Data is not zstd compressed, it starts like {} compressed data",
+                detected
+            ),
+            FrameHeaderError::ReadFailed(m) => write!(f, "Failed to read the frame header: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for FrameHeaderError {}
+
+#[derive(Debug)]
+pub enum BlockDecoderError {
+    UnexpectedEof {
+        context: EofContext,
+        bytes_missing: usize,
+    },
+    /// The literals section header declares more bytes than are left in the block
+    LiteralsSectionTooBig {
+        declared: usize,
+        available: usize,
+    },
+    /// The literals section would decode to more bytes than the maximum block size allows
+    LiteralsRegeneratedSizeTooBig {
+        size: usize,
+        max: usize,
+    },
+    Invalid(String),
+    /// The source returned an error
+    ReadFailed(String),
+}
+
+impl BlockDecoderError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            BlockDecoderError::UnexpectedEof { .. } => ErrorCategory::UnexpectedEof,
+            BlockDecoderError::LiteralsSectionTooBig { .. }
+            | BlockDecoderError::LiteralsRegeneratedSizeTooBig { .. }
+            | BlockDecoderError::Invalid(_) => ErrorCategory::InvalidData,
+            BlockDecoderError::ReadFailed(_) => ErrorCategory::Other,
+        }
+    }
+
+    /// Wraps an error from reading a block header for the FrameDecoder
+    pub(crate) fn into_header_error(self) -> FrameDecoderError {
+        match self {
+            BlockDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            } => FrameDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            },
+            BlockDecoderError::Invalid(m) => FrameDecoderError::invalid_block_header(m),
+            BlockDecoderError::ReadFailed(m) => FrameDecoderError::FailedToReadSource(m),
+            e @ (BlockDecoderError::LiteralsSectionTooBig { .. }
+            | BlockDecoderError::LiteralsRegeneratedSizeTooBig { .. }) => {
+                FrameDecoderError::invalid_block_header(e.to_string())
+            }
+        }
+    }
+
+    /// Wraps an error from decoding a block body for the FrameDecoder
+    pub(crate) fn into_body_error(self) -> FrameDecoderError {
+        match self {
+            BlockDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            } => FrameDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            },
+            BlockDecoderError::Invalid(m) => FrameDecoderError::invalid_block_body(m),
+            BlockDecoderError::ReadFailed(m) => FrameDecoderError::FailedToReadSource(m),
+            e @ (BlockDecoderError::LiteralsSectionTooBig { .. }
+            | BlockDecoderError::LiteralsRegeneratedSizeTooBig { .. }) => {
+                FrameDecoderError::invalid_block_body(e.to_string())
+            }
+        }
+    }
+}
+
+impl From<String> for BlockDecoderError {
+    fn from(m: String) -> Self {
+        BlockDecoderError::Invalid(m)
+    }
+}
+
+impl std::fmt::Display for BlockDecoderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            } => write!(
+                f,
+                "Source ended while reading the {}. At least {} more bytes were needed",
+                context, bytes_missing
+            ),
+            BlockDecoderError::LiteralsSectionTooBig {
+                declared,
+                available,
+            } => write!(
+                f,
+                "Malformed literals section header. Says literals would be this long: {} but only {} bytes are left in the block",
+                declared, available
+            ),
+            BlockDecoderError::LiteralsRegeneratedSizeTooBig { size, max } => write!(
+                f,
+                "Malformed literals section header. Says literals decode to {} bytes but a block can decode to at most {} bytes",
+                size, max
+            ),
+            BlockDecoderError::Invalid(m) => write!(f, "{}", m),
+            BlockDecoderError::ReadFailed(m) => write!(f, "Failed to read from the source: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for BlockDecoderError {}
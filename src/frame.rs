@@ -1,6 +1,35 @@
 use std::convert::TryInto;
 
 pub const MAGIC_NUM: u32 = 0xFD2F_B528;
+/// Skippable frames can use any magic number from this range. They contain user data and no zstd content
+pub const SKIPPABLE_MAGIC_NUM_MIN: u32 = 0x184D_2A50;
+pub const SKIPPABLE_MAGIC_NUM_MAX: u32 = 0x184D_2A5F;
+
+/// Returns the minor version (v0.x) of the pre-1.0 zstd format that uses this magic number, if any.
+/// This only detects those frames, there is no decoder for any of the legacy formats yet
+pub fn legacy_version(magic_num: u32) -> Option<u8> {
+    match magic_num {
+        0xFD2F_B51E => Some(1),
+        0xFD2F_B522..=0xFD2F_B527 => Some((magic_num - 0xFD2F_B520) as u8),
+        _ => None,
+    }
+}
+
+/// Returns the name of the compression format the data starts with if that is not zstd but one of the formats
+/// that are often mistaken for it, detected by their magic bytes
+pub fn other_format(data: &[u8]) -> Option<&'static str> {
+    const MAGICS: [(&[u8], &str); 4] = [
+        (&[0x1F, 0x8B], "gzip"),
+        (&[0xFD, 0x37, 0x7A, 0x58, 0x5A], "xz"),
+        (&[0x42, 0x5A, 0x68], "bzip2"),
+        (&[0x04, 0x22, 0x4D, 0x18], "lz4"),
+    ];
+    MAGICS
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|&(_, name)| name)
+}
+
 pub const MIN_WINDOW_SIZE: u64 = 1024;
 pub const MAX_WINDOW_SIZE: u64 = (1 << 41) + 7 * (1 << 38);
 
@@ -9,6 +38,7 @@ pub struct Frame {
     pub header: FrameHeader,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FrameHeader {
     pub descriptor: FrameDescriptor,
     window_descriptor: u8,
@@ -16,6 +46,7 @@ pub struct FrameHeader {
     frame_content_size: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FrameDescriptor(u8);
 
 impl FrameDescriptor {
@@ -73,10 +104,25 @@ impl FrameDescriptor {
     }
 }
 impl FrameHeader {
+    /// The header of a multi-segment frame without content size, checksum or dictionary id.
+    /// Its window descriptor is 0, for block streams with a window size that is stored elsewhere
+    pub(crate) fn without_fields() -> FrameHeader {
+        FrameHeader {
+            descriptor: FrameDescriptor(0),
+            window_descriptor: 0,
+            dict_id: Vec::new(),
+            frame_content_size: Vec::new(),
+        }
+    }
+
+    /// Single segment frames use their content size as window size, so empty frames have a window of 0 bytes.
+    /// These are valid but no sequence in them may contain a match.
     pub fn window_size(&self) -> Result<u64, String> {
         if self.descriptor.single_segment_flag() {
             self.frame_content_size()
         } else {
+            // window_size = (1 << (10 + exponent)) + mantissa * (1 << (7 + exponent))
+            // with a 5 bit exponent this can not get bigger than MAX_WINDOW_SIZE, which is an allowed size itself
             let exp = self.window_descriptor >> 3;
             let mantissa = self.window_descriptor & 0x7;
 
@@ -87,11 +133,11 @@ impl FrameHeader {
             let window_size = window_base + window_add;
 
             if window_size >= MIN_WINDOW_SIZE {
-                if window_size < MAX_WINDOW_SIZE {
+                if window_size <= MAX_WINDOW_SIZE {
                     Ok(window_size)
                 } else {
                     Err(format!(
-                        "window_size bigger than allowed maximum. Is: {}, Should be lower than: {}",
+                        "window_size bigger than allowed maximum. Is: {}, Should be at most: {}",
                         window_size, MAX_WINDOW_SIZE
                     ))
                 }
@@ -150,9 +196,11 @@ impl FrameHeader {
                 }
                 2 => {
                     if self.frame_content_size.len() == 2 {
-                        let val = (u64::from(self.frame_content_size[1]) << 8)
-                            + (u64::from(self.frame_content_size[0]));
-                        Ok(val + 256) //this weird offset is from the documentation. Only if bytes == 2
+                        let val = self.frame_content_size[..2]
+                            .try_into()
+                            .expect("optimized away");
+                        let val = u16::from_le_bytes(val);
+                        Ok(u64::from(val) + 256) //this weird offset is from the documentation. Only if bytes == 2
                     } else {
                         Err(format!(
                             "frame_content_size not long enough. Is: {}, Should be: {}",
@@ -201,14 +249,30 @@ impl FrameHeader {
 }
 
 impl Frame {
+    /// Creates a frame for a header that was not read from the frame itself, e.g. because it was stored separately
+    pub(crate) fn from_header(header: FrameHeader) -> Frame {
+        Frame {
+            magic_num: MAGIC_NUM,
+            header,
+        }
+    }
+
     pub fn check_valid(&self) -> Result<(), String> {
+        if self.header.descriptor.reserved_flag() {
+            Err("Reserved Flag set. Must be zero".to_string())
+        } else {
+            self.check_valid_allow_reserved()
+        }
+    }
+
+    /// Like check_valid but accepts frames that have the reserved bit of the frame descriptor set.
+    /// A future version of the format might give that bit a meaning that older decoders can safely ignore
+    pub fn check_valid_allow_reserved(&self) -> Result<(), String> {
         if self.magic_num != MAGIC_NUM {
             Err(format!(
                 "magic_num wrong. Is: {}. Should be: {}",
                 self.magic_num, MAGIC_NUM
             ))
-        } else if self.header.descriptor.reserved_flag() {
-            Err("Reserved Flag set. Must be zero".to_string())
         } else {
             match self.header.dictiornary_id() {
                 Ok(_) => match self.header.window_size() {
@@ -230,64 +294,335 @@ impl Frame {
     }
 }
 
+use crate::decoding::scratch::FSEScratch;
+use crate::decoding::source::read_until_full;
+use crate::errors::{BlockDecoderError, FrameDecoderError, FrameHeaderError};
 use std::io::Read;
-pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), String> {
-    let mut buf = [0u8; 4];
-    let magic_num: u32 = match r.read_exact(&mut buf) {
-        Ok(_) => u32::from_le_bytes(buf),
-        Err(_) => return Err("Error while reading magic number".to_owned()),
-    };
 
-    let mut bytes_read = 4;
+/// Reads the frame header from the source. If the source ends before the header is complete
+/// the error reports how many bytes of the header were missing
+pub fn read_frame_header(mut r: impl Read) -> Result<(Frame, u8), FrameHeaderError> {
+    // magic number and frame descriptor
+    let mut buf = [0u8; 5];
+    let bytes_read = read_until_full(&mut r, &mut buf).map_err(|e| {
+        FrameHeaderError::ReadFailed(format!(
+            "Error while reading magic number and frame descriptor: {}",
+            e
+        ))
+    })?;
+    // even if the source is too short for a zstd header, the magic bytes can tell what it is instead
+    if let Some(detected) = other_format(&buf[..bytes_read]) {
+        return Err(FrameHeaderError::WrongFormat { detected });
+    }
+    if bytes_read < buf.len() {
+        return Err(FrameHeaderError::UnexpectedEof {
+            bytes_missing: buf.len() - bytes_read,
+        });
+    }
+    let magic_num = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if let Some(version) = legacy_version(magic_num) {
+        // the rest of a legacy header has a different layout, dont try to parse it
+        return Err(FrameHeaderError::LegacyFormat { version });
+    }
+    let desc = FrameDescriptor(buf[4]);
+
+    let window_descriptor_bytes = if desc.single_segment_flag() { 0 } else { 1 };
+    let dict_id_bytes = desc.dictionary_id_bytes()? as usize;
+    let fcs_bytes = desc.frame_content_size_bytes()? as usize;
 
-    let desc: FrameDescriptor = match r.read_exact(&mut buf[0..1]) {
-        Ok(_) => FrameDescriptor(buf[0]),
-        Err(_) => return Err("Error while reading frame descriptor".to_owned()),
+    // the rest of the header is at most 1 + 4 + 8 bytes and its length is known from the descriptor
+    let mut rest = [0u8; 13];
+    let rest = &mut rest[..window_descriptor_bytes + dict_id_bytes + fcs_bytes];
+    let bytes_read = read_until_full(&mut r, rest).map_err(|e| {
+        FrameHeaderError::ReadFailed(format!("Error while reading the frame header: {}", e))
+    })?;
+    if bytes_read < rest.len() {
+        return Err(FrameHeaderError::UnexpectedEof {
+            bytes_missing: rest.len() - bytes_read,
+        });
+    }
+
+    let (window_descriptor, rest) = rest.split_at(window_descriptor_bytes);
+    let (dict_id, frame_content_size) = rest.split_at(dict_id_bytes);
+
+    let frame_header = FrameHeader {
+        descriptor: desc,
+        window_descriptor: window_descriptor.first().copied().unwrap_or(0),
+        dict_id: dict_id.to_vec(),
+        frame_content_size: frame_content_size.to_vec(),
     };
 
-    bytes_read += 1;
-
-    let mut frame_header = FrameHeader {
-        descriptor: FrameDescriptor(desc.0),
-        dict_id: match desc.dictionary_id_bytes() {
-            Ok(bytes) => vec![0; bytes as usize],
-            Err(m) => return Err(m),
-        },
-        frame_content_size: match desc.frame_content_size_bytes() {
-            Ok(bytes) => vec![0; bytes as usize],
-            Err(m) => return Err(m),
-        },
-        window_descriptor: 0,
+    let frame: Frame = Frame {
+        magic_num,
+        header: frame_header,
     };
 
-    if !desc.single_segment_flag() {
-        match r.read_exact(&mut buf[0..1]) {
-            Ok(_) => frame_header.window_descriptor = buf[0],
-            Err(_) => return Err("Error while reading window descriptor".to_owned()),
+    Ok((
+        frame,
+        (5 + window_descriptor_bytes + dict_id_bytes + fcs_bytes) as u8,
+    ))
+}
+
+/// Returns the Frame_Content_Size declared in the header of the frame at the start of input.
+/// Returns None if the frame does not declare its size, use scan_decompressed_size for those frames.
+pub fn decompressed_size(input: &[u8]) -> Result<Option<u64>, FrameHeaderError> {
+    let (frame, _) = read_frame_header(input)?;
+    frame.check_valid()?;
+    if frame.header.descriptor.frame_content_size_bytes()? == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(frame.header.frame_content_size()?))
+    }
+}
+
+/// Calculates the decompressed size of the frame at the start of input by walking all of its blocks.
+/// This works for frames that do not declare their Frame_Content_Size. Raw and RLE blocks carry their size in the block header,
+/// compressed blocks need their sequences decoded but no output is produced.
+///
+/// Frames that need a dictionary can not be scanned and return FrameDecoderError::DictNotProvided
+pub fn scan_decompressed_size(input: &[u8]) -> Result<u64, FrameDecoderError> {
+    let mut source = input;
+    let (frame, _) = read_frame_header(&mut source)?;
+    frame
+        .check_valid()
+        .map_err(FrameDecoderError::invalid_init)?;
+    if let Some(dict_id) = frame
+        .header
+        .dictiornary_id()
+        .map_err(FrameDecoderError::invalid_init)?
+    {
+        return Err(FrameDecoderError::DictNotProvided {
+            dict_id,
+            loaded: Vec::new(),
+        });
+    }
+
+    let mut block_dec = crate::decoding::block_decoder::new();
+    let mut fse = FSEScratch::new();
+    let mut sequences = Vec::new();
+    let mut total = 0;
+    loop {
+        let (header, _) = block_dec
+            .read_block_header(&mut source)
+            .map_err(BlockDecoderError::into_header_error)?;
+        total += block_dec
+            .regenerated_size(&header, source, &mut fse, &mut sequences)
+            .map_err(BlockDecoderError::into_body_error)?;
+        source = &source[header.content_size as usize..];
+        if header.last_block {
+            break;
         }
-        bytes_read += 1;
     }
+    Ok(total)
+}
 
-    if !frame_header.dict_id.is_empty() {
-        match r.read_exact(frame_header.dict_id.as_mut_slice()) {
-            Ok(_) => {}
-            Err(_) => return Err("Error while reading dcitionary id".to_owned()),
+/// Decodes the literals lengths and the sequences of every block in the frame at the start of input, without executing the sequences.
+/// This is meant for analyzing what an encoder produced, e.g. to build histograms of literal and match lengths.
+///
+/// Frames that need a dictionary can not be inspected and return FrameDecoderError::DictNotProvided
+pub fn inspect_blocks(
+    input: &[u8],
+) -> Result<Vec<crate::blocks::block::BlockInspection>, FrameDecoderError> {
+    let mut source = input;
+    let (frame, _) = read_frame_header(&mut source)?;
+    frame
+        .check_valid()
+        .map_err(FrameDecoderError::invalid_init)?;
+    if let Some(dict_id) = frame
+        .header
+        .dictiornary_id()
+        .map_err(FrameDecoderError::invalid_init)?
+    {
+        return Err(FrameDecoderError::DictNotProvided {
+            dict_id,
+            loaded: Vec::new(),
+        });
+    }
+
+    let mut block_dec = crate::decoding::block_decoder::new();
+    let mut fse = FSEScratch::new();
+    let mut blocks = Vec::new();
+    loop {
+        let (header, _) = block_dec
+            .read_block_header(&mut source)
+            .map_err(BlockDecoderError::into_header_error)?;
+        let mut sequences = Vec::new();
+        let literals_length = block_dec
+            .inspect_block_content(&header, source, &mut fse, &mut sequences)
+            .map_err(BlockDecoderError::into_body_error)?;
+        source = &source[header.content_size as usize..];
+        // the match lengths come straight from the input and a crafted block can make them add up to anything,
+        // even though a valid block decodes to at most 128kb
+        let max_block_size = u64::from(crate::decoding::block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE);
+        let regenerated_size = sequences
+            .iter()
+            .try_fold(u64::from(literals_length), |size, seq| {
+                size.checked_add(u64::from(seq.ml))
+                    .filter(|&size| size <= max_block_size)
+            })
+            .ok_or_else(|| {
+                BlockDecoderError::Invalid(format!(
+                    "The {} sequences of the block decode to more than the maximum block size of {} bytes",
+                    sequences.len(),
+                    max_block_size
+                ))
+                .into_body_error()
+            })?;
+        blocks.push(crate::blocks::block::BlockInspection {
+            block_type: header.block_type,
+            content_size: header.content_size,
+            literals_length,
+            regenerated_size: regenerated_size as u32,
+            sequences,
+        });
+        if header.last_block {
+            break;
         }
-        bytes_read += frame_header.dict_id.len();
     }
+    Ok(blocks)
+}
+
+/// Finds the byte range of every zstd frame in input by walking the frame headers and block headers, without decoding anything.
+/// The ranges include the checksum at the end of the frame if there is one.
+/// Skippable frames are stepped over and are not part of the result, see frames_with_skippable to read them.
+///
+/// If input ends in the middle of a frame this returns FrameHeaderError::UnexpectedEof, even if the frame header
+/// itself was complete
+pub fn scan_frames(input: &[u8]) -> Result<Vec<std::ops::Range<usize>>, FrameHeaderError> {
+    frames_with_skippable(input)
+        .filter_map(|frame| match frame {
+            Ok(FrameOrSkippable::Frame(range)) => Some(Ok(range)),
+            Ok(FrameOrSkippable::Skippable(_)) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect()
+}
+
+/// A skippable frame, which carries user data instead of zstd content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippableFrame {
+    /// One of SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX
+    pub magic: u32,
+    pub data: Vec<u8>,
+}
+
+impl SkippableFrame {
+    /// The low 4 bits of the magic number, which users can pick freely to tell their kinds of skippable frames apart
+    pub fn user_tag(&self) -> u8 {
+        (self.magic & 0xF) as u8
+    }
+}
+
+/// An item of frames_with_skippable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameOrSkippable {
+    /// The byte range of a zstd frame in the input, including its checksum
+    Frame(std::ops::Range<usize>),
+    Skippable(SkippableFrame),
+}
+
+/// Iterates over all frames in input, the zstd frames as byte ranges like scan_frames returns them and the skippable
+/// frames with their payload. Nothing is decoded.
+///
+/// If input ends in the middle of a frame or a frame header is invalid, the last item is the error
+pub fn frames_with_skippable(input: &[u8]) -> FramesWithSkippable<'_> {
+    FramesWithSkippable {
+        input,
+        frame_start: 0,
+        failed: false,
+    }
+}
 
-    if !frame_header.frame_content_size.is_empty() {
-        match r.read_exact(frame_header.frame_content_size.as_mut_slice()) {
-            Ok(_) => {}
-            Err(_) => return Err("Error while reading frame content size".to_owned()),
+/// Iterator returned by frames_with_skippable
+pub struct FramesWithSkippable<'a> {
+    input: &'a [u8],
+    frame_start: usize,
+    failed: bool,
+}
+
+impl Iterator for FramesWithSkippable<'_> {
+    type Item = Result<FrameOrSkippable, FrameHeaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.frame_start >= self.input.len() {
+            return None;
         }
-        bytes_read += frame_header.frame_content_size.len();
+        let result = self.next_frame();
+        if result.is_err() {
+            self.failed = true;
+        }
+        Some(result)
     }
+}
 
-    let frame: Frame = Frame {
-        magic_num,
-        header: frame_header,
-    };
+impl FramesWithSkippable<'_> {
+    fn next_frame(&mut self) -> Result<FrameOrSkippable, FrameHeaderError> {
+        let input = self.input;
+        let frame_start = self.frame_start;
+        let mut source = &input[frame_start..];
+        if source.len() < 4 {
+            return Err(FrameHeaderError::UnexpectedEof {
+                bytes_missing: 4 - source.len(),
+            });
+        }
+        let magic_num = u32::from_le_bytes(source[..4].try_into().expect("4 bytes were checked"));
+
+        if (SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num) {
+            if source.len() < 8 {
+                return Err(FrameHeaderError::UnexpectedEof {
+                    bytes_missing: 8 - source.len(),
+                });
+            }
+            let size = u32::from_le_bytes(source[4..8].try_into().expect("8 bytes were checked"));
+            let frame_len = 8 + size as usize;
+            if source.len() < frame_len {
+                return Err(FrameHeaderError::UnexpectedEof {
+                    bytes_missing: frame_len - source.len(),
+                });
+            }
+            self.frame_start += frame_len;
+            return Ok(FrameOrSkippable::Skippable(SkippableFrame {
+                magic: magic_num,
+                data: source[8..frame_len].to_vec(),
+            }));
+        }
+
+        let (frame, _) = read_frame_header(&mut source)?;
+        frame.check_valid()?;
 
-    Ok((frame, bytes_read as u8))
+        let mut block_dec = crate::decoding::block_decoder::new();
+        loop {
+            let (header, _) = block_dec
+                .read_block_header(&mut source)
+                .map_err(|e| match e {
+                    BlockDecoderError::UnexpectedEof { bytes_missing, .. } => {
+                        FrameHeaderError::UnexpectedEof { bytes_missing }
+                    }
+                    e => FrameHeaderError::Invalid(e.to_string()),
+                })?;
+            let content_size = header.content_size as usize;
+            if source.len() < content_size {
+                return Err(FrameHeaderError::UnexpectedEof {
+                    bytes_missing: content_size - source.len(),
+                });
+            }
+            source = &source[content_size..];
+            if header.last_block {
+                break;
+            }
+        }
+
+        if frame.header.descriptor.content_checksum_flag() {
+            if source.len() < 4 {
+                return Err(FrameHeaderError::UnexpectedEof {
+                    bytes_missing: 4 - source.len(),
+                });
+            }
+            source = &source[4..];
+        }
+
+        let frame_end = input.len() - source.len();
+        self.frame_start = frame_end;
+        Ok(FrameOrSkippable::Frame(frame_start..frame_end))
+    }
 }
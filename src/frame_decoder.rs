@@ -1,10 +1,17 @@
 use super::frame;
+use crate::blocks::block::BlockType;
 use crate::decoding;
+use crate::decoding::block_decoder::ABSOLUTE_MAXIMUM_BLOCK_SIZE;
+use crate::decoding::content_hasher::{ContentHash, ContentHasher};
+#[cfg(feature = "dictionary")]
 use crate::decoding::dictionary::Dictionary;
+use crate::decoding::ringbuffer::RingBuffer;
 use crate::decoding::scratch::DecoderScratch;
+use crate::decoding::source::{read_until_full, SliceChain};
+use crate::errors::{BlockDecoderError, EofContext, FrameDecoderError, InvalidDataError};
+#[cfg(feature = "dictionary")]
 use std::collections::HashMap;
-use std::convert::TryInto;
-use std::hash::Hasher;
+use std::convert::{TryFrom, TryInto};
 use std::io::Read;
 
 /// This implements a decoder for zstd frames. This decoder is able to decode frames only partially and gives control
@@ -55,17 +62,31 @@ use std::io::Read;
 /// ```
 pub struct FrameDecoder {
     state: Option<FrameDecoderState>,
+    #[cfg(feature = "dictionary")]
     dicts: HashMap<u32, Dictionary>,
+    /// The dictionary given to the last reset_with_dict, kept parsed for reset_keep_dict
+    #[cfg(feature = "dictionary")]
+    reset_dict: Option<Dictionary>,
+    truncation_policy: TruncationPolicy,
+    force_checksum: bool,
+    allow_reserved_bits: bool,
+    zero_window_memory: bool,
+    max_window_size: u64,
+    /// A ringbuffer given to use_ring_buffer before there was a frame to use it for
+    ring_buffer: Option<RingBuffer>,
+    /// Creates the hasher for every frame if one was set with use_content_hasher
+    new_content_hasher: Option<fn() -> Box<dyn ContentHasher + Send + Sync>>,
 }
 
 struct FrameDecoderState {
     pub frame: frame::Frame,
     decoder_scratch: DecoderScratch,
     frame_finished: bool,
-    block_counter: usize,
+    block_counter: u64,
     bytes_read_counter: u64,
     check_sum: Option<u32>,
     using_dict: Option<u32>,
+    truncated: bool,
 }
 
 pub enum BlockDecodingStrategy {
@@ -74,47 +95,206 @@ pub enum BlockDecodingStrategy {
     UptoBytes(usize),
 }
 
+/// What decode_blocks does if the source ends before the frame is complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncationPolicy {
+    /// Fail with FrameDecoderError::UnexpectedEof. This is the default
+    Strict,
+    /// Treat the frame as finished so everything decoded so far can be collected,
+    /// and fail with FrameDecoderError::Truncated which tells how many bytes were recovered
+    BestEffort,
+}
+
+/// How much decoded content decode_to_writer collects before writing it
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The default for FrameDecoder::set_max_window_size
 const MAX_WINDOW_SIZE: u64 = 1024 * 1024 * 100;
 
 impl FrameDecoderState {
-    pub fn new(source: impl Read) -> Result<FrameDecoderState, String> {
-        let (frame, header_size) = frame::read_frame_header(source)?;
-        let window_size = frame.header.window_size()?;
-        frame.check_valid()?;
-        Ok(FrameDecoderState {
-            frame,
+    /// A state without a frame, which has to be started with reset before anything can be decoded
+    fn new() -> FrameDecoderState {
+        FrameDecoderState {
+            frame: frame::Frame::from_header(frame::FrameHeader::without_fields()),
             frame_finished: false,
             block_counter: 0,
-            decoder_scratch: DecoderScratch::new(window_size as usize),
-            bytes_read_counter: u64::from(header_size),
+            decoder_scratch: DecoderScratch::new(0, false),
+            bytes_read_counter: 0,
             check_sum: None,
             using_dict: None,
-        })
-    }
-
-    pub fn reset(&mut self, source: impl Read) -> Result<(), String> {
-        let (frame, header_size) = frame::read_frame_header(source)?;
-        let window_size = frame.header.window_size()?;
-        frame.check_valid()?;
-
-        if window_size > MAX_WINDOW_SIZE {
-            return Err(format!(
-                "Dont support window_sizes (requested: {}) over: {}",
-                window_size, MAX_WINDOW_SIZE
-            ));
+            truncated: false,
         }
+    }
 
+    /// Fails if the window of the frame can not be allocated, the state must not be used for decoding then
+    fn reset(
+        &mut self,
+        frame: frame::Frame,
+        header_size: u8,
+        window_size: usize,
+    ) -> Result<(), String> {
+        self.decoder_scratch
+            .reset(window_size, frame.header.descriptor.single_segment_flag())?;
         self.frame = frame;
         self.frame_finished = false;
         self.block_counter = 0;
-        self.decoder_scratch.reset(window_size as usize);
         self.bytes_read_counter = u64::from(header_size);
         self.check_sum = None;
         self.using_dict = None;
+        self.truncated = false;
         Ok(())
     }
+
+    fn set_checksum(&mut self, check_sum: u32) {
+        self.check_sum = Some(check_sum);
+        log_debug!("Read frame checksum {:08x}", check_sum);
+        // comparing needs a pass over the undrained bytes, only do that if someone is listening
+        #[cfg(feature = "log")]
+        if log::log_enabled!(log::Level::Debug) && self.decoder_scratch.buffer.compute_hash {
+            let calculated = match self.decoder_scratch.buffer.current_hash() {
+                Some(hash) => hash as u32,
+                None => return,
+            };
+            if calculated == check_sum {
+                log::debug!("Frame checksum matches the decoded content");
+            } else {
+                log::debug!(
+                    "Frame checksum mismatch, the decoded content hashes to {:08x}",
+                    calculated
+                );
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn log_block(&self, block_header: &crate::blocks::block::BlockHeader) {
+        log_trace!(
+            "Decoded {} block {} with {} bytes of content, {} bytes decoded so far",
+            block_header.block_type,
+            self.block_counter,
+            block_header.content_size,
+            self.decoder_scratch.buffer.total_output()
+        );
+    }
 }
 
+/// Reads and validates the frame header. See check_frame
+fn read_checked_frame_header(
+    source: impl Read,
+    allow_reserved_bits: bool,
+    max_window_size: u64,
+) -> Result<(frame::Frame, u8, u64), FrameDecoderError> {
+    let (frame, header_size) = frame::read_frame_header(source)?;
+    let window_size = check_frame(&frame, allow_reserved_bits, max_window_size)?;
+    Ok((frame, header_size, window_size))
+}
+
+/// Validates the frame header and returns its window size, see compute_window_size
+fn check_frame(
+    frame: &frame::Frame,
+    allow_reserved_bits: bool,
+    max_window_size: u64,
+) -> Result<u64, FrameDecoderError> {
+    let window_size = compute_window_size(&frame.header, max_window_size)?;
+    if allow_reserved_bits {
+        frame.check_valid_allow_reserved()
+    } else {
+        frame.check_valid()
+    }
+    .map_err(FrameDecoderError::invalid_init)?;
+    Ok(window_size)
+}
+
+/// The window the decoder allocates for a frame with this header. Every frame goes through here before any buffers get
+/// allocated, so the window is checked against the maximum no matter if it comes from the window descriptor or,
+/// for single segment frames, from the content size
+fn compute_window_size(
+    header: &frame::FrameHeader,
+    max_window_size: u64,
+) -> Result<u64, FrameDecoderError> {
+    let window_size = header
+        .window_size()
+        .map_err(FrameDecoderError::invalid_init)?;
+    if window_size > max_window_size {
+        return Err(FrameDecoderError::invalid_init(format!(
+            "Dont support window_sizes (requested: {}) over: {}",
+            window_size, max_window_size
+        )));
+    }
+    Ok(window_size)
+}
+
+/// How much of the frame content size given in the header is worth reserving up front. The header can claim
+/// any size, but every block needs at least 4 bytes of input (its header and one RLE byte) for at most 128kb of output
+fn plausible_content_size(content_size: u64, input_len: usize) -> usize {
+    let max_output = (input_len as u64 / 4).saturating_mul(u64::from(ABSOLUTE_MAXIMUM_BLOCK_SIZE));
+    u64::min(content_size, max_output) as usize
+}
+
+/// Length of the skippable frame at the start of input, if there is one
+fn skippable_frame_len(input: &[u8]) -> Option<usize> {
+    if input.len() < 8 {
+        return None;
+    }
+    let magic_num = u32::from_le_bytes(input[..4].try_into().expect("8 bytes were checked"));
+    if !(frame::SKIPPABLE_MAGIC_NUM_MIN..=frame::SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num) {
+        return None;
+    }
+    let size = u32::from_le_bytes(input[4..8].try_into().expect("8 bytes were checked"));
+    Some(8 + size as usize)
+}
+
+/// Position of the first zstd or skippable magic number in input
+fn find_next_magic_num(input: &[u8]) -> Option<usize> {
+    input.windows(4).position(|window| {
+        let magic_num = u32::from_le_bytes(window.try_into().expect("windows are 4 bytes"));
+        magic_num == frame::MAGIC_NUM
+            || (frame::SKIPPABLE_MAGIC_NUM_MIN..=frame::SKIPPABLE_MAGIC_NUM_MAX)
+                .contains(&magic_num)
+    })
+}
+
+/// Checks that the first block of a body can belong to a frame with this header.
+/// No block may be bigger than the window (or 128kb), and the decoded size of raw and rle blocks is known up front so
+/// it can be compared to the frame content size
+fn check_first_block(
+    header: &frame::FrameHeader,
+    window_size: u64,
+    body: &[u8],
+) -> Result<(), FrameDecoderError> {
+    let mut block_dec = decoding::block_decoder::new();
+    let (block_header, _) = block_dec
+        .read_block_header(body)
+        .map_err(BlockDecoderError::into_header_error)?;
+
+    let max_block_size = u64::min(window_size, u64::from(ABSOLUTE_MAXIMUM_BLOCK_SIZE));
+    let block_size = match block_header.block_type {
+        BlockType::Compressed => block_header.content_size,
+        _ => block_header.decompressed_size,
+    };
+    if u64::from(block_size) > max_block_size {
+        return Err(FrameDecoderError::invalid_init(format!(
+            "First block of the body is {} bytes, but the header only allows blocks up to {} bytes",
+            block_size, max_block_size
+        )));
+    }
+    // frames without a content size in the header return an error here
+    if let Ok(content_size) = header.frame_content_size() {
+        let known_size = match block_header.block_type {
+            BlockType::Compressed => 0,
+            _ => u64::from(block_header.decompressed_size),
+        };
+        if known_size > content_size {
+            return Err(FrameDecoderError::invalid_init(format!(
+                "First block of the body decodes to {} bytes, but the header declares a content size of {} bytes",
+                known_size, content_size
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Same as FrameDecoder::new, nothing is allocated until the first frame is decoded
 impl Default for FrameDecoder {
     fn default() -> Self {
         Self::new()
@@ -128,21 +308,114 @@ impl FrameDecoder {
     pub fn new() -> FrameDecoder {
         FrameDecoder {
             state: None,
+            #[cfg(feature = "dictionary")]
             dicts: HashMap::new(),
+            #[cfg(feature = "dictionary")]
+            reset_dict: None,
+            truncation_policy: TruncationPolicy::Strict,
+            force_checksum: false,
+            allow_reserved_bits: false,
+            zero_window_memory: false,
+            max_window_size: MAX_WINDOW_SIZE,
+            ring_buffer: None,
+            new_content_hasher: None,
+        }
+    }
+
+    /// Like new, but allocates the ringbuffer for the window of multi-segment frames right away with room for capacity
+    /// bytes. If that covers the window and everything that is decoded before it gets collected, the buffer never has
+    /// to grow while decoding. Like any ringbuffer of the decoder it is reused by reset.
+    ///
+    /// This allocation is not zeroed even if set_zero_window_memory is used later. Panics if it fails, like Vec::with_capacity
+    pub fn with_initial_capacity(capacity: usize) -> FrameDecoder {
+        let mut ring = RingBuffer::new();
+        if let Err(e) = ring.reserve(capacity) {
+            panic!("{}", e);
+        }
+        let mut decoder = FrameDecoder::new();
+        decoder.use_ring_buffer(ring);
+        decoder
+    }
+
+    /// Calculate the checksum of the decoded content even for frames that do not contain a checksum.
+    /// By default the checksum is only calculated if the frame has one to compare against. This is kept across resets
+    pub fn set_force_checksum(&mut self, force: bool) {
+        self.force_checksum = force;
+    }
+
+    /// Hash the decoded content of the following frames with a new H::default() per frame instead of the built-in XXH64,
+    /// e.g. to use a faster implementation of it. Everything that reports hashes or checksums uses H then,
+    /// if H does not calculate XXH64 they will not match the checksums stored in the frames. This is kept across resets
+    pub fn use_content_hasher<H: ContentHasher + Default + Send + Sync + 'static>(&mut self) {
+        self.new_content_hasher = Some(|| Box::new(H::default()));
+    }
+
+    /// Accept frames that have the reserved bit of the frame descriptor set instead of failing in reset()/init().
+    /// The format requires this bit to be zero, but encoders for a newer revision might use it for something
+    /// older decoders can ignore. Defaults to false. This is kept across resets
+    pub fn set_allow_reserved_bits(&mut self, allow: bool) {
+        self.allow_reserved_bits = allow;
+    }
+
+    /// Zero the memory the ringbuffer of multi-segment frames allocates for the window, so it never contains
+    /// leftover heap contents, not even in the parts that were not written yet. Those are never read or returned
+    /// either way. Defaults to false. This is kept across resets
+    pub fn set_zero_window_memory(&mut self, zero: bool) {
+        self.zero_window_memory = zero;
+    }
+
+    /// The biggest window a frame may ask for, frames with a bigger one fail in reset()/init() before anything
+    /// is allocated. Defaults to 100MiB. Frames compressed with zstd --long can use bigger windows (--long=27 is 128MiB),
+    /// the window of such a frame has to be kept in memory while decoding it. This is kept across resets.
+    ///
+    /// reset() allocates the whole window of a frame up front, so this is how much memory a single frame can make the
+    /// decoder allocate. Values above isize::MAX, the biggest allocation possible at all, are lowered to it. Windows
+    /// below that which still can not be allocated make reset() fail with FrameDecoderError::FailedToAllocate
+    pub fn set_max_window_size(&mut self, max: u64) {
+        self.max_window_size = u64::min(max, isize::MAX as u64);
+    }
+
+    /// Makes the decoder use this ringbuffer for the window of multi-segment frames instead of allocating its own,
+    /// e.g. to take buffers from a pool. The buffer is cleared and grown to the window size of the frame as needed.
+    ///
+    /// If a frame is currently being decoded its decoded bytes are dropped, so call this before reset/init
+    pub fn use_ring_buffer(&mut self, ring: RingBuffer) {
+        match &mut self.state {
+            Some(state) => state.decoder_scratch.buffer.use_ring_buffer(ring),
+            None => self.ring_buffer = Some(ring),
         }
     }
 
+    /// Takes the ringbuffer out of the decoder so it can be given back to a pool. It is returned empty.
+    /// The current frame can not be decoded any further after this, the next reset/init makes the decoder usable again
+    pub fn take_ring_buffer(&mut self) -> RingBuffer {
+        match &mut self.state {
+            Some(state) => state.decoder_scratch.buffer.take_ring_buffer(),
+            None => self.ring_buffer.take().unwrap_or_default(),
+        }
+    }
+
+    /// Sets what happens if the source ends in the middle of a frame, see TruncationPolicy. This is kept across resets
+    pub fn set_truncation_policy(&mut self, policy: TruncationPolicy) {
+        self.truncation_policy = policy;
+    }
+
     /// init() will allocate all needed buffers if it is the first time this decoder is used
     /// else they just reset these buffers with not further allocations
     ///
     /// Note that all bytes currently in the decodebuffer from any previous frame will be lost. Collect them with collect()/collect_to_writer()
     ///
     /// equivalent to reset()
-    pub fn init(&mut self, source: impl Read) -> Result<(), String> {
+    pub fn init(&mut self, source: impl Read) -> Result<(), FrameDecoderError> {
         self.reset(source)
     }
     /// Like init but provides the dict to use for the next frame
-    pub fn init_with_dict(&mut self, source: impl Read, dict: &[u8]) -> Result<(), String> {
+    #[cfg(feature = "dictionary")]
+    pub fn init_with_dict(
+        &mut self,
+        source: impl Read,
+        dict: &[u8],
+    ) -> Result<(), FrameDecoderError> {
         self.reset_with_dict(source, dict)
     }
 
@@ -152,33 +425,280 @@ impl FrameDecoder {
     /// Note that all bytes currently in the decodebuffer from any previous frame will be lost. Collect them with collect()/collect_to_writer()
     ///
     /// equivalent to init()
-    pub fn reset(&mut self, source: impl Read) -> Result<(), String> {
-        match &mut self.state {
-            Some(s) => s.reset(source),
-            None => {
-                self.state = Some(FrameDecoderState::new(source)?);
-                Ok(())
-            }
+    ///
+    /// If the source ends before the frame header is complete this returns FrameDecoderError::UnexpectedEof
+    pub fn reset(&mut self, source: impl Read) -> Result<(), FrameDecoderError> {
+        let (frame, header_size, window_size) =
+            read_checked_frame_header(source, self.allow_reserved_bits, self.max_window_size)?;
+        self.start_frame(frame, header_size, window_size)
+    }
+
+    fn start_frame(
+        &mut self,
+        frame: frame::Frame,
+        header_size: u8,
+        window_size: u64,
+    ) -> Result<(), FrameDecoderError> {
+        let window_size = usize::try_from(window_size).map_err(|_| {
+            FrameDecoderError::invalid_init(format!(
+                "Window size {} does not fit into the address space of this platform",
+                window_size
+            ))
+        })?;
+        let state = self.state.get_or_insert_with(FrameDecoderState::new);
+        let buffer = &mut state.decoder_scratch.buffer;
+        buffer.set_zero_new_allocations(self.zero_window_memory);
+        if let Some(ring) = self.ring_buffer.take() {
+            buffer.use_ring_buffer(ring);
+        }
+        // reset reserves the window, in the ringbuffer given to use_ring_buffer if there was one
+        if let Err(e) = state.reset(frame, header_size, window_size) {
+            self.state = None;
+            return Err(FrameDecoderError::FailedToAllocate(e));
+        }
+        let state = self.state.as_mut().expect("the state was just reset");
+        log_debug!(
+            "Frame header of {} bytes: window size {}, content size {:?}, dictionary id {:?}, checksum {}",
+            header_size,
+            window_size,
+            state.frame.header.frame_content_size().ok(),
+            state.frame.header.dictiornary_id().ok().flatten(),
+            state.frame.header.descriptor.content_checksum_flag()
+        );
+        state.decoder_scratch.buffer.compute_hash =
+            self.force_checksum || state.frame.header.descriptor.content_checksum_flag();
+        if let Some(new_hasher) = self.new_content_hasher {
+            state.decoder_scratch.buffer.hash = ContentHash::Custom(new_hasher());
+        }
+        Ok(())
+    }
+
+    /// Decodes a frame whose header is stored separately from its body. The body are the blocks
+    /// (and the checksum if the header has the checksum flag set) that would follow the header in a normal frame.
+    /// The header is checked against the first block of the body before anything is decoded.
+    ///
+    /// Like decode_blocks with BlockDecodingStrategy::All, the content can be collected afterwards with collect()/collect_to_writer().
+    /// Returns how many bytes of the body belonged to the frame
+    pub fn decode_headerless(
+        &mut self,
+        header: &frame::FrameHeader,
+        body: &[u8],
+    ) -> Result<usize, FrameDecoderError> {
+        let frame = frame::Frame::from_header(header.clone());
+        let window_size = check_frame(&frame, self.allow_reserved_bits, self.max_window_size)?;
+        check_first_block(header, window_size, body)?;
+
+        // the header was not read from the body so it does not count towards bytes_read_from_source
+        self.start_frame(frame, 0, window_size)?;
+        let mut source = body;
+        self.decode_blocks(&mut source, BlockDecodingStrategy::All)?;
+        Ok(body.len() - source.len())
+    }
+
+    /// Decodes a stream of blocks that has no frame around it at all, e.g. stored by a container format that keeps the
+    /// window size itself. Decoding ends with the block that has the last block flag set, the blocks are decoded like
+    /// those of a multi-segment frame without checksum or dictionary.
+    ///
+    /// window_size has to fit into the maximum of set_max_window_size
+    pub fn decode_blocks_raw(
+        &mut self,
+        blocks: &[u8],
+        window_size: u64,
+    ) -> Result<Vec<u8>, FrameDecoderError> {
+        if window_size > self.max_window_size {
+            return Err(FrameDecoderError::invalid_init(format!(
+                "Dont support window_sizes (requested: {}) over: {}",
+                window_size, self.max_window_size
+            )));
         }
+        let frame = frame::Frame::from_header(frame::FrameHeader::without_fields());
+        self.start_frame(frame, 0, window_size)?;
+        let mut source = blocks;
+        self.decode_blocks(&mut source, BlockDecodingStrategy::All)?;
+        let mut out = Vec::new();
+        self.collect_to_writer(&mut out)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        Ok(out)
     }
 
     /// Like reset but provides the dict to use for the next frame
-    pub fn reset_with_dict(&mut self, source: impl Read, dict: &[u8]) -> Result<(), String> {
+    #[cfg(feature = "dictionary")]
+    pub fn reset_with_dict(
+        &mut self,
+        source: impl Read,
+        dict: &[u8],
+    ) -> Result<(), FrameDecoderError> {
         self.reset(source)?;
+        let dict = Dictionary::decode_dict(dict).map_err(FrameDecoderError::invalid_init)?;
         if let Some(state) = &mut self.state {
-            let id = state.decoder_scratch.load_dict(dict)?;
-            state.using_dict = Some(id);
+            state.decoder_scratch.use_dict(&dict);
+            state.using_dict = Some(dict.id);
+        };
+        self.reset_dict = Some(dict);
+        Ok(())
+    }
+
+    /// Like reset but primes the next frame with the dictionary the current frame was decoded with, which was given to
+    /// reset_with_dict or added with add_dict. The dictionary is not parsed again, its tables and content are applied
+    /// to the new frame like at the start of the current one, no matter what the current frame left in the window.
+    ///
+    /// Fails with InvalidData if the current frame did not use a dictionary or the next frame declares another
+    /// dictionary id
+    #[cfg(feature = "dictionary")]
+    pub fn reset_keep_dict(&mut self, source: impl Read) -> Result<(), FrameDecoderError> {
+        let id = match &self.state {
+            None => return Err(FrameDecoderError::NotYetInitialized),
+            Some(state) => state.using_dict.ok_or_else(|| {
+                FrameDecoderError::invalid_init(
+                    "The current frame did not use a dictionary that could be kept".to_owned(),
+                )
+            })?,
+        };
+        self.reset(source)?;
+
+        let dict = match &self.reset_dict {
+            Some(dict) if dict.id == id => dict,
+            _ => self
+                .dicts
+                .get(&id)
+                .ok_or_else(|| FrameDecoderError::DictNotProvided {
+                    dict_id: id,
+                    loaded: self.dict_ids(),
+                })?,
         };
+        let state = self.state.as_mut().expect("reset succeeded");
+        if let Some(frame_dict) = state
+            .frame
+            .header
+            .dictiornary_id()
+            .map_err(FrameDecoderError::invalid_init)?
+        {
+            if frame_dict != id {
+                return Err(FrameDecoderError::invalid_init(format!(
+                    "Frame needs the dictionary with id {} but the decoder keeps the one with id {}",
+                    frame_dict, id
+                )));
+            }
+        }
+        state.decoder_scratch.use_dict(dict);
+        state.using_dict = Some(id);
+        Ok(())
+    }
+
+    /// Makes data the content that precedes the frame, like the content of a raw content dictionary.
+    /// Back-references of the frame that reach further back than its own output resolve into these bytes.
+    ///
+    /// Has to be called after reset and before decoding blocks. Only the last window_size bytes of data can be
+    /// referenced, so only those are kept.
+    ///
+    /// The content of a dictionary takes the same place, so this fails if the frame names a dictionary or one was
+    /// given with reset_with_dict, instead of one of them silently replacing the other.
+    pub fn prime_window(&mut self, data: &[u8]) -> Result<(), FrameDecoderError> {
+        let state = match &mut self.state {
+            None => return Err(FrameDecoderError::NotYetInitialized),
+            Some(s) => s,
+        };
+        let frame_dict = state
+            .frame
+            .header
+            .dictiornary_id()
+            .map_err(FrameDecoderError::invalid_init)?;
+        if let Some(id) = frame_dict.or(state.using_dict) {
+            return Err(FrameDecoderError::invalid_init(format!(
+                "Can not prime the window of a frame that uses the dictionary with id {}",
+                id
+            )));
+        }
+        let buffer = &mut state.decoder_scratch.buffer;
+        let keep = usize::min(data.len(), buffer.window_size);
+        buffer.dict_content.clear();
+        buffer
+            .dict_content
+            .extend_from_slice(&data[data.len() - keep..]);
         Ok(())
     }
 
     /// Add a dict to the FrameDecoder that can be used when needed. The FrameDecoder uses the appropriate one dynamically
+    #[cfg(feature = "dictionary")]
     pub fn add_dict(&mut self, raw_dict: &[u8]) -> Result<(), String> {
         let dict = Dictionary::decode_dict(raw_dict)?;
         self.dicts.insert(dict.id, dict);
         Ok(())
     }
 
+    /// Checks whether a frame with this header could be decoded, without touching the current frame: the window has
+    /// to fit into the maximum of set_max_window_size and a dictionary the frame needs has to be added with add_dict already.
+    /// Dictionaries passed to reset_with_dict are not known here. The blocks are not looked at, so decoding can still fail
+    pub fn can_decode(&self, header: &frame::FrameHeader) -> Result<(), FrameDecoderError> {
+        if header.descriptor.reserved_flag() && !self.allow_reserved_bits {
+            return Err(FrameDecoderError::invalid_init(
+                "Reserved Flag set. Must be zero".to_owned(),
+            ));
+        }
+        compute_window_size(header, self.max_window_size)?;
+
+        match header
+            .dictiornary_id()
+            .map_err(FrameDecoderError::invalid_init)?
+        {
+            Some(dict_id) if !self.has_dict(dict_id) => Err(FrameDecoderError::DictNotProvided {
+                dict_id,
+                loaded: self.dict_ids(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "dictionary")]
+    fn has_dict(&self, dict_id: u32) -> bool {
+        self.dicts.contains_key(&dict_id)
+    }
+
+    #[cfg(not(feature = "dictionary"))]
+    fn has_dict(&self, _dict_id: u32) -> bool {
+        false
+    }
+
+    /// The ids of the dictionaries added with add_dict, sorted
+    fn dict_ids(&self) -> Vec<u32> {
+        #[cfg(feature = "dictionary")]
+        let mut ids: Vec<u32> = self.dicts.keys().copied().collect();
+        #[cfg(not(feature = "dictionary"))]
+        let mut ids: Vec<u32> = Vec::new();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Sets up the dictionary the current frame needs, unless it has none or it was given to reset_with_dict.
+    /// Without the dictionary feature every frame that needs one fails with DictNotProvided
+    fn load_needed_dict(&mut self) -> Result<(), FrameDecoderError> {
+        let state = match &mut self.state {
+            None => return Err(FrameDecoderError::NotYetInitialized),
+            Some(s) => s,
+        };
+        let id = match state.frame.header.dictiornary_id() {
+            Ok(Some(id)) => id,
+            Ok(None) => return Ok(()),
+            //should never happen we check this directly after decoding the frame header
+            Err(e) => return Err(FrameDecoderError::invalid_init(e)),
+        };
+        if let Some(using_id) = state.using_dict {
+            //happy
+            debug_assert!(id == using_id);
+            return Ok(());
+        }
+        #[cfg(feature = "dictionary")]
+        if let Some(dict) = self.dicts.get(&id) {
+            state.decoder_scratch.use_dict(dict);
+            state.using_dict = Some(id);
+            return Ok(());
+        }
+        Err(FrameDecoderError::DictNotProvided {
+            dict_id: id,
+            loaded: self.dict_ids(),
+        })
+    }
+
     /// Returns how many bytes the frame contains after decompression
     pub fn content_size(&self) -> Option<u64> {
         let state = match &self.state {
@@ -186,10 +706,30 @@ impl FrameDecoder {
             Some(s) => s,
         };
 
-        match state.frame.header.frame_content_size() {
-            Err(_) => None,
-            Ok(x) => Some(x),
-        }
+        state.frame.header.frame_content_size().ok()
+    }
+
+    /// The window size of the current frame, from its window descriptor or for single segment frames its content size.
+    /// None if no frame has been started
+    pub fn window_size(&self) -> Option<u64> {
+        self.state
+            .as_ref()
+            .map(|state| state.decoder_scratch.buffer.window_size as u64)
+    }
+
+    /// The id of the dictionary the current frame needs, None if its header has none or no frame has been started
+    pub fn dictionary_id(&self) -> Option<u32> {
+        self.state
+            .as_ref()
+            .and_then(|state| state.frame.header.dictiornary_id().ok().flatten())
+    }
+
+    /// Whether the current frame has the content checksum flag set, so its last block is followed by the checksum
+    #[cfg(feature = "streaming")]
+    pub(crate) fn has_checksum(&self) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|state| state.frame.header.descriptor.content_checksum_flag())
     }
 
     /// Returns the checksum that was read from the data. Only available after all bytes have been read. It is the last 4 bytes of a zstd-frame
@@ -204,16 +744,62 @@ impl FrameDecoder {
 
     /// Returns the checksum that was calculated while decoding.
     /// Only a sensible value after all decoded bytes have been collected/read from the FrameDecoder
+    ///
+    /// equivalent to computed_checksum()
     pub fn get_calculated_checksum(&self) -> Option<u32> {
+        self.computed_checksum()
+    }
+
+    /// Returns the lower 32 bits of the XXH64 hash calculated over the decoded content.
+    /// This is only calculated if the frame has the content checksum flag set or set_force_checksum(true) was used,
+    /// otherwise this returns None.
+    /// The value is only complete after all decoded bytes have been collected/read from the FrameDecoder
+    pub fn computed_checksum(&self) -> Option<u32> {
         let state = match &self.state {
             None => return None,
             Some(s) => s,
         };
+        if !state.decoder_scratch.buffer.compute_hash {
+            return None;
+        }
         let cksum_64bit = state.decoder_scratch.buffer.hash.finish();
         //truncate to lower 32bit because reasons...
         Some(cksum_64bit as u32)
     }
 
+    /// Compares the checksum of the frame with the one calculated over its content, which has to be collected
+    /// completely. Frames without a checksum always pass
+    pub(crate) fn verify_checksum(&self) -> Result<(), FrameDecoderError> {
+        match (self.get_checksum_from_data(), self.computed_checksum()) {
+            (Some(expected), Some(calculated)) if expected != calculated => {
+                Err(InvalidDataError::ChecksumMismatch {
+                    expected,
+                    calculated,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the full 64 bit XXH64 hash over all bytes decoded from the current frame so far, in order.
+    /// Unlike computed_checksum this does not depend on how many bytes have been collected, so it can be used to
+    /// checkpoint the output after every decode_blocks call. Like computed_checksum this is only available if the
+    /// frame has the content checksum flag set or set_force_checksum(true) was used.
+    ///
+    /// This hashes the bytes that have not been collected yet on every call, which can be up to the window size.
+    /// A hasher set with use_content_hasher can not do that, with one this is None until all bytes were collected
+    pub fn current_hash(&self) -> Option<u64> {
+        let state = match &self.state {
+            None => return None,
+            Some(s) => s,
+        };
+        if !state.decoder_scratch.buffer.compute_hash {
+            return None;
+        }
+        state.decoder_scratch.buffer.current_hash()
+    }
+
     /// Counter for how many bytes have been consumed while deocidng the frame
     pub fn bytes_read_from_source(&self) -> u64 {
         let state = match &self.state {
@@ -231,6 +817,10 @@ impl FrameDecoder {
             None => return true,
             Some(s) => s,
         };
+        if state.truncated {
+            // nothing more can be decoded from this frame
+            return true;
+        }
         if state.frame.header.descriptor.content_checksum_flag() {
             state.frame_finished && state.check_sum.is_some()
         } else {
@@ -238,8 +828,28 @@ impl FrameDecoder {
         }
     }
 
-    /// Counter for how many blocks have already been decoded
-    pub fn blocks_decoded(&self) -> usize {
+    /// The biggest distance a match of the current frame has reached back so far, including matches into a dictionary.
+    /// This can be much smaller than the window size from the header, e.g. to choose a window when re-encoding.
+    /// 0 if no match has been executed yet
+    pub fn max_offset_used(&self) -> u64 {
+        let state = match &self.state {
+            None => return 0,
+            Some(s) => s,
+        };
+        u64::from(state.decoder_scratch.max_offset)
+    }
+
+    /// How long the stages of decoding took for the current frame so far, and how much each of them produced.
+    /// None if no frame has been started. The counters are cleared by reset
+    #[cfg(feature = "profiling")]
+    pub fn profile(&self) -> Option<&crate::decoding::profile::DecodeProfile> {
+        self.state
+            .as_ref()
+            .map(|state| &state.decoder_scratch.profile)
+    }
+
+    /// Counter for how many blocks of the current frame have already been decoded
+    pub fn blocks_decoded(&self) -> u64 {
         let state = match &self.state {
             None => return 0,
             Some(s) => s,
@@ -247,44 +857,60 @@ impl FrameDecoder {
         state.block_counter
     }
 
+    /// Counter for how many bytes the blocks of the current frame have been decoded to so far,
+    /// including bytes that have not been collected yet. Together with blocks_decoded this can be
+    /// polled between decode_blocks calls to report progress
+    pub fn bytes_decoded(&self) -> u64 {
+        let state = match &self.state {
+            None => return 0,
+            Some(s) => s,
+        };
+        state.decoder_scratch.buffer.total_output()
+    }
+
     /// Decodes blocks from a reader. It requires that the framedecoder has been initialized first.
     /// The Strategy influences how many blocks will be decoded before the function returns
     /// This is important if you want to manage memory consumption carefully. If you dont care
     /// about that you can just choose the strategy "All" and have all blocks of the frame decoded into the buffer
+    ///
+    /// If the source ends in the middle of the frame this returns FrameDecoderError::UnexpectedEof,
+    /// which tells what was being read and how many bytes were missing.
+    /// With TruncationPolicy::BestEffort it returns FrameDecoderError::Truncated instead and the partial output can be collected
     pub fn decode_blocks(
+        &mut self,
+        source: impl Read,
+        strat: BlockDecodingStrategy,
+    ) -> Result<bool, FrameDecoderError> {
+        let result = self.decode_blocks_strict(source, strat);
+        match result {
+            Err(FrameDecoderError::UnexpectedEof { .. })
+                if self.truncation_policy == TruncationPolicy::BestEffort =>
+            {
+                let state = self
+                    .state
+                    .as_mut()
+                    .expect("decoding can only start on an initialized decoder");
+                state.frame_finished = true;
+                state.truncated = true;
+                Err(FrameDecoderError::Truncated {
+                    bytes_recovered: state.decoder_scratch.buffer.total_output(),
+                })
+            }
+            result => result,
+        }
+    }
+
+    fn decode_blocks_strict(
         &mut self,
         mut source: impl Read,
         strat: BlockDecodingStrategy,
-    ) -> Result<bool, crate::errors::FrameDecoderError> {
+    ) -> Result<bool, FrameDecoderError> {
+        self.load_needed_dict()?;
         let state = match &mut self.state {
             None => return Err(crate::errors::FrameDecoderError::NotYetInitialized),
             Some(s) => s,
         };
 
-        match state.frame.header.dictiornary_id() {
-            Ok(Some(id)) => {
-                match state.using_dict {
-                    Some(using_id) => {
-                        //happy
-                        debug_assert!(id == using_id);
-                    }
-                    None => {
-                        let dict = match self.dicts.get(&id) {
-                            Some(dict) => dict,
-                            None => return Err(crate::errors::FrameDecoderError::DictNotProvided),
-                        };
-                        state.decoder_scratch.use_dict(dict);
-                        state.using_dict = Some(id);
-                    }
-                }
-            }
-            Ok(None) => {}
-            Err(e) => {
-                //should never happen we check this directly after decoding the frame header
-                return Err(crate::errors::FrameDecoderError::FailedToInitialize(e));
-            }
-        }
-
         let mut block_dec = decoding::block_decoder::new();
 
         let buffer_size_before = state.decoder_scratch.buffer.len();
@@ -295,10 +921,21 @@ impl FrameDecoder {
                 println!("Next Block: {}", state.block_counter);
                 println!("################");
             }
-            let (block_header, block_header_size) = match block_dec.read_block_header(&mut source) {
-                Ok(h) => h,
-                Err(m) => return Err(crate::errors::FrameDecoderError::FailedToReadBlockHeader(m)),
-            };
+            let blocks_before = state.block_counter;
+            let (block_header, block_header_size) = block_dec
+                .read_block_header(&mut source)
+                .map_err(|e| match e.into_header_error() {
+                    // a frame that is missing the flag goes on to read its checksum or whatever follows as a block
+                    FrameDecoderError::InvalidData(InvalidDataError::BlockHeader(m))
+                        if blocks_before > 0 =>
+                    {
+                        FrameDecoderError::invalid_block_header(format!(
+                            "{}. This follows block {} which was not marked as the last block of the frame",
+                            m, blocks_before
+                        ))
+                    }
+                    e => e,
+                })?;
             state.bytes_read_counter += u64::from(block_header_size);
 
             if crate::VERBOSE {
@@ -311,17 +948,13 @@ impl FrameDecoder {
                 );
             }
 
-            let bytes_read_in_block_body = match block_dec.decode_block_content(
-                &block_header,
-                &mut state.decoder_scratch,
-                &mut source,
-            ) {
-                Ok(h) => h,
-                Err(m) => return Err(crate::errors::FrameDecoderError::FailedToReadBlockBody(m)),
-            };
+            let bytes_read_in_block_body = block_dec
+                .decode_block_content(&block_header, &mut state.decoder_scratch, &mut source)
+                .map_err(BlockDecoderError::into_body_error)?;
             state.bytes_read_counter += bytes_read_in_block_body;
 
             state.block_counter += 1;
+            state.log_block(&block_header);
 
             if crate::VERBOSE {
                 println!("Output: {}", state.decoder_scratch.buffer.len());
@@ -331,16 +964,16 @@ impl FrameDecoder {
                 state.frame_finished = true;
                 if state.frame.header.descriptor.content_checksum_flag() {
                     let mut chksum = [0u8; 4];
-                    match source.read_exact(&mut chksum) {
-                        Err(_) => {
-                            return Err(crate::errors::FrameDecoderError::FailedToReadChecksum)
-                        }
-                        Ok(()) => {
-                            state.bytes_read_counter += 4;
-                            let chksum = u32::from_le_bytes(chksum);
-                            state.check_sum = Some(chksum);
-                        }
-                    };
+                    let bytes_read = read_until_full(&mut source, &mut chksum)
+                        .map_err(|_| FrameDecoderError::FailedToReadChecksum)?;
+                    if bytes_read < 4 {
+                        return Err(FrameDecoderError::UnexpectedEof {
+                            context: EofContext::Checksum,
+                            bytes_missing: 4 - bytes_read,
+                        });
+                    }
+                    state.bytes_read_counter += 4;
+                    state.set_checksum(u32::from_le_bytes(chksum));
                 }
                 break;
             }
@@ -348,7 +981,7 @@ impl FrameDecoder {
             match strat {
                 BlockDecodingStrategy::All => { /* keep going */ }
                 BlockDecodingStrategy::UptoBlocks(n) => {
-                    if state.block_counter - block_counter_before >= n {
+                    if state.block_counter - block_counter_before >= n as u64 {
                         break;
                     }
                 }
@@ -413,6 +1046,496 @@ impl FrameDecoder {
         }
     }
 
+    /// Decodes the whole frame at the start of input and appends its content to out. Bytes already in out are kept,
+    /// so decoding many frames into the same Vec reuses its capacity.
+    ///
+    /// Returns how many bytes of input belonged to the frame, the next frame (if any) starts there.
+    /// If the frame has a checksum it is compared to the decoded content, a mismatch fails with
+    /// InvalidDataError::ChecksumMismatch after the content was appended
+    pub fn decode_append(
+        &mut self,
+        input: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<usize, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        if let Some(size) = self.content_size() {
+            out.reserve(plausible_content_size(size, input.len()));
+        }
+        self.decode_blocks(&mut source, BlockDecodingStrategy::All)?;
+        self.collect_to_writer(&mut *out)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        self.verify_checksum()?;
+        Ok(input.len() - source.len())
+    }
+
+    /// Decodes the frame at the start of the concatenation of the slices, without concatenating them first.
+    /// Blocks and headers may be split over any number of slices.
+    ///
+    /// Returns the content of the frame and how many bytes of the slices (counted together) belonged to it.
+    /// The checksum is checked like in decode_append
+    pub fn decode_slices(
+        &mut self,
+        input: &[&[u8]],
+    ) -> Result<(Vec<u8>, usize), FrameDecoderError> {
+        let mut source = SliceChain::new(input);
+        self.reset(&mut source)?;
+        let mut out = Vec::new();
+        if let Some(size) = self.content_size() {
+            let input_len = input.iter().map(|slice| slice.len()).sum();
+            out.reserve(plausible_content_size(size, input_len));
+        }
+        self.decode_blocks(&mut source, BlockDecodingStrategy::All)?;
+        self.collect_to_writer(&mut out)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        self.verify_checksum()?;
+        Ok((out, source.bytes_read()))
+    }
+
+    /// Decodes the frame at the start of input, which has to decode to exactly exact_size bytes, e.g. because an
+    /// external index says so. All of the content is decoded into one flat Vec of that size, there is no ringbuffer
+    /// that has to wrap around even if the frame is a multi-segment frame.
+    ///
+    /// Fails with InvalidDataError::ContentSizeMismatch if the frame header declares a different size or the frame
+    /// decodes to a different size. Decoding stops after the first block that goes beyond exact_size.
+    /// The checksum is checked like in decode_append
+    pub fn decode_known_size(
+        &mut self,
+        input: &[u8],
+        exact_size: usize,
+    ) -> Result<Vec<u8>, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        let expected = exact_size as u64;
+        if let Some(declared) = self.content_size() {
+            if declared != expected {
+                return Err(InvalidDataError::ContentSizeMismatch {
+                    expected,
+                    decoded: declared,
+                }
+                .into());
+            }
+        }
+        let state = self.state.as_mut().expect("reset succeeded");
+        state
+            .decoder_scratch
+            .buffer
+            .use_flat_buffer(plausible_content_size(expected, input.len()))
+            .map_err(FrameDecoderError::FailedToAllocate)?;
+
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            let decoded = self
+                .state
+                .as_ref()
+                .map_or(0, |s| s.decoder_scratch.buffer.total_output());
+            if decoded > expected {
+                return Err(InvalidDataError::ContentSizeMismatch { expected, decoded }.into());
+            }
+        }
+        let output = self.collect().unwrap_or_default();
+        if output.len() != exact_size {
+            return Err(InvalidDataError::ContentSizeMismatch {
+                expected,
+                decoded: output.len() as u64,
+            }
+            .into());
+        }
+        self.verify_checksum()?;
+        Ok(output)
+    }
+
+    /// Decodes the frame at the start of input into target and returns how many bytes of target were written.
+    /// The box is never grown or reallocated, so one box can be reused for many frames of the same (maximal) size.
+    ///
+    /// Fails with TargetTooSmall if the frame header declares a bigger content size than target can hold or the frame
+    /// decodes to more bytes than that. The bytes of target after the returned length are left as they were.
+    /// The checksum is checked like in decode_append
+    pub fn decompress_into_boxed(
+        &mut self,
+        input: &[u8],
+        target: &mut Box<[u8]>,
+    ) -> Result<usize, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        if let Some(size) = self.content_size() {
+            if size > target.len() as u64 {
+                return Err(FrameDecoderError::TargetTooSmall);
+            }
+        }
+
+        let mut written = 0;
+        loop {
+            let finished = self.is_finished();
+            if !finished {
+                self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            }
+            written += self
+                .read(&mut target[written..])
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+            if written == target.len() && self.can_collect() > 0 {
+                return Err(FrameDecoderError::TargetTooSmall);
+            }
+            // the window is only given up by read() after the last block was decoded
+            if finished {
+                self.verify_checksum()?;
+                return Ok(written);
+            }
+        }
+    }
+
+    /// Decodes the frame at the start of input and calls progress after each block with how many bytes the frame has
+    /// been decoded to so far and the content size declared in the frame header, if there is one.
+    /// The callback only gets these values and can not touch the decoder, so it can not disturb the decoding.
+    ///
+    /// Decoded bytes are moved out of the decoder after every block, so only about a window is kept inside of it.
+    /// The checksum is checked like in decode_append
+    pub fn decode_with_progress(
+        &mut self,
+        input: &[u8],
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<Vec<u8>, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        let total = self.content_size();
+        let mut output = Vec::new();
+        if let Some(size) = total {
+            output.reserve(plausible_content_size(size, input.len()));
+        }
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            self.collect_to_writer(&mut output)
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+            progress(self.bytes_decoded(), total);
+        }
+        self.collect_to_writer(&mut output)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        self.verify_checksum()?;
+        Ok(output)
+    }
+
+    /// Decodes the frame at the start of input and returns its content together with the full 64 bit XXH64 hash over it.
+    /// The hash is calculated even if the frame has no checksum, as if set_force_checksum(true) was used for this frame.
+    /// If the frame has a checksum it is compared to the lower 32 bits of the hash and a mismatch is an error
+    pub fn decode_with_hash(&mut self, input: &[u8]) -> Result<(Vec<u8>, u64), FrameDecoderError> {
+        let mut source = input;
+        let force_checksum = self.force_checksum;
+        self.force_checksum = true;
+        let reset = self.reset(&mut source);
+        self.force_checksum = force_checksum;
+        reset?;
+
+        let mut output = Vec::new();
+        if let Some(size) = self.content_size() {
+            output.reserve(plausible_content_size(size, input.len()));
+        }
+        self.decode_blocks(&mut source, BlockDecodingStrategy::All)?;
+        self.collect_to_writer(&mut output)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+
+        let hash = self
+            .current_hash()
+            .expect("hashing was forced for this frame");
+        if let Some(expected) = self.get_checksum_from_data() {
+            if expected != hash as u32 {
+                return Err(InvalidDataError::ChecksumMismatch {
+                    expected,
+                    calculated: hash as u32,
+                }
+                .into());
+            }
+        }
+        Ok((output, hash))
+    }
+
+    /// Decodes as much of the frame at the start of input as possible, e.g. to rescue what is left of a damaged file.
+    /// Returns the content of all blocks before the one that failed, together with the error that stopped decoding.
+    /// What the failing block decoded before the error was noticed is left out, it may already be wrong.
+    ///
+    /// Corruption that does not make a block fail is only noticed by the checksum. If the frame has one and it does
+    /// not match, the whole content is returned with InvalidDataError::ChecksumMismatch
+    pub fn decode_best_effort(&mut self, input: &[u8]) -> (Vec<u8>, Option<FrameDecoderError>) {
+        let mut output = Vec::new();
+        let mut source = input;
+        if let Err(e) = self.reset(&mut source) {
+            return (output, Some(e));
+        }
+        while !self.is_finished() {
+            let complete_blocks = self.bytes_decoded();
+            if let Err(e) = self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1)) {
+                // the bytes after output.len() that are still in the window, up to the end of the last complete block
+                let buffer = &self
+                    .state
+                    .as_ref()
+                    .expect("initialized by reset")
+                    .decoder_scratch
+                    .buffer;
+                let undrained = (complete_blocks - output.len() as u64) as usize;
+                output.extend_from_slice(&buffer.contiguous()[..undrained]);
+                return (output, Some(e));
+            }
+            if self.collect_to_writer(&mut output).is_err() {
+                return (output, Some(FrameDecoderError::FailedToDrainDecodebuffer));
+            }
+        }
+        if self.collect_to_writer(&mut output).is_err() {
+            return (output, Some(FrameDecoderError::FailedToDrainDecodebuffer));
+        }
+        if let (Some(expected), Some(calculated)) = (
+            self.get_checksum_from_data(),
+            self.get_calculated_checksum(),
+        ) {
+            if expected != calculated {
+                return (
+                    output,
+                    Some(
+                        InvalidDataError::ChecksumMismatch {
+                            expected,
+                            calculated,
+                        }
+                        .into(),
+                    ),
+                );
+            }
+        }
+        (output, None)
+    }
+
+    /// Decodes the frame at the start of input up to start + len and returns the len bytes of its content from start on,
+    /// or fewer if the frame ends before that. Everything before start still has to be decoded because later matches
+    /// can refer to it, but it is dropped as soon as it is no longer needed for the window instead of being kept.
+    ///
+    /// The rest of the frame is not decoded, so its checksum is not checked either
+    pub fn decode_range(
+        &mut self,
+        input: &[u8],
+        start: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        let mut range = RangeWriter {
+            pos: 0,
+            start,
+            end: start.saturating_add(len as u64),
+            out: Vec::new(),
+        };
+        while !self.is_finished() && self.bytes_decoded() < range.end {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            self.collect_to_writer(&mut range)
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        }
+        // the end of the range can still be in the window, which is not collected before the frame is finished
+        let state = self.state.as_ref().expect("initialized by reset");
+        range.take(&state.decoder_scratch.buffer.contiguous());
+        Ok(range.out)
+    }
+
+    /// Consumes the decoder and returns all decoded bytes that have not been collected yet. While the frame is not
+    /// finished collect() and read() keep the last window_size bytes in the decoder, so if the frame was only collected
+    /// before it was finished the returned bytes are the rest of its output and end with its complete window.
+    ///
+    /// This is meant for formats where the window at the end of one frame is the dictionary of the next one,
+    /// pass the returned bytes to prime_window after initializing the decoder with the next frame
+    pub fn into_window(mut self) -> Vec<u8> {
+        match &mut self.state {
+            None => Vec::new(),
+            Some(s) => s.decoder_scratch.buffer.drain(),
+        }
+    }
+
+    /// Decodes the next block of the current frame from input into out and returns how many bytes it decoded to,
+    /// or None if the frame is already finished. input is advanced past the block (and the checksum after the last block).
+    /// The decoder has to be initialized with reset first.
+    ///
+    /// out has to be able to hold the biggest block the frame may contain, which is the window size but at most 128kb.
+    /// Otherwise this fails with TargetTooSmall before anything is decoded. Only the window is kept in the decoder,
+    /// so this should not be mixed with collect() or read() on the same frame
+    pub fn next_block_into(
+        &mut self,
+        input: &mut &[u8],
+        out: &mut [u8],
+    ) -> Result<Option<usize>, FrameDecoderError> {
+        let state = self
+            .state
+            .as_ref()
+            .ok_or(FrameDecoderError::NotYetInitialized)?;
+        if self.is_finished() {
+            return Ok(None);
+        }
+        let max_block_size = usize::min(
+            state.decoder_scratch.buffer.window_size,
+            ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize,
+        );
+        if out.len() < max_block_size {
+            return Err(FrameDecoderError::TargetTooSmall);
+        }
+
+        let decoded_before = self.bytes_decoded();
+        let finished = self.decode_blocks(&mut *input, BlockDecodingStrategy::UptoBlocks(1))?;
+        let produced = (self.bytes_decoded() - decoded_before) as usize;
+        if produced > out.len() {
+            return Err(FrameDecoderError::TargetTooSmall);
+        }
+
+        let buffer = &mut self
+            .state
+            .as_mut()
+            .expect("checked above")
+            .decoder_scratch
+            .buffer;
+        buffer.copy_newest(&mut out[..produced]);
+        // the bytes were handed out already, only the window has to stay for the next block
+        if finished {
+            buffer.drain_to_writer(std::io::sink())
+        } else {
+            buffer.drain_to_window_size_writer(std::io::sink())
+        }
+        .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        Ok(Some(produced))
+    }
+
+    /// Decodes the rest of the frame block by block and appends the decoded bytes to target, which stays owned by the
+    /// caller and keeps whatever it contained before. Returns how many bytes were appended.
+    ///
+    /// Only a window of bytes is kept inside the decoder in between blocks, everything else is moved to target
+    pub fn decode_to_ring_buffer(
+        &mut self,
+        mut source: impl Read,
+        target: &mut RingBuffer,
+    ) -> Result<usize, FrameDecoderError> {
+        if self.state.is_none() {
+            return Err(FrameDecoderError::NotYetInitialized);
+        }
+        let mut appended = 0;
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            appended += self
+                .collect_to_writer(&mut *target)
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        }
+        // the window is only given up after the last block
+        appended += self
+            .collect_to_writer(&mut *target)
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        Ok(appended)
+    }
+
+    /// Decodes the whole frame at the start of input and writes its content to writer, e.g. a File. Only the window is
+    /// kept in memory, the decoded bytes of every block go through a 1MiB buffer so they reach the writer in few large
+    /// writes instead of one per block. The writer is flushed at the end.
+    ///
+    /// Returns how many bytes of input belonged to the frame like decode_append, bytes_decoded() tells how many were
+    /// written. If the writer fails this returns FailedToDrainDecodebuffer. The checksum is checked like in decode_append,
+    /// the content has already been written when a mismatch is noticed
+    pub fn decode_to_writer(
+        &mut self,
+        input: &[u8],
+        writer: impl std::io::Write,
+    ) -> Result<usize, FrameDecoderError> {
+        let mut source = input;
+        self.reset(&mut source)?;
+        let mut writer = std::io::BufWriter::with_capacity(WRITE_BUFFER_SIZE, writer);
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            self.collect_to_writer(&mut writer)
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        }
+        self.collect_to_writer(&mut writer)
+            .and_then(|_| std::io::Write::flush(&mut writer))
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        self.verify_checksum()?;
+        Ok(input.len() - source.len())
+    }
+
+    /// Decodes the frame at the start of input without keeping its content and returns how many bytes of input belonged
+    /// to it, so the next frame starts there. Only the window is kept while decoding because later blocks reference it.
+    /// A skippable frame at the start of input is stepped over without decoding anything.
+    ///
+    /// The content checksum is not calculated, so get_calculated_checksum() is meaningless after this
+    pub fn skip_frame(&mut self, input: &[u8]) -> Result<usize, FrameDecoderError> {
+        if let Some(skippable_len) = skippable_frame_len(input) {
+            if skippable_len > input.len() {
+                return Err(FrameDecoderError::UnexpectedEof {
+                    context: crate::errors::EofContext::FrameHeader,
+                    bytes_missing: skippable_len - input.len(),
+                });
+            }
+            return Ok(skippable_len);
+        }
+
+        let mut source = input;
+        self.reset(&mut source)?;
+        if let Some(state) = &mut self.state {
+            state.decoder_scratch.buffer.compute_hash = false;
+        }
+        while !self.is_finished() {
+            self.decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))?;
+            self.collect_to_writer(std::io::sink())
+                .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        }
+        self.collect_to_writer(std::io::sink())
+            .map_err(|_| FrameDecoderError::FailedToDrainDecodebuffer)?;
+        Ok(input.len() - source.len())
+    }
+
+    /// Decodes the whole frame at the start of input and returns its content together with the number of bytes
+    /// after the frame that were not consumed. The trailing bytes are input[input.len() - trailing..]
+    pub fn decode_frame(&mut self, input: &[u8]) -> Result<(Vec<u8>, usize), FrameDecoderError> {
+        let mut out = Vec::new();
+        let consumed = self.decode_append(input, &mut out)?;
+        Ok((out, input.len() - consumed))
+    }
+
+    /// Decodes all frames in input and returns one result per zstd frame, skippable frames are left out.
+    ///
+    /// If a frame can not be decoded its error is put into the result and decoding continues at the next magic number
+    /// found after the start of the broken frame. Because the magic number can also appear inside of compressed data,
+    /// a corrupted frame may produce more than one error.
+    ///
+    /// Corruption that leaves the blocks intact is caught by the checksum of frames that have one. Such a frame is
+    /// reported as InvalidDataError::ChecksumMismatch and decoding continues right after it
+    pub fn decode_frames_skip_corrupt(
+        &mut self,
+        input: &[u8],
+    ) -> Vec<Result<Vec<u8>, FrameDecoderError>> {
+        let mut results = Vec::new();
+        let mut pos = 0;
+        while pos < input.len() {
+            let rest = &input[pos..];
+            if let Some(skippable_len) = skippable_frame_len(rest) {
+                if skippable_len <= rest.len() {
+                    pos += skippable_len;
+                    continue;
+                }
+            }
+
+            match self.decode_frame(rest) {
+                Ok((output, trailing)) => {
+                    results.push(Ok(output));
+                    pos = input.len() - trailing;
+                }
+                Err(
+                    e @ FrameDecoderError::InvalidData(InvalidDataError::ChecksumMismatch {
+                        ..
+                    }),
+                ) => {
+                    results.push(Err(e));
+                    // the whole frame could be read, so its end is known
+                    pos += self.bytes_read_from_source() as usize;
+                }
+                Err(e) => {
+                    results.push(Err(e));
+                    match find_next_magic_num(&rest[1..]) {
+                        Some(offset) => pos += 1 + offset,
+                        None => break,
+                    }
+                }
+            }
+        }
+        results
+    }
+
     /// Decodes as many blocks as possible from the source slice and reads from the decodebuffer into the target slice
     /// The source slice may contain only parts of a frame but must contain at least one full block to make progress
     ///
@@ -440,15 +1563,13 @@ impl FrameDecoder {
             let mut mt_source = source;
 
             if self.state.is_none() {
-                match self.init(&mut mt_source) {
-                    Ok(()) => {}
-                    Err(m) => return Err(crate::errors::FrameDecoderError::FailedToInitialize(m)),
-                }
+                self.init(&mut mt_source)?;
             }
+            self.load_needed_dict()?;
 
             //pseudo block to scope "state" so we can borrow self again after the block
             {
-                let mut state = match &mut self.state {
+                let state = match &mut self.state {
                     Some(s) => s,
                     None => panic!("Bug in library"),
                 };
@@ -459,57 +1580,24 @@ impl FrameDecoder {
                     && state.check_sum.is_none()
                 {
                     //this block is needed if the checksum were the only 4 bytes that were not included in the last decode_from_to call for a frame
-                    if mt_source.len() >= 4 {
-                        let chksum = mt_source[..4].try_into().expect("optimized away");
-                        state.bytes_read_counter += 4;
-                        let chksum = u32::from_le_bytes(chksum);
-                        state.check_sum = Some(chksum);
+                    if mt_source.len() < 4 {
+                        //the checksum is not complete yet, nothing can be consumed until it is
+                        return Ok((0, 0));
                     }
+                    let chksum = mt_source[..4].try_into().expect("optimized away");
+                    state.bytes_read_counter += 4;
+                    state.set_checksum(u32::from_le_bytes(chksum));
                     return Ok((4, 0));
                 }
 
-                match state.frame.header.dictiornary_id() {
-                    Ok(Some(id)) => {
-                        match state.using_dict {
-                            Some(using_id) => {
-                                //happy
-                                debug_assert!(id == using_id);
-                            }
-                            None => {
-                                let dict = match self.dicts.get(&id) {
-                                    Some(dict) => dict,
-                                    None => {
-                                        return Err(
-                                            crate::errors::FrameDecoderError::DictNotProvided,
-                                        )
-                                    }
-                                };
-                                state.decoder_scratch.use_dict(dict);
-                                state.using_dict = Some(id);
-                            }
-                        }
-                    }
-                    Ok(None) => {}
-                    Err(e) => {
-                        //should never happen we check this directly after decoding the frame header
-                        return Err(crate::errors::FrameDecoderError::FailedToInitialize(e));
-                    }
-                }
-
                 loop {
                     //check if there are enough bytes for the next header
                     if mt_source.len() < 3 {
                         break;
                     }
-                    let (block_header, block_header_size) =
-                        match block_dec.read_block_header(&mut mt_source) {
-                            Ok(h) => h,
-                            Err(m) => {
-                                return Err(
-                                    crate::errors::FrameDecoderError::FailedToReadBlockHeader(m),
-                                )
-                            }
-                        };
+                    let (block_header, block_header_size) = block_dec
+                        .read_block_header(&mut mt_source)
+                        .map_err(BlockDecoderError::into_header_error)?;
 
                     // check the needed size for the block before updating counters.
                     // If not enough bytes are in the source, the header will have to be read again, so act like we never read it in the first place
@@ -518,18 +1606,16 @@ impl FrameDecoder {
                     }
                     state.bytes_read_counter += u64::from(block_header_size);
 
-                    let bytes_read_in_block_body = match block_dec.decode_block_content(
-                        &block_header,
-                        &mut state.decoder_scratch,
-                        &mut mt_source,
-                    ) {
-                        Ok(h) => h,
-                        Err(m) => {
-                            return Err(crate::errors::FrameDecoderError::FailedToReadBlockBody(m))
-                        }
-                    };
+                    let bytes_read_in_block_body = block_dec
+                        .decode_block_content(
+                            &block_header,
+                            &mut state.decoder_scratch,
+                            &mut mt_source,
+                        )
+                        .map_err(BlockDecoderError::into_body_error)?;
                     state.bytes_read_counter += bytes_read_in_block_body;
                     state.block_counter += 1;
+                    state.log_block(&block_header);
 
                     if block_header.last_block {
                         state.frame_finished = true;
@@ -538,8 +1624,7 @@ impl FrameDecoder {
                             if mt_source.len() >= 4 {
                                 let chksum = mt_source[..4].try_into().expect("optimized away");
                                 state.bytes_read_counter += 4;
-                                let chksum = u32::from_le_bytes(chksum);
-                                state.check_sum = Some(chksum);
+                                state.set_checksum(u32::from_le_bytes(chksum));
                             }
                         }
                         break;
@@ -561,6 +1646,37 @@ impl FrameDecoder {
     }
 }
 
+/// Keeps the bytes written to it that are in start..end of everything written, for decode_range
+struct RangeWriter {
+    pos: u64,
+    start: u64,
+    end: u64,
+    out: Vec<u8>,
+}
+
+impl RangeWriter {
+    fn take(&mut self, data: &[u8]) {
+        let data_end = self.pos + data.len() as u64;
+        if data_end > self.start && self.pos < self.end {
+            let from = self.start.saturating_sub(self.pos) as usize;
+            let to = (u64::min(self.end, data_end) - self.pos) as usize;
+            self.out.extend_from_slice(&data[from..to]);
+        }
+        self.pos = data_end;
+    }
+}
+
+impl std::io::Write for RangeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.take(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Read bytes from the decode_buffer that are no longer needed. While the frame is not yet finished
 /// this will retain window_size bytes, else it will drain it completely
 impl std::io::Read for FrameDecoder {
@@ -576,3 +1692,29 @@ impl std::io::Read for FrameDecoder {
         }
     }
 }
+
+/// Decodes the first zstd frame in input into a new Vec. Bytes after that frame are ignored.
+///
+/// This is a shortcut for FrameDecoder::decode_frame, so a frame with a wrong checksum fails with
+/// InvalidDataError::ChecksumMismatch. For inputs with multiple concatenated frames use
+/// FrameDecoder::decode_frame in a loop, FrameDecoder::decode_frames_skip_corrupt or the StreamingDecoder.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, FrameDecoderError> {
+    let (output, _) = FrameDecoder::new().decode_frame(input)?;
+    Ok(output)
+}
+
+thread_local! {
+    static POOLED_DECODER: std::cell::RefCell<FrameDecoder> = std::cell::RefCell::new(FrameDecoder::new());
+}
+
+/// Like decompress but reuses a FrameDecoder that belongs to the calling thread, so after the first call
+/// the scratch space (window, huffman and fse tables) is not allocated again. Only the returned Vec is new.
+///
+/// The pooled decoder lives until the thread exits and keeps the buffers of the largest frame the thread has
+/// decoded so far. Threads that decode a few huge frames should use a FrameDecoder they can drop instead.
+pub fn decode_pooled(input: &[u8]) -> Result<Vec<u8>, FrameDecoderError> {
+    POOLED_DECODER.with(|decoder| {
+        let (output, _) = decoder.borrow_mut().decode_frame(input)?;
+        Ok(output)
+    })
+}
@@ -36,7 +36,7 @@ fn highest_bit_set(x: u32) -> u32 {
 }
 
 impl<'t> FSEDecoder<'t> {
-    pub fn new(table: &'t FSETable) -> FSEDecoder<'_> {
+    pub fn new(table: &'t FSETable) -> FSEDecoder<'t> {
         FSEDecoder { state: 0, table }
     }
 
@@ -53,6 +53,19 @@ impl<'t> FSEDecoder<'t> {
         Ok(())
     }
 
+    /// How many bits the next update_state call reads
+    pub fn state_bits(&self) -> u8 {
+        self.table.decode[self.state].num_bits
+    }
+
+    /// Like update_state but reads the bits with get_bits_unchecked. The caller has to use ensure_bits first
+    pub fn update_state_unchecked(&mut self, bits: &mut BitReaderReversed<'_>) {
+        let entry = self.table.decode[self.state];
+        let new_state = entry.base_line + bits.get_bits_unchecked(entry.num_bits) as usize;
+        assert!(new_state < self.table.decode.len());
+        self.state = new_state;
+    }
+
     pub fn update_state(&mut self, bits: &mut BitReaderReversed<'_>) -> Result<(), String> {
         let num_bits = self.table.decode[self.state].num_bits as usize;
         let add = bits.get_bits(num_bits)?;
@@ -84,10 +97,18 @@ impl FSETable {
     }
 
     //returns how many BYTEs (not bits) were read while building the decoder
+    //the decoders index the table with states of accuracy_log bits, so the table always has 1 << accuracy_log entries.
+    //If reading the probabilities fails the table is left uninitialized instead of keeping the old entries with the new accuracy_log
     pub fn build_decoder(&mut self, source: &[u8], max_log: u8) -> Result<usize, String> {
         self.accuracy_log = 0;
 
-        let bytes_read = self.read_probabilities(source, max_log)?;
+        let bytes_read = match self.read_probabilities(source, max_log) {
+            Ok(bytes_read) => bytes_read,
+            Err(e) => {
+                self.reset();
+                return Err(e);
+            }
+        };
         self.build_decoding_table();
 
         Ok(bytes_read)
@@ -27,6 +27,8 @@ pub struct Entry {
 }
 
 const MAX_MAX_NUM_BITS: u8 = 11;
+//the fse table for the compressed huffman weights can have an accuracy log of at most 6
+const MAX_WEIGHTS_ACC_LOG: u8 = 6;
 
 fn highest_bit_set(x: u32) -> u32 {
     assert!(x > 0);
@@ -99,9 +101,16 @@ impl HuffmanTable {
     pub fn build_decoder(&mut self, source: &[u8]) -> Result<u32, String> {
         self.decode.clear();
 
-        let bytes_used = self.read_weights(source)?;
-        self.build_table_from_weights()?;
-        Ok(bytes_used)
+        //the decoders index the table with states of max_num_bits bits, so a failed build must not leave a max_num_bits
+        //that does not match the table. max_num_bits == 0 marks the table as uninitialized
+        let result = self
+            .read_weights(source)
+            .and_then(|bytes_used| self.build_table_from_weights().map(|_| bytes_used));
+        if result.is_err() {
+            self.decode.clear();
+            self.max_num_bits = 0;
+        }
+        result
     }
 
     fn read_weights(&mut self, source: &[u8]) -> Result<u32, String> {
@@ -120,7 +129,7 @@ impl HuffmanTable {
                 //fse decompress weights
                 let bytes_used_by_fse_header = self
                     .fse_table
-                    .build_decoder(fse_stream, /*TODO find actual max*/ 100)?;
+                    .build_decoder(fse_stream, MAX_WEIGHTS_ACC_LOG)?;
 
                 if bytes_used_by_fse_header > header as usize {
                     return Err(format!("FSE table used more bytes: {} than were meant to be used for the whole stream of huffman weights", bytes_used_by_fse_header));
@@ -147,6 +156,13 @@ impl HuffmanTable {
                     ));
                 }
                 let compressed_weights = &compressed_weights[..compressed_length];
+                // the stop bit is the highest set bit of the last byte, without it the start of the stream is unknown
+                if compressed_weights.last().copied().unwrap_or(0) == 0 {
+                    return Err(
+                        "The bitstream of the huffman weights has no stop bit, its last byte is missing or 0"
+                            .to_owned(),
+                    );
+                }
                 let mut br = BitReaderReversed::new(compressed_weights);
 
                 bits_read += (bytes_used_by_fse_header + compressed_length) * 8;
@@ -162,7 +178,7 @@ impl HuffmanTable {
                 }
                 if skipped_bits > 8 {
                     //if more than 7 bits are 0, this is not the correct end of the bitstream. Either a bug or corrupted data
-                    return Err(format!("Padding at the end of the sequence_section was more than a byte long: {}. Probably cause by data corruption", skipped_bits));
+                    return Err(format!("Padding at the end of the huffman weights was more than a byte long: {}. Probably cause by data corruption", skipped_bits));
                 }
 
                 dec1.init_state(&mut br)?;
@@ -198,6 +214,13 @@ impl HuffmanTable {
                         );
                     }
                 }
+                // the final states can push the count over the limit without going through the check in the loop
+                if self.weights.len() > 255 {
+                    return Err(format!(
+                        "{} weights decoded but there can be at most 255. Stream is probably corrupted",
+                        self.weights.len()
+                    ));
+                }
             }
             _ => {
                 // weights are directly encoded
@@ -278,9 +301,6 @@ impl HuffmanTable {
             self.bits[symbol] = bits;
         }
 
-        self.bits[self.weights.len()] = max_bits + 1 - last_weight;
-        self.max_num_bits = max_bits;
-
         if max_bits > MAX_MAX_NUM_BITS {
             return Err(format!(
                 "max_bits derived from weights is: {} should be lower than: {} ",
@@ -288,6 +308,9 @@ impl HuffmanTable {
             ));
         }
 
+        self.bits[self.weights.len()] = max_bits + 1 - last_weight;
+        self.max_num_bits = max_bits;
+
         self.bit_ranks.clear();
         self.bit_ranks.resize((max_bits + 1) as usize, 0);
         for num_bits in &self.bits {
@@ -1,5 +1,44 @@
+//! A pure rust implementation of a zstd decoder.
+//!
+//! The easiest way to decode a frame that is already in memory is decompress:
+//! ```
+//! let compressed = std::fs::read("./decodecorpus_files/z000019.zst").unwrap();
+//! let decompressed = ruzstd::decompress(&compressed).unwrap();
+//! assert_eq!(decompressed, std::fs::read("./decodecorpus_files/z000019").unwrap());
+//! ```
+//!
+//! For decoding from an io::Read use the StreamingDecoder, for more control over the decoding process the FrameDecoder.
+
 #![deny(trivial_casts, trivial_numeric_casts, rust_2018_idioms)]
 
+/// debug_assert! that can be turned off with the "no-debug-asserts" feature, for builds that keep debug assertions
+/// enabled but want the speed. Only used for invariants that are also guaranteed by checks that are always on
+macro_rules! internal_debug_assert {
+    ($($arg:tt)*) => {
+        // the condition still gets type checked when the feature is enabled, but is never evaluated
+        if cfg!(not(feature = "no-debug-asserts")) {
+            debug_assert!($($arg)*);
+        }
+    };
+}
+
+/// log::debug! with the "log" feature. Without it nothing is emitted and the arguments are not evaluated
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*)
+    };
+}
+
+/// log::trace! with the "log" feature, see log_debug!
+macro_rules! log_trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::trace!($($arg)*)
+    };
+}
+
+pub mod archive;
 pub mod blocks;
 pub mod decoding;
 pub mod errors;
@@ -7,10 +46,24 @@ pub mod frame;
 pub mod frame_decoder;
 pub mod fse;
 pub mod huff0;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(all(feature = "io-read", feature = "streaming"))]
+pub mod seekable_streaming_decoder;
+#[cfg(feature = "streaming")]
 pub mod streaming_decoder;
 mod tests;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub const VERBOSE: bool = false;
+pub use archive::Archive;
+pub use frame_decoder::decode_pooled;
+pub use frame_decoder::decompress;
 pub use frame_decoder::BlockDecodingStrategy;
 pub use frame_decoder::FrameDecoder;
+pub use frame_decoder::TruncationPolicy;
+#[cfg(all(feature = "io-read", feature = "streaming"))]
+pub use seekable_streaming_decoder::SeekableStreamingDecoder;
+#[cfg(feature = "streaming")]
 pub use streaming_decoder::StreamingDecoder;
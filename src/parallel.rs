@@ -0,0 +1,28 @@
+use crate::errors::FrameDecoderError;
+use crate::frame;
+use crate::frame_decoder::FrameDecoder;
+use rayon::prelude::*;
+
+/// Decodes all frames in input and returns their concatenated content, like decoding them one after another would.
+/// The frame boundaries are found first by walking the headers, then the frames are decoded in parallel
+/// on the rayon thread pool, each into its own Vec. Skippable frames are ignored.
+///
+/// Frames that need a dictionary fail with FrameDecoderError::DictNotProvided
+pub fn decode_frames_parallel(input: &[u8]) -> Result<Vec<u8>, FrameDecoderError> {
+    let ranges = frame::scan_frames(input)?;
+    let outputs = ranges
+        .into_par_iter()
+        .map(|range| {
+            let mut frame_dec = FrameDecoder::new();
+            let mut output = Vec::new();
+            frame_dec.decode_append(&input[range], &mut output)?;
+            Ok(output)
+        })
+        .collect::<Result<Vec<Vec<u8>>, FrameDecoderError>>()?;
+
+    let mut result = Vec::with_capacity(outputs.iter().map(Vec::len).sum());
+    for output in outputs {
+        result.extend_from_slice(&output);
+    }
+    Ok(result)
+}
@@ -0,0 +1,151 @@
+use crate::errors::{EofContext, FrameDecoderError};
+use crate::frame::{SKIPPABLE_MAGIC_NUM_MAX, SKIPPABLE_MAGIC_NUM_MIN};
+use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+use crate::streaming_decoder::decoder_error_to_io;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Like the StreamingDecoder, but decodes all frames in the source one after another.
+/// Skippable frames in between are skipped with seek() so their content is never read.
+pub struct SeekableStreamingDecoder<READ: Read + Seek> {
+    pub decoder: FrameDecoder,
+    source: READ,
+    skipped_bytes: u64,
+}
+
+impl<READ: Read + Seek> SeekableStreamingDecoder<READ> {
+    /// Skips all skippable frames at the start of the source and initializes the decoder with the first zstd frame.
+    /// Fails with FrameDecoderError::UnexpectedEof if the source does not contain any zstd frame
+    pub fn new(source: READ) -> Result<SeekableStreamingDecoder<READ>, FrameDecoderError> {
+        Self::new_with_decoder(source, FrameDecoder::new())
+    }
+
+    pub fn new_with_decoder(
+        source: READ,
+        decoder: FrameDecoder,
+    ) -> Result<SeekableStreamingDecoder<READ>, FrameDecoderError> {
+        let mut this = SeekableStreamingDecoder {
+            decoder,
+            source,
+            skipped_bytes: 0,
+        };
+        if !this.next_frame()? {
+            return Err(FrameDecoderError::UnexpectedEof {
+                context: EofContext::FrameHeader,
+                bytes_missing: 4,
+            });
+        }
+        Ok(this)
+    }
+
+    /// How many bytes of skippable frames (including their magic number and size) were skipped so far
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped_bytes
+    }
+
+    pub fn inner(self) -> FrameDecoder {
+        self.decoder
+    }
+
+    /// Skips skippable frames and resets the decoder for the next zstd frame.
+    /// Returns false if the source ended before another zstd frame started
+    fn next_frame(&mut self) -> Result<bool, FrameDecoderError> {
+        loop {
+            let mut magic = [0u8; 4];
+            let bytes_read = crate::decoding::source::read_until_full(&mut self.source, &mut magic)
+                .map_err(|e| {
+                    FrameDecoderError::FailedToReadSource(format!(
+                        "Error while reading the next frame: {}",
+                        e
+                    ))
+                })?;
+            if bytes_read == 0 {
+                return Ok(false);
+            }
+            if bytes_read < magic.len() {
+                return Err(FrameDecoderError::UnexpectedEof {
+                    context: EofContext::FrameHeader,
+                    bytes_missing: magic.len() - bytes_read,
+                });
+            }
+
+            let magic_num = u32::from_le_bytes(magic);
+            if !(SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num) {
+                self.decoder.reset((&magic[..]).chain(&mut self.source))?;
+                return Ok(true);
+            }
+
+            let mut frame_size = [0u8; 4];
+            let bytes_read =
+                crate::decoding::source::read_until_full(&mut self.source, &mut frame_size)
+                    .map_err(|e| {
+                        FrameDecoderError::FailedToReadSource(format!(
+                            "Error while reading the size of a skippable frame: {}",
+                            e
+                        ))
+                    })?;
+            if bytes_read < frame_size.len() {
+                return Err(FrameDecoderError::UnexpectedEof {
+                    context: EofContext::FrameHeader,
+                    bytes_missing: frame_size.len() - bytes_read,
+                });
+            }
+            let frame_size = u32::from_le_bytes(frame_size);
+            let seek_error = |e: std::io::Error| {
+                FrameDecoderError::FailedToReadSource(format!(
+                    "Error while skipping a skippable frame: {}",
+                    e
+                ))
+            };
+            let frame_end = self
+                .source
+                .seek(SeekFrom::Current(i64::from(frame_size)))
+                .map_err(seek_error)?;
+            // seeking past the end succeeds, so a cut off skippable frame would look like the end of the source
+            let source_end = self.source.seek(SeekFrom::End(0)).map_err(seek_error)?;
+            if frame_end > source_end {
+                return Err(FrameDecoderError::UnexpectedEof {
+                    context: EofContext::BlockContent,
+                    bytes_missing: (frame_end - source_end) as usize,
+                });
+            }
+            self.source
+                .seek(SeekFrom::Start(frame_end))
+                .map_err(seek_error)?;
+            self.skipped_bytes += 8 + u64::from(frame_size);
+        }
+    }
+}
+
+impl<READ: Read + Seek> Read for SeekableStreamingDecoder<READ> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.decoder.is_finished() && self.decoder.can_collect() == 0 {
+                // the current frame is done, continue with the next one if there is any
+                if !self.next_frame().map_err(decoder_error_to_io)? {
+                    return Ok(0);
+                }
+            }
+
+            // same as in StreamingDecoder::read, UptoBytes may not produce collectable bytes right away
+            while self.decoder.can_collect() < buf.len() && !self.decoder.is_finished() {
+                let additional_bytes_needed = buf.len() - self.decoder.can_collect();
+                self.decoder
+                    .decode_blocks(
+                        &mut self.source,
+                        BlockDecodingStrategy::UptoBytes(additional_bytes_needed),
+                    )
+                    .map_err(decoder_error_to_io)?;
+            }
+
+            let bytes_read = Read::read(&mut self.decoder, buf)?;
+            // empty frames dont produce any bytes, go on with the next frame in that case
+            if bytes_read > 0 {
+                return Ok(bytes_read);
+            }
+        }
+    }
+}
@@ -1,4 +1,10 @@
+use crate::decoding::block_decoder::{self, ABSOLUTE_MAXIMUM_BLOCK_SIZE};
+use crate::decoding::ringbuffer::RingBuffer;
+use crate::decoding::source::{read_until_full, ByteSource, ByteSourceReader};
+use crate::errors::{BlockDecoderError, EofContext, ErrorCategory, FrameDecoderError};
+use crate::frame::{SKIPPABLE_MAGIC_NUM_MAX, SKIPPABLE_MAGIC_NUM_MIN};
 use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+use std::collections::VecDeque;
 use std::io::Read;
 
 /// High level decoder that implements a io::Read that can be used with
@@ -6,33 +12,407 @@ use std::io::Read;
 ///
 /// The lower level FrameDecoder by comparison allows for finer grained control but need sto have it's decode_blocks method called continously
 /// to decode the zstd-frame.
-pub struct StreamingDecoder<READ: Read> {
+///
+/// The source can be anything implementing ByteSource, which includes every io::Read and &[u8]
+///
+/// Malformed input is reported as an io::Error from read, it does not make the decoder panic.
+/// This is checked by test_streaming_decoder_never_panics with random and corrupted frames
+pub struct StreamingDecoder<READ: ByteSource> {
     pub decoder: FrameDecoder,
     source: READ,
+    /// Bytes that were decoded by peek() but not read yet, starting at peeked_pos
+    peeked: Vec<u8>,
+    peeked_pos: usize,
+    /// Bytes read_available took from the source that do not make up a complete block yet.
+    /// Everything that reads from the source reads these first
+    input: VecDeque<u8>,
+    /// Set when the source ended in the middle of the frame. The frame can not be continued after that,
+    /// even if the source returns more bytes later
+    source_ended: bool,
 }
 
-impl<READ: Read> StreamingDecoder<READ> {
-    pub fn new(mut source: READ) -> Result<StreamingDecoder<READ>, String> {
+impl<READ: ByteSource> StreamingDecoder<READ> {
+    pub fn new(mut source: READ) -> Result<StreamingDecoder<READ>, FrameDecoderError> {
         let mut decoder = FrameDecoder::new();
-        decoder.init(&mut source)?;
-        Ok(StreamingDecoder { decoder, source })
+        decoder.init(ByteSourceReader(&mut source))?;
+        Ok(StreamingDecoder {
+            decoder,
+            source,
+            peeked: Vec::new(),
+            peeked_pos: 0,
+            input: VecDeque::new(),
+            source_ended: false,
+        })
     }
 
     pub fn new_with_decoder(
         mut source: READ,
         mut decoder: FrameDecoder,
-    ) -> Result<StreamingDecoder<READ>, String> {
-        decoder.init(&mut source)?;
-        Ok(StreamingDecoder { decoder, source })
+    ) -> Result<StreamingDecoder<READ>, FrameDecoderError> {
+        decoder.init(ByteSourceReader(&mut source))?;
+        Ok(StreamingDecoder {
+            decoder,
+            source,
+            peeked: Vec::new(),
+            peeked_pos: 0,
+            input: VecDeque::new(),
+            source_ended: false,
+        })
+    }
+
+    /// Like new but decodes into a ringbuffer provided by the caller instead of allocating one, e.g. one taken from a
+    /// pool. Use into_buffer to get it back when the decoder is no longer needed
+    pub fn with_buffer(
+        source: READ,
+        buffer: RingBuffer,
+    ) -> Result<StreamingDecoder<READ>, FrameDecoderError> {
+        let mut decoder = FrameDecoder::new();
+        decoder.use_ring_buffer(buffer);
+        Self::new_with_decoder(source, decoder)
+    }
+
+    /// Prepares the decoder for the next frame in the source, reusing the buffers of the FrameDecoder.
+    /// Returns false if the source has no bytes left, so there is no next frame to decode.
+    ///
+    /// The rest of the current frame is skipped by its block headers without decoding it, so its bytes that have not
+    /// been read yet are lost. Skippable frames in front of the next frame are skipped as well.
+    /// If the current frame failed with invalid data, where it ends is not known and this starts at the wrong place
+    pub fn reset(&mut self) -> Result<bool, FrameDecoderError> {
+        self.peeked.clear();
+        self.peeked_pos = 0;
+        // a frame that was cut off by the end of the source has nothing left to skip
+        if !self.source_ended && !self.decoder.is_finished() {
+            self.skip_rest_of_frame()?;
+        }
+        self.source_ended = false;
+
+        loop {
+            let mut source = buffered(&mut self.input, &mut self.source);
+            // read the first bytes by hand to tell a clean end of the source from a truncated header
+            let mut magic = [0u8; 4];
+            let bytes_read = read_until_full(&mut source, &mut magic).map_err(|e| {
+                FrameDecoderError::FailedToReadSource(format!(
+                    "Error while reading the next frame: {}",
+                    e
+                ))
+            })?;
+            if bytes_read == 0 {
+                return Ok(false);
+            }
+
+            let magic_num = u32::from_le_bytes(magic);
+            if bytes_read == magic.len()
+                && (SKIPPABLE_MAGIC_NUM_MIN..=SKIPPABLE_MAGIC_NUM_MAX).contains(&magic_num)
+            {
+                let mut frame_size = [0u8; 4];
+                skip_exact(&mut source, &mut frame_size, EofContext::FrameHeader)?;
+                skip_bytes(
+                    &mut source,
+                    u64::from(u32::from_le_bytes(frame_size)),
+                    EofContext::BlockContent,
+                )?;
+                continue;
+            }
+
+            self.decoder.reset((&magic[..bytes_read]).chain(source))?;
+            return Ok(true);
+        }
     }
 
+    /// Reads over the blocks of the current frame that were not decoded yet, and its checksum, without decoding them
+    fn skip_rest_of_frame(&mut self) -> Result<(), FrameDecoderError> {
+        let has_checksum = self.decoder.has_checksum();
+        let mut source = buffered(&mut self.input, &mut self.source);
+        let mut block_dec = block_decoder::new();
+        loop {
+            let (header, _) = block_dec
+                .read_block_header(&mut source)
+                .map_err(BlockDecoderError::into_header_error)?;
+            skip_bytes(
+                &mut source,
+                u64::from(header.content_size),
+                EofContext::BlockContent,
+            )?;
+            if header.last_block {
+                break;
+            }
+        }
+        if has_checksum {
+            skip_bytes(&mut source, 4, EofContext::Checksum)?;
+        }
+        Ok(())
+    }
+
+    /// Returns up to n of the next decoded bytes without consuming them, the next read() returns the same bytes.
+    /// Less than n bytes are only returned if the frame ends before that.
+    ///
+    /// The peeked bytes are kept in a buffer inside of the StreamingDecoder until they are read
+    pub fn peek(&mut self, n: usize) -> std::io::Result<&[u8]> {
+        if self.peeked_pos > 0 {
+            self.peeked.drain(..self.peeked_pos);
+            self.peeked_pos = 0;
+        }
+        // take the buffer out so read_decoded can borrow self
+        let mut peeked = std::mem::take(&mut self.peeked);
+        let mut result = Ok(());
+        while peeked.len() < n {
+            let old_len = peeked.len();
+            peeked.resize(n, 0);
+            match self.read_decoded(&mut peeked[old_len..]) {
+                Ok(bytes_read) => {
+                    peeked.truncate(old_len + bytes_read);
+                    if bytes_read == 0 {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    peeked.truncate(old_len);
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.peeked = peeked;
+        result?;
+        Ok(&self.peeked[..usize::min(n, self.peeked.len())])
+    }
+
+    /// The window size of the frame, see FrameDecoder::window_size. The header is read when the decoder is created,
+    /// so this is known before anything is decoded, e.g. to decide where the output goes
+    pub fn window_size(&self) -> Option<u64> {
+        self.decoder.window_size()
+    }
+
+    /// The content size the frame header declares, None if it has none. See FrameDecoder::content_size
+    pub fn content_size(&self) -> Option<u64> {
+        self.decoder.content_size()
+    }
+
+    /// The id of the dictionary the frame needs, see FrameDecoder::dictionary_id
+    pub fn dictionary_id(&self) -> Option<u32> {
+        self.decoder.dictionary_id()
+    }
+
+    /// Consumes the StreamingDecoder and returns the FrameDecoder, e.g. to reuse it for the next frame with
+    /// new_with_decoder. Bytes read_available took from the source but did not decode yet are dropped, and these
+    /// can already belong to the next frame. Use into_parts to keep them
     pub fn inner(self) -> FrameDecoder {
         self.decoder
     }
+
+    /// Consumes the StreamingDecoder and returns the FrameDecoder, the source and the bytes that were taken from the
+    /// source but not decoded yet. read_available reads ahead in the source, so these can be the end of the current
+    /// frame or the start of the next one. They come before whatever the source returns next
+    pub fn into_parts(self) -> (FrameDecoder, READ, Vec<u8>) {
+        (self.decoder, self.source, self.input.into())
+    }
+
+    /// Consumes the decoder and returns its ringbuffer, e.g. to give it back to the pool it was taken from
+    /// for with_buffer. Bytes that have not been read yet are dropped, including those read_available took from the
+    /// source ahead of time
+    pub fn into_buffer(mut self) -> RingBuffer {
+        self.decoder.take_ring_buffer()
+    }
+
+    /// Turns the decoder into an iterator over the decoded content of the frame.
+    /// Each call to next() decodes one block and returns all bytes that are no longer needed for the window,
+    /// so some bytes of a block may only be returned with a later chunk. Chunks are never empty.
+    ///
+    /// After an error was returned the iterator ends.
+    pub fn chunks(self) -> Chunks<READ> {
+        Chunks {
+            decoder: self,
+            failed: false,
+        }
+    }
+
+    /// Appends everything that can be decoded from what the source has right now to out and returns how many bytes
+    /// that were. The source is only read again as long as it fills the whole read buffer. After a short read, or a read
+    /// of 0 bytes, the rest is left for the next call, so a blocking source (a pipe or a socket) is not read again
+    /// after it handed out what it had. Only complete blocks are decoded; the bytes of a block that is not complete
+    /// are kept in the StreamingDecoder, so a later call (or read) continues with them once the rest is available.
+    ///
+    /// Like read this keeps the last window of the decoded bytes until the frame is finished, so the appended bytes are
+    /// everything that was decoded except those
+    pub fn read_available(&mut self, out: &mut Vec<u8>) -> std::io::Result<usize> {
+        let len_before = out.len();
+        out.extend_from_slice(&self.peeked[self.peeked_pos..]);
+        self.peeked.clear();
+        self.peeked_pos = 0;
+
+        // a block with its header and the checksum after it if it is the last one
+        let most_needed = ABSOLUTE_MAXIMUM_BLOCK_SIZE as usize + 3 + 4;
+        let mut buf = [0u8; 8 * 1024];
+        let mut source_drained = false;
+        while !self.decoder.is_finished() {
+            while !source_drained && self.input.len() < most_needed {
+                let bytes_read = ByteSource::read(&mut self.source, &mut buf)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                self.input.extend(&buf[..bytes_read]);
+                // a short read means the source had nothing more at the moment, reading again could block
+                source_drained = bytes_read < buf.len();
+            }
+            if !self.next_block_available().map_err(decoder_error_to_io)? {
+                break;
+            }
+            self.decoder
+                .decode_blocks(
+                    buffered(&mut self.input, &mut self.source),
+                    BlockDecodingStrategy::UptoBlocks(1),
+                )
+                .map_err(decoder_error_to_io)?;
+            self.decoder
+                .collect_to_writer(&mut *out)
+                .map_err(|_| decoder_error_to_io(FrameDecoderError::FailedToDrainDecodebuffer))?;
+        }
+        self.decoder
+            .collect_to_writer(&mut *out)
+            .map_err(|_| decoder_error_to_io(FrameDecoderError::FailedToDrainDecodebuffer))?;
+        Ok(out.len() - len_before)
+    }
+
+    /// Whether input holds the whole next block, including the checksum if it is the last one
+    fn next_block_available(&mut self) -> Result<bool, FrameDecoderError> {
+        if self.input.len() < 3 {
+            return Ok(false);
+        }
+        let header_bytes = [self.input[0], self.input[1], self.input[2]];
+        let (header, header_size) = block_decoder::new()
+            .read_block_header(&header_bytes[..])
+            .map_err(BlockDecoderError::into_header_error)?;
+        let mut needed = header_size as usize + header.content_size as usize;
+        if header.last_block && self.decoder.has_checksum() {
+            needed += 4;
+        }
+        Ok(self.input.len() >= needed)
+    }
+}
+
+/// Reads exactly buf.len() bytes, a source that ends before is UnexpectedEof in context
+fn skip_exact(
+    source: impl Read,
+    buf: &mut [u8],
+    context: EofContext,
+) -> Result<(), FrameDecoderError> {
+    let bytes_read = read_until_full(source, buf).map_err(|e| {
+        FrameDecoderError::FailedToReadSource(format!("Error while skipping a frame: {}", e))
+    })?;
+    if bytes_read < buf.len() {
+        return Err(FrameDecoderError::UnexpectedEof {
+            context,
+            bytes_missing: buf.len() - bytes_read,
+        });
+    }
+    Ok(())
 }
 
-impl<READ: Read> Read for StreamingDecoder<READ> {
+/// Reads and drops amount bytes, a source that ends before is UnexpectedEof in context
+fn skip_bytes(
+    source: impl Read,
+    amount: u64,
+    context: EofContext,
+) -> Result<(), FrameDecoderError> {
+    let skipped = std::io::copy(&mut source.take(amount), &mut std::io::sink()).map_err(|e| {
+        FrameDecoderError::FailedToReadSource(format!("Error while skipping a frame: {}", e))
+    })?;
+    if skipped < amount {
+        return Err(FrameDecoderError::UnexpectedEof {
+            context,
+            bytes_missing: (amount - skipped) as usize,
+        });
+    }
+    Ok(())
+}
+
+/// The bytes read_available kept back followed by the rest of the source
+fn buffered<'a, S: ByteSource>(input: &'a mut VecDeque<u8>, source: &'a mut S) -> impl Read + 'a {
+    input.chain(ByteSourceReader(source))
+}
+
+/// Iterator over the decoded content of a frame. Created by StreamingDecoder::chunks()
+pub struct Chunks<READ: ByteSource> {
+    decoder: StreamingDecoder<READ>,
+    failed: bool,
+}
+
+impl<READ: ByteSource> Iterator for Chunks<READ> {
+    type Item = Result<Vec<u8>, FrameDecoderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let StreamingDecoder {
+            decoder,
+            source,
+            peeked,
+            peeked_pos,
+            input,
+            ..
+        } = &mut self.decoder;
+
+        if *peeked_pos < peeked.len() {
+            let chunk = peeked.split_off(*peeked_pos);
+            peeked.clear();
+            *peeked_pos = 0;
+            return Some(Ok(chunk));
+        }
+
+        loop {
+            if !decoder.is_finished() {
+                if let Err(e) = decoder.decode_blocks(
+                    buffered(input, source),
+                    BlockDecodingStrategy::UptoBlocks(1),
+                ) {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+
+            match decoder.collect() {
+                Some(chunk) if !chunk.is_empty() => return Some(Ok(chunk)),
+                // the decoded bytes are still needed for the window, decode the next block
+                _ if !decoder.is_finished() => {}
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "io-read")]
+impl StreamingDecoder<std::io::BufReader<std::fs::File>> {
+    /// Opens the file at path through a BufReader and prepares decoding the frame at its start.
+    /// Errors from opening the file are passed on, errors in the frame header are converted like decoder_error_to_io
+    pub fn from_path(
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<StreamingDecoder<std::io::BufReader<std::fs::File>>> {
+        let file = std::fs::File::open(path)?;
+        StreamingDecoder::new(std::io::BufReader::new(file)).map_err(decoder_error_to_io)
+    }
+}
+
+impl<READ: ByteSource> Read for StreamingDecoder<READ> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.peeked_pos < self.peeked.len() {
+            let peeked = &self.peeked[self.peeked_pos..];
+            let amount = usize::min(peeked.len(), buf.len());
+            buf[..amount].copy_from_slice(&peeked[..amount]);
+            self.peeked_pos += amount;
+            return Ok(amount);
+        }
+        self.read_decoded(buf)
+    }
+}
+
+impl<READ: ByteSource> StreamingDecoder<READ> {
+    /// Reads bytes directly from the FrameDecoder, ignoring the peeked bytes
+    fn read_decoded(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.source_ended {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Error in the zstd decoder: The source ended before the frame was complete",
+            ));
+        }
         if self.decoder.is_finished() && self.decoder.can_collect() == 0 {
             //No more bytes can ever be decoded
             return Ok(0);
@@ -46,21 +426,28 @@ impl<READ: Read> Read for StreamingDecoder<READ> {
         while self.decoder.can_collect() < buf.len() && !self.decoder.is_finished() {
             //More bytes can be decoded
             let additional_bytes_needed = buf.len() - self.decoder.can_collect();
-            match self.decoder.decode_blocks(
-                &mut self.source,
+            // a source that returns 0 bytes has ended, even if some readers return more after that.
+            // Whatever they return later does not continue the frame where it was cut off
+            if let Err(e) = self.decoder.decode_blocks(
+                buffered(&mut self.input, &mut self.source),
                 BlockDecodingStrategy::UptoBytes(additional_bytes_needed),
             ) {
-                Ok(_) => { /*Nothing to do*/ }
-                Err(e) => {
-                    let err = std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Error in the zstd decoder: {:?}", e),
-                    );
-                    return Err(err);
-                }
+                self.source_ended = e.category() == ErrorCategory::UnexpectedEof;
+                return Err(decoder_error_to_io(e));
             }
         }
 
-        self.decoder.read(buf)
+        Read::read(&mut self.decoder, buf)
     }
 }
+
+/// Errors for running out of input are io::ErrorKind::UnexpectedEof, malformed input is io::ErrorKind::InvalidData
+/// and everything else is io::ErrorKind::Other. See FrameDecoderError::category
+pub(crate) fn decoder_error_to_io(e: FrameDecoderError) -> std::io::Error {
+    let kind = match e.category() {
+        ErrorCategory::UnexpectedEof => std::io::ErrorKind::UnexpectedEof,
+        ErrorCategory::InvalidData => std::io::ErrorKind::InvalidData,
+        ErrorCategory::Other => std::io::ErrorKind::Other,
+    };
+    std::io::Error::new(kind, format!("Error in the zstd decoder: {}", e))
+}
@@ -38,6 +38,80 @@ fn test_bitreader_reversed() {
     }
 }
 
+#[test]
+fn test_bitreader_reversed_unchecked() {
+    use crate::decoding::bit_reader_reverse::BitReaderReversed;
+
+    let encoded: [u8; 16] = [
+        0xC1, 0x41, 0x08, 0x00, 0x00, 0xEC, 0xC8, 0x96, 0x42, 0x79, 0xD4, 0xBC, 0xF7, 0x2C, 0xD5,
+        0x48,
+    ];
+    //just the u128 in encoded
+    let num_rev: u128 = 0x48_D5_2C_F7_BC_D4_79_42_96_C8_EC_00_00_08_41_C1;
+
+    let mut br = BitReaderReversed::new(&encoded[..]);
+    let mut accumulator = 0;
+    let mut bits_read = 0;
+    let mut x = 0;
+
+    loop {
+        x += 5;
+        //semi random access pattern, mixing checked and unchecked reads
+        let mut num_bits = x % 24;
+        if bits_read > 128 - num_bits {
+            num_bits = 128 - bits_read;
+        }
+
+        let bits = if br.ensure_bits(num_bits as u8) {
+            br.get_bits_unchecked(num_bits as u8)
+        } else {
+            br.get_bits(num_bits).unwrap()
+        };
+        bits_read += num_bits;
+        accumulator |= u128::from(bits) << (128 - bits_read);
+        if bits_read >= 128 {
+            break;
+        }
+    }
+
+    assert_eq!(accumulator, num_rev);
+}
+
+#[test]
+fn test_bitreader_reversed_against_bitwise() {
+    use crate::decoding::bit_reader_reverse::BitReaderReversed;
+
+    // the n bits in front of bit position from, one at a time. Bits before the start of the source are 0
+    fn bitwise(source: &[u8], from: isize, n: usize) -> u64 {
+        (1..=n as isize).fold(0, |value, i| {
+            let bit = from - i;
+            let set = bit >= 0 && source[bit as usize / 8] >> (bit % 8) & 1 == 1;
+            value << 1 | u64::from(set)
+        })
+    }
+
+    let source: Vec<u8> = (0..1000u32).map(|i| (i * 97 % 251) as u8 ^ 0x5A).collect();
+    // many read sizes, so the container is filled at every offset in and outside of the fast path
+    for step in 1..=56usize {
+        let mut br = BitReaderReversed::new(&source);
+        let mut pos = source.len() as isize * 8;
+        let mut x = 0;
+        while pos > -64 {
+            x += step;
+            let num_bits = x % 57;
+            let expected = bitwise(&source, pos, num_bits);
+            let bits = if step % 2 == 0 && br.ensure_bits(num_bits as u8) {
+                br.get_bits_unchecked(num_bits as u8)
+            } else {
+                br.get_bits(num_bits).unwrap()
+            };
+            assert_eq!(bits, expected, "step {} at bit {}", step, pos);
+            pos -= num_bits as isize;
+            assert_eq!(br.bits_remaining(), pos);
+        }
+    }
+}
+
 #[test]
 fn test_bitreader_normal() {
     use crate::decoding::bit_reader::BitReader;
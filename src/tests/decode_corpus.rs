@@ -2,7 +2,6 @@
 fn test_decode_corpus_files() {
     use crate::frame_decoder;
     use std::fs;
-    use std::io::Read;
 
     let mut success_counter = 0;
     let mut fail_counter_diff = 0;
@@ -69,8 +68,7 @@ fn test_decode_corpus_files() {
 
         let mut original_p = p.clone();
         original_p.truncate(original_p.len() - 4);
-        let original_f = fs::File::open(original_p).unwrap();
-        let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+        let original: Vec<u8> = fs::read(original_p).unwrap();
 
         println!("Results for file: {}", p.clone());
         let mut success = true;
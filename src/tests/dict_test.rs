@@ -77,7 +77,6 @@ fn test_dict_parsing() {
 fn test_dict_decoding() {
     use crate::frame_decoder;
     use std::fs;
-    use std::io::Read;
 
     let mut success_counter = 0;
     let mut fail_counter_diff = 0;
@@ -90,8 +89,7 @@ fn test_dict_decoding() {
     let mut speeds_read = Vec::new();
 
     let mut files: Vec<_> = fs::read_dir("./dict_tests/files").unwrap().collect();
-    let dict = fs::File::open("./dict_tests/dictionary").unwrap();
-    let dict: Vec<u8> = dict.bytes().map(|x| x.unwrap()).collect();
+    let dict: Vec<u8> = fs::read("./dict_tests/dictionary").unwrap();
 
     files.sort_by_key(|x| match x {
         Err(_) => "".to_owned(),
@@ -141,8 +139,7 @@ fn test_dict_decoding() {
 
         let mut original_p = p.clone();
         original_p.truncate(original_p.len() - 4);
-        let original_f = fs::File::open(original_p).unwrap();
-        let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+        let original: Vec<u8> = fs::read(original_p).unwrap();
 
         println!("Results for file: {}", p.clone());
         let mut success = true;
@@ -250,3 +247,225 @@ fn test_dict_decoding() {
 
     assert!(failed.is_empty());
 }
+
+#[test]
+fn test_dict_content() {
+    use crate::decoding::dictionary::Dictionary;
+
+    let raw = std::fs::read("./dict_tests/dictionary").unwrap();
+    let dict = Dictionary::decode_dict(&raw).unwrap();
+
+    // magic number and dict id (8 bytes), the huffman and fse tables (130 bytes, see test_dict_parsing)
+    // and the offset history (12 bytes) come before the content
+    let content_start = 8 + 130 + 12;
+    assert_eq!(dict.content().len(), 51_812);
+    assert_eq!(dict.content().len(), raw.len() - content_start);
+    assert_eq!(dict.content(), &raw[content_start..]);
+    assert_eq!(dict.offset_hist, [1, 4, 8]);
+}
+
+#[test]
+fn test_can_decode() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let content = fs::read("./dict_tests/files/ModemManager.service.zst").unwrap();
+    let (frame, _) = frame::read_frame_header(content.as_slice()).unwrap();
+    let dict = fs::read("./dict_tests/dictionary").unwrap();
+    // the id comes right after the magic number, the frame needs this one
+    let dict_id = u32::from_le_bytes([dict[4], dict[5], dict[6], dict[7]]);
+    assert_eq!(frame.header.dictiornary_id().unwrap(), Some(dict_id));
+    // the same dictionary with another id
+    let mut other_dict = dict.clone();
+    other_dict[4..8].copy_from_slice(&0x1234u32.to_le_bytes());
+
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.add_dict(&other_dict).unwrap();
+    match frame_dec.can_decode(&frame.header) {
+        Err(e @ FrameDecoderError::DictNotProvided { .. }) => {
+            assert!(matches!(
+                &e,
+                FrameDecoderError::DictNotProvided { dict_id: id, loaded } if *id == dict_id && loaded == &[0x1234]
+            ));
+            let message = e.to_string();
+            assert!(
+                message.contains(&dict_id.to_string()) && message.contains("4660"),
+                "{}",
+                message
+            );
+        }
+        other => panic!("Expected DictNotProvided, got: {:?}", other),
+    }
+
+    frame_dec.add_dict(&dict).unwrap();
+    frame_dec.can_decode(&frame.header).unwrap();
+
+    // a window that is too big is rejected no matter which dictionaries there are
+    let huge_window = crate::tests::test_frame(&[0, 0xFF], &[]);
+    let (frame, _) = frame::read_frame_header(huge_window.as_slice()).unwrap();
+    assert!(matches!(
+        frame_dec.can_decode(&frame.header),
+        Err(FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::Initialize(_)
+        ))
+    ));
+}
+
+#[test]
+fn test_reset_keep_dict() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    let dict = fs::read("./dict_tests/dictionary").unwrap();
+    let names = [
+        "ModemManager.service",
+        "NetworkManager.service",
+        "auditd.service",
+    ];
+
+    let mut frame_dec = FrameDecoder::new();
+    // only the first frame gets the dictionary, the decoder does not know it otherwise
+    for (i, name) in names.iter().enumerate() {
+        let frame = fs::read(format!("./dict_tests/files/{}.zst", name)).unwrap();
+        let original = fs::read(format!("./dict_tests/files/{}", name)).unwrap();
+        let mut source = frame.as_slice();
+        if i == 0 {
+            frame_dec.reset_with_dict(&mut source, &dict).unwrap();
+        } else {
+            frame_dec.reset_keep_dict(&mut source).unwrap();
+        }
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::All)
+            .unwrap();
+        assert_eq!(frame_dec.collect().unwrap(), original, "{}", name);
+        // the frames reach back into the dictionary content
+        assert!(
+            frame_dec.max_offset_used() > original.len() as u64,
+            "{}",
+            name
+        );
+    }
+
+    // a plain reset forgets it
+    let frame = fs::read("./dict_tests/files/auditd.service.zst").unwrap();
+    let mut source = frame.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    assert!(matches!(
+        frame_dec.decode_blocks(&mut source, BlockDecodingStrategy::All),
+        Err(FrameDecoderError::DictNotProvided { dict_id, loaded })
+            if dict_id == u32::from_le_bytes([dict[4], dict[5], dict[6], dict[7]]) && loaded.is_empty()
+    ));
+    assert!(matches!(
+        FrameDecoder::new().reset_keep_dict(frame.as_slice()),
+        Err(FrameDecoderError::NotYetInitialized)
+    ));
+    // the frame before did not get its dictionary
+    assert!(matches!(
+        frame_dec.reset_keep_dict(frame.as_slice()),
+        Err(FrameDecoderError::InvalidData(_))
+    ));
+
+    // the same dictionary with another id does not fit the frames
+    let mut other_dict = dict.clone();
+    other_dict[4..8].copy_from_slice(&0x1234u32.to_le_bytes());
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec
+        .reset_with_dict(frame.as_slice(), &other_dict)
+        .unwrap();
+    match frame_dec.reset_keep_dict(frame.as_slice()) {
+        Err(FrameDecoderError::InvalidData(crate::errors::InvalidDataError::Initialize(m))) => {
+            assert!(m.contains("4660"), "{}", m)
+        }
+        other => panic!(
+            "expected the other dictionary to be rejected, got {:?}",
+            other
+        ),
+    }
+}
+
+#[test]
+fn test_offsets_into_dict_content() {
+    use crate::decoding::dictionary::Dictionary;
+    use crate::frame_decoder::FrameDecoder;
+
+    let raw_dict = std::fs::read("./dict_tests/dictionary").unwrap();
+    let dict = Dictionary::decode_dict(&raw_dict).unwrap();
+    let content = dict.content();
+
+    // a compressed block without literals and one sequence with literal length 0 and match length 34.
+    // The offset is given directly, without the repeat offsets the dictionary starts with
+    fn one_match(offset: u32, last: bool) -> Vec<u8> {
+        let offset_value = offset + 3;
+        let offset_code = 31 - offset_value.leading_zeros();
+        let mut block = vec![0, 1, 0x54, 0, offset_code as u8, 31];
+        // the stop bit before the extra bits of the offset, which are the bits of the value below its highest bit
+        let bits = u64::from(offset_value);
+        block.extend_from_slice(&bits.to_le_bytes()[..(offset_code as usize + 8) / 8]);
+        let header = u32::from(last) | 2 << 1 | (block.len() as u32) << 3;
+        let mut with_header = header.to_le_bytes()[..3].to_vec();
+        with_header.extend_from_slice(&block);
+        with_header
+    }
+    // a 1MB window, the dictionary id in 4 bytes and no content size
+    let frame_with = |blocks: &[Vec<u8>]| {
+        let mut frame =
+            crate::tests::test_frame(&[&[3, 10 << 3][..], &dict.id.to_le_bytes()].concat(), &[]);
+        for block in blocks {
+            frame.extend_from_slice(block);
+        }
+        frame
+    };
+
+    // the first match starts 12 bytes after the start of the dictionary content. The second one reaches back over
+    // the start of the output into the last 20 bytes of the dictionary, so it continues with the first 14 output bytes
+    let deep = content.len() as u32 - 12;
+    let frame = frame_with(&[one_match(deep, false), one_match(34 + 20, true)]);
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.add_dict(&raw_dict).unwrap();
+    let (output, _) = frame_dec.decode_frame(&frame).unwrap();
+    let mut expected = content[12..46].to_vec();
+    expected.extend_from_slice(&content[content.len() - 20..]);
+    expected.extend_from_slice(&content[12..26]);
+    assert_eq!(output, expected);
+    assert_eq!(frame_dec.max_offset_used(), u64::from(deep));
+
+    // the whole dictionary is in reach, but nothing before it
+    let frame = frame_with(&[one_match(content.len() as u32, true)]);
+    let (output, _) = frame_dec.decode_frame(&frame).unwrap();
+    assert_eq!(output, &content[..34]);
+    let frame = frame_with(&[one_match(content.len() as u32 + 1, true)]);
+    let err = frame_dec.decode_frame(&frame).unwrap_err();
+    assert!(err.to_string().contains("from the dictionary"), "{}", err);
+}
+
+#[test]
+fn test_prime_window_with_dict() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let dict = fs::read("./dict_tests/dictionary").unwrap();
+    let dict_id = u32::from_le_bytes([dict[4], dict[5], dict[6], dict[7]]);
+
+    // the frame names the dictionary, priming would be overwritten by its content
+    let content = fs::read("./dict_tests/files/ModemManager.service.zst").unwrap();
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.add_dict(&dict).unwrap();
+    frame_dec.reset(content.as_slice()).unwrap();
+    let err = frame_dec.prime_window(b"xyz").unwrap_err();
+    assert!(err.to_string().contains(&dict_id.to_string()), "{}", err);
+
+    // a frame without a dictionary id that was given a dictionary anyway
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    frame_dec
+        .reset_with_dict(content.as_slice(), &dict)
+        .unwrap();
+    let err = frame_dec.prime_window(b"xyz").unwrap_err();
+    assert!(err.to_string().contains(&dict_id.to_string()), "{}", err);
+
+    // without a dictionary the same frame can be primed
+    frame_dec.reset(content.as_slice()).unwrap();
+    frame_dec.prime_window(b"xyz").unwrap();
+}
@@ -15,12 +15,93 @@ fn test_all_artifacts() {
         }
 
         let mut f = File::open(file_name.clone()).unwrap();
-        match frame_dec.reset(&mut f) {
-            Ok(_) => {
-                let _ = frame_dec.decode_blocks(&mut f, frame_decoder::BlockDecodingStrategy::All);
-                /* ignore errors. It just should never panic on invalid input */
+        /* ignore errors. It just should never panic on invalid input */
+        if frame_dec.reset(&mut f).is_ok() {
+            let _ = frame_dec.decode_blocks(&mut f, frame_decoder::BlockDecodingStrategy::All);
+        }
+    }
+}
+
+/// Every file in fuzz/malformed is a frame that must be rejected with an error.
+/// To add a case, put the smallest input that shows the bug into that directory with a name that says what is wrong with it.
+#[test]
+fn test_malformed_files() {
+    use crate::frame_decoder;
+    use std::fs;
+
+    let mut files: Vec<_> = fs::read_dir("./fuzz/malformed")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+
+    let mut frame_dec = frame_decoder::FrameDecoder::new();
+    for path in files {
+        let content = fs::read(&path).unwrap();
+        let mut source = content.as_slice();
+        let result = frame_dec.reset(&mut source).and_then(|_| {
+            frame_dec.decode_blocks(&mut source, frame_decoder::BlockDecodingStrategy::All)
+        });
+        assert!(result.is_err(), "{:?} was decoded without an error", path);
+    }
+}
+
+/// Feeds random bytes and randomly corrupted frames through the io::Read of the StreamingDecoder.
+/// Every read has to return Ok or Err, a panic fails the test with the input that caused it
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_decoder_never_panics() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use rand::{Rng, SeedableRng};
+    use std::fs;
+    use std::io::Read;
+
+    let read_all = |input: &[u8]| {
+        let mut decoder = match StreamingDecoder::new(input) {
+            Ok(decoder) => decoder,
+            Err(_) => return,
+        };
+        let mut buf = [0u8; 1000];
+        // bounded in case some input makes reads return Ok without making progress
+        for _ in 0..10_000 {
+            match decoder.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
             }
-            Err(_) => {} /* ignore errors. It just should never panic on invalid input */
         }
+    };
+    let check = |input: &[u8]| {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| read_all(input)));
+        assert!(
+            result.is_ok(),
+            "Reading panicked for the input {:x?}",
+            input
+        );
+    };
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0x5EED);
+    let frames: Vec<Vec<u8>> = ["z000019", "z000027", "z000068", "z000095"]
+        .iter()
+        .map(|name| fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap())
+        .collect();
+
+    for _ in 0..2000 {
+        // random bytes after a valid magic number and frame header
+        let mut input = crate::tests::test_frame(&[0, 0], &[]);
+        let len = rng.gen_range(0, 300);
+        input.extend((0..len).map(|_| rng.gen::<u8>()));
+        check(&input);
+
+        // a real frame with a few bytes changed and maybe cut off
+        let mut input = frames[rng.gen_range(0, frames.len())].clone();
+        for _ in 0..rng.gen_range(1, 4) {
+            let idx = rng.gen_range(4, input.len());
+            input[idx] = rng.gen();
+        }
+        if rng.gen_bool(0.3) {
+            input.truncate(rng.gen_range(4, input.len()));
+        }
+        check(&input);
     }
 }
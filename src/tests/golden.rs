@@ -0,0 +1,60 @@
+//! Decodes the small frames in golden_files, each of them covers one corner of the format that the random frames of
+//! decodecorpus_files rarely or never hit. They are named after the cases of the golden-decompression files in the zstd
+//! repository. Every x.zst has its expected output in x, the frames in golden_files/errors have to be rejected.
+//! Add new cases by dropping such a pair (or a broken frame) into the directories, nothing here has to change.
+
+#[cfg(test)]
+fn files_in(dir: &str) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_golden_files() {
+    use crate::frame_decoder::FrameDecoder;
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut failed = Vec::new();
+    let files = files_in("./golden_files");
+    assert!(!files.is_empty());
+    for path in files {
+        let content = std::fs::read(&path).unwrap();
+        let expected = std::fs::read(path.with_extension("")).unwrap();
+        match frame_dec.decode_frame(&content) {
+            Ok((output, 0)) if output == expected => {}
+            Ok((output, trailing)) => failed.push(format!(
+                "{}: decoded {} bytes with {} trailing bytes, expected {} bytes",
+                path.display(),
+                output.len(),
+                trailing,
+                expected.len()
+            )),
+            Err(e) => failed.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    assert!(failed.is_empty(), "{:#?}", failed);
+}
+
+#[test]
+fn test_golden_error_files() {
+    use crate::frame_decoder::FrameDecoder;
+
+    let mut frame_dec = FrameDecoder::new();
+    let files = files_in("./golden_files/errors");
+    assert!(!files.is_empty());
+    for path in files {
+        let content = std::fs::read(&path).unwrap();
+        if let Ok((output, _)) = frame_dec.decode_frame(&content) {
+            panic!(
+                "{} was decoded to {} bytes instead of being rejected",
+                path.display(),
+                output.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,349 @@
+#[cfg(test)]
+fn encode_literals_header(
+    ls_type: u8,
+    size_format: u8,
+    regenerated: u32,
+    compressed: u32,
+) -> Vec<u8> {
+    // see the literals section header in the zstd format documentation
+    let (value, bytes): (u64, usize) = match (ls_type, size_format) {
+        (0 | 1, 0 | 2) => (u64::from(ls_type) | (u64::from(regenerated) << 3), 1),
+        (0 | 1, 1) => (u64::from(ls_type) | 1 << 2 | u64::from(regenerated) << 4, 2),
+        (0 | 1, 3) => (u64::from(ls_type) | 3 << 2 | u64::from(regenerated) << 4, 3),
+        (_, 0 | 1) => (
+            u64::from(ls_type)
+                | u64::from(size_format) << 2
+                | u64::from(regenerated) << 4
+                | u64::from(compressed) << 14,
+            3,
+        ),
+        (_, 2) => (
+            u64::from(ls_type) | 2 << 2 | u64::from(regenerated) << 4 | u64::from(compressed) << 18,
+            4,
+        ),
+        (_, 3) => (
+            u64::from(ls_type) | 3 << 2 | u64::from(regenerated) << 4 | u64::from(compressed) << 22,
+            5,
+        ),
+        _ => unreachable!(),
+    };
+    value.to_le_bytes()[..bytes].to_vec()
+}
+
+#[test]
+fn test_literals_header_size_formats() {
+    use crate::blocks::literals_section::LiteralsSection;
+
+    // (type, size_format, regenerated_size, compressed_size, header bytes)
+    let cases: &[(u8, u8, u32, u32, u8)] = &[
+        // raw and rle use 5, 12 or 20 bits for the regenerated size
+        (0, 0, 0, 0, 1),
+        (0, 0, 31, 0, 1),
+        (0, 2, 17, 0, 1),
+        (1, 0, 31, 0, 1),
+        (0, 1, 32, 0, 2),
+        (0, 1, 4095, 0, 2),
+        (1, 1, 4095, 0, 2),
+        (0, 3, 4096, 0, 3),
+        (0, 3, (1 << 20) - 1, 0, 3),
+        (1, 3, (1 << 20) - 1, 0, 3),
+        // compressed and treeless use 10, 14 or 18 bits for both sizes
+        (2, 0, 1023, 1023, 3),
+        (2, 0, 1, 0, 3),
+        (3, 0, 1023, 1023, 3),
+        (2, 1, 1023, 1023, 3),
+        (2, 2, 16383, 16383, 4),
+        (3, 2, 1024, 1, 4),
+        (2, 3, (1 << 18) - 1, (1 << 18) - 1, 5),
+        (3, 3, 16384, 16384, 5),
+    ];
+
+    for &(ls_type, size_format, regenerated, compressed, header_bytes) in cases {
+        let raw = encode_literals_header(ls_type, size_format, regenerated, compressed);
+        let mut section = LiteralsSection::new();
+
+        assert_eq!(section.header_bytes_needed(raw[0]).unwrap(), header_bytes);
+        assert_eq!(section.parse_from_header(&raw).unwrap(), header_bytes);
+        assert_eq!(section.regenerated_size, regenerated);
+        if ls_type < 2 {
+            assert_eq!(section.compressed_size, None);
+        } else {
+            assert_eq!(section.compressed_size, Some(compressed));
+            // only size format 0 has a single stream
+            let num_streams = if size_format == 0 { 1 } else { 4 };
+            assert_eq!(section.num_streams, Some(num_streams));
+        }
+
+        // a header that is cut off must be rejected
+        assert!(section
+            .parse_from_header(&raw[..header_bytes as usize - 1])
+            .is_err());
+    }
+}
+
+#[test]
+fn test_literals_header_big_sizes_from_corpus() {
+    use crate::blocks::literals_section::{LiteralsSection, LiteralsSectionType};
+    use std::fs;
+
+    // headers written by the reference encoder, with sizes that need the high bits of the 18 and 20 bit forms.
+    // (file, offset of the literals header, regenerated size, compressed size)
+    let cases: &[(&str, usize, u32, Option<u32>)] = &[
+        ("./decodecorpus_files/z000027.zst", 17, 72521, Some(12282)),
+        (
+            "./decodecorpus_files/z000088.zst",
+            65067,
+            71366,
+            Some(35702),
+        ),
+        ("./decodecorpus_files/z000095.zst", 6792, 5505, None),
+    ];
+    for &(file, offset, regenerated, compressed) in cases {
+        let content = fs::read(file).unwrap();
+        let mut section = LiteralsSection::new();
+        let header_bytes = section.parse_from_header(&content[offset..]).unwrap();
+        assert_eq!(section.regenerated_size, regenerated, "{}", file);
+        assert_eq!(section.compressed_size, compressed, "{}", file);
+        match section.ls_type {
+            LiteralsSectionType::Compressed => {
+                assert_eq!((header_bytes, section.num_streams), (5, Some(4)))
+            }
+            LiteralsSectionType::Raw => assert_eq!((header_bytes, section.num_streams), (3, None)),
+            _ => panic!("Unexpected literals section type in {}", file),
+        }
+    }
+
+    // a raw header parsed after a compressed one must not keep the stream count
+    let mut section = LiteralsSection::new();
+    section
+        .parse_from_header(&encode_literals_header(2, 3, 1 << 17, 1 << 17))
+        .unwrap();
+    section
+        .parse_from_header(&encode_literals_header(1, 3, 1 << 19, 0))
+        .unwrap();
+    assert_eq!(
+        (section.regenerated_size, section.num_streams),
+        (1 << 19, None)
+    );
+}
+
+#[test]
+fn test_rle_literals_with_20_bit_size() {
+    // a single segment frame with one compressed block that only consists of rle literals.
+    // The size needs more than 16 of the 20 bits
+    let regenerated = 100_000u32;
+    let mut content = encode_literals_header(1, 3, regenerated, 0);
+    content.push(0xAB);
+    // no sequences
+    content.push(0);
+
+    // single segment with a 4 byte frame content size
+    let frame = crate::tests::test_frame(
+        &[&[2 << 6 | 1 << 5][..], &regenerated.to_le_bytes()].concat(),
+        &[crate::tests::TestBlock::Compressed(&content)],
+    );
+
+    let output = crate::decompress(&frame).unwrap();
+    assert_eq!(output.len(), regenerated as usize);
+    assert!(output.iter().all(|&b| b == 0xAB));
+}
+
+#[test]
+fn test_rle_literals_of_maximum_size() {
+    use crate::blocks::literals_section::{LiteralsSection, LiteralsSectionType};
+    use crate::decoding::literals_section_decoder::{decode_literals, decode_literals_section};
+    use crate::decoding::scratch::HuffmanScratch;
+
+    let mut scratch = HuffmanScratch::new();
+    let mut raw = encode_literals_header(1, 3, 128 * 1024, 0);
+    raw.push(0x5A);
+    // the bytes after the one rle byte belong to the sequences section
+    raw.extend_from_slice(&[0, 1, 2]);
+    let (literals, size) = decode_literals_section(&raw, &mut scratch).unwrap();
+    assert_eq!(size, 4);
+    assert_eq!(literals.len(), 128 * 1024);
+    assert!(literals.iter().all(|&b| b == 0x5A));
+
+    // the rle byte has to be in the block
+    match decode_single_block(&encode_literals_header(1, 3, 1000, 0), &[]) {
+        crate::errors::BlockDecoderError::LiteralsSectionTooBig {
+            declared,
+            available,
+        } => assert_eq!((declared, available), (1, 0)),
+        e => panic!("Expected LiteralsSectionTooBig, got: {}", e),
+    }
+    let mut section = LiteralsSection::new();
+    section.ls_type = LiteralsSectionType::RLE;
+    section.regenerated_size = 1000;
+    let mut target = Vec::new();
+    assert!(decode_literals(&section, &mut scratch, &[], &mut target).is_err());
+    assert!(target.is_empty());
+}
+
+#[cfg(test)]
+fn decode_single_block(
+    literals_header: &[u8],
+    rest_of_block: &[u8],
+) -> crate::errors::BlockDecoderError {
+    use crate::decoding::block_decoder;
+    use crate::decoding::scratch::DecoderScratch;
+
+    let content_size = (literals_header.len() + rest_of_block.len()) as u32;
+    // last block, compressed
+    let mut block = (1 | 2 << 1 | content_size << 3).to_le_bytes()[..3].to_vec();
+    block.extend_from_slice(literals_header);
+    block.extend_from_slice(rest_of_block);
+
+    let mut source = block.as_slice();
+    let mut block_dec = block_decoder::new();
+    let mut scratch = DecoderScratch::new(1024 * 1024, false);
+    let (header, _) = block_dec.read_block_header(&mut source).unwrap();
+    match block_dec.decode_block_content(&header, &mut scratch, &mut source) {
+        Err(e) => e,
+        Ok(_) => panic!("Decoding the malformed block did not fail"),
+    }
+}
+
+#[test]
+fn test_literals_bigger_than_block() {
+    use crate::errors::BlockDecoderError;
+
+    // raw literals that claim 20 bytes in a block that only has 4 bytes after the header
+    match decode_single_block(&encode_literals_header(0, 0, 20, 0), &[0; 4]) {
+        BlockDecoderError::LiteralsSectionTooBig {
+            declared,
+            available,
+        } => assert_eq!((declared, available), (20, 4)),
+        e => panic!("Expected LiteralsSectionTooBig, got: {}", e),
+    }
+
+    // compressed literals with a compressed size bigger than the block
+    match decode_single_block(&encode_literals_header(2, 0, 100, 50), &[0; 10]) {
+        BlockDecoderError::LiteralsSectionTooBig {
+            declared,
+            available,
+        } => assert_eq!((declared, available), (50, 10)),
+        e => panic!("Expected LiteralsSectionTooBig, got: {}", e),
+    }
+
+    // rle literals can not regenerate more than the maximum block size
+    match decode_single_block(&encode_literals_header(1, 3, 128 * 1024 + 1, 0), &[0; 10]) {
+        BlockDecoderError::LiteralsRegeneratedSizeTooBig { size, max } => {
+            assert_eq!((size, max), (128 * 1024 + 1, 128 * 1024))
+        }
+        e => panic!("Expected LiteralsRegeneratedSizeTooBig, got: {}", e),
+    }
+}
+
+#[test]
+fn test_literals_bigger_than_window() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    // The literals buffer is sized per block and has nothing to do with the window.
+    // Every block holds as many literals as the block maximum allows for its window, and the
+    // frame has many more literals in total than fit into the window.
+    // (window descriptor, window size)
+    for &(window_descriptor, window_size) in &[(0u8, 1024usize), (7 << 3, 128 * 1024)] {
+        // content of a compressed block: literals header, literals and a sequences header for 0 sequences
+        let literals_per_block = window_size - 4;
+        let num_blocks = 5;
+
+        let mut contents = Vec::new();
+        let mut expected = Vec::new();
+        for block_idx in 0..num_blocks {
+            let literals: Vec<u8> = (0..literals_per_block)
+                .map(|idx| (idx * 7 + block_idx * 13) as u8)
+                .collect();
+
+            let mut content = encode_literals_header(0, 3, literals_per_block as u32, 0);
+            content.extend_from_slice(&literals);
+            content.push(0);
+            assert_eq!(content.len(), window_size);
+            contents.push(content);
+            expected.extend_from_slice(&literals);
+        }
+        let blocks: Vec<_> = contents
+            .iter()
+            .map(|content| crate::tests::TestBlock::Compressed(content))
+            .collect();
+        let frame = crate::tests::test_frame(&[0, window_descriptor], &blocks);
+
+        let mut frame_dec = FrameDecoder::new();
+        let mut source = frame.as_slice();
+        frame_dec.reset(&mut source).unwrap();
+        let mut output = Vec::new();
+        while !frame_dec.is_finished() {
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                .unwrap();
+            output.extend(frame_dec.collect().unwrap_or_default());
+        }
+        output.extend(frame_dec.collect().unwrap());
+        assert!(
+            output == expected,
+            "Wrong output for window {}",
+            window_size
+        );
+        assert!(source.is_empty());
+    }
+}
+
+#[test]
+fn test_huffman_tree_description_too_big() {
+    // fse compressed weights that claim 100 bytes in a literals section of 20 bytes
+    let mut rest_of_block = vec![100];
+    rest_of_block.extend_from_slice(&[0; 19]);
+    let err = decode_single_block(&encode_literals_header(2, 0, 100, 20), &rest_of_block);
+    assert!(
+        err.to_string()
+            .contains("there should be 100 bytes for the weights"),
+        "{}",
+        err
+    );
+
+    // 128 directly stored weights need 64 bytes
+    let mut rest_of_block = vec![127 + 128];
+    rest_of_block.extend_from_slice(&[0x11; 9]);
+    let err = decode_single_block(&encode_literals_header(2, 0, 100, 10), &rest_of_block);
+    assert!(err.to_string().contains("at least 64 bytes"), "{}", err);
+
+    // a valid description of two weights of 1 that is the whole section, so there is no stream
+    let err = decode_single_block(&encode_literals_header(2, 0, 100, 2), &[127 + 2, 0x11]);
+    assert!(
+        err.to_string().contains("nothing is left for the streams"),
+        "{}",
+        err
+    );
+
+    // an fse table for the weights where symbol 0 has all of the probability, so decoding a weight never reads any bits.
+    // Accuracy log 5 and the probability 32, then a bitstream with just enough bits for the initial states
+    let err = decode_single_block(
+        &encode_literals_header(2, 0, 100, 10),
+        &[4, 0xF0, 0x03, 0x00, 0x04, 0, 0, 0, 0, 0],
+    );
+    assert!(err.to_string().contains("255 weights"), "{}", err);
+}
+
+#[test]
+fn test_huffman_weights_without_stop_bit() {
+    use std::fs;
+
+    // the dictionary starts with a huffman table description after the magic number and the dict id.
+    // Its first byte says that the next 54 bytes are fse compressed weights
+    let dict = fs::read("./dict_tests/dictionary").unwrap();
+    let weights_len = dict[8] as usize;
+    assert!(weights_len < 128);
+    let mut description = dict[8..][..1 + weights_len].to_vec();
+
+    // the stop bit is in the last byte of the weights bitstream
+    *description.last_mut().unwrap() = 0;
+    let mut rest_of_block = description;
+    rest_of_block.extend_from_slice(&[0; 20]);
+
+    let compressed_size = rest_of_block.len() as u32;
+    let err = decode_single_block(
+        &encode_literals_header(2, 0, 100, compressed_size),
+        &rest_of_block,
+    );
+    assert!(err.to_string().contains("no stop bit"), "{}", err);
+}
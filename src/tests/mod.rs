@@ -1,3 +1,44 @@
+/// A block of a frame built by test_frame
+#[cfg(test)]
+pub(crate) enum TestBlock<'a> {
+    Raw(&'a [u8]),
+    /// The byte and how many times it is repeated
+    Rle(u8, u32),
+    /// The content of a compressed block, the literals and sequences sections
+    Compressed(&'a [u8]),
+}
+
+/// The magic number, then header (the frame header descriptor and the fields after it), then the blocks.
+/// The last of the blocks gets the last block flag
+#[cfg(test)]
+pub(crate) fn test_frame(header: &[u8], blocks: &[TestBlock<'_>]) -> Vec<u8> {
+    let mut frame = crate::frame::MAGIC_NUM.to_le_bytes().to_vec();
+    frame.extend_from_slice(header);
+    for (idx, block) in blocks.iter().enumerate() {
+        let (block_type, size, content) = match block {
+            TestBlock::Raw(data) => (0, data.len() as u32, *data),
+            TestBlock::Rle(byte, len) => (1, *len, std::slice::from_ref(byte)),
+            TestBlock::Compressed(data) => (2, data.len() as u32, *data),
+        };
+        let last = u32::from(idx + 1 == blocks.len());
+        let block_header = size << 3 | block_type << 1 | last;
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(content);
+    }
+    frame
+}
+
+/// A skippable frame with the magic number SKIPPABLE_MAGIC_NUM_MIN + tag around payload
+#[cfg(test)]
+pub(crate) fn skippable_frame(tag: u32, payload: &[u8]) -> Vec<u8> {
+    let mut frame = (crate::frame::SKIPPABLE_MAGIC_NUM_MIN + tag)
+        .to_le_bytes()
+        .to_vec();
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
 #[cfg(test)]
 #[test]
 fn test_frame_header_reading() {
@@ -9,6 +50,521 @@ fn test_frame_header_reading() {
     frame.check_valid().unwrap();
 }
 
+#[test]
+fn test_window_size() {
+    use crate::frame;
+
+    for window_descriptor in 0..=255u8 {
+        // frame descriptor without any flags, window descriptor
+        let header = test_frame(&[0, window_descriptor], &[]);
+        let (frame, _) = frame::read_frame_header(header.as_slice()).unwrap();
+
+        let exponent = u64::from(window_descriptor >> 3);
+        let mantissa = u64::from(window_descriptor & 0x7);
+        let expected = (1u64 << (10 + exponent)) + mantissa * (1u64 << (7 + exponent));
+
+        // every possible descriptor is inside of the limits from the RFC
+        assert!((frame::MIN_WINDOW_SIZE..=frame::MAX_WINDOW_SIZE).contains(&expected));
+        assert_eq!(frame.header.window_size().unwrap(), expected);
+        frame.check_valid().unwrap();
+    }
+
+    // the biggest window this decoder supports is 100MB, bigger ones must be rejected before allocating the buffers
+    let header = test_frame(&[0, 0xFF], &[]);
+    let mut frame_dec = crate::frame_decoder::FrameDecoder::new();
+    assert!(matches!(
+        frame_dec.reset(header.as_slice()),
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::Initialize(_)
+        ))
+    ));
+}
+
+#[test]
+fn test_huge_window_and_content_size() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+
+    // the biggest window descriptor, a window of 3.75TB
+    let huge_window = test_frame(&[0, 0xFF], &[TestBlock::Rle(b'a', 4)]);
+    let err = FrameDecoder::new().decode_frame(&huge_window).unwrap_err();
+    assert!(
+        matches!(&err, FrameDecoderError::InvalidData(crate::errors::InvalidDataError::Initialize(m)) if m.contains("window_size")),
+        "{}",
+        err
+    );
+
+    // a single segment frame uses the content size as the window, here u64::MAX
+    let huge_single_segment = test_frame(
+        &[&[0xE0][..], &u64::MAX.to_le_bytes()].concat(),
+        &[TestBlock::Rle(b'a', 4)],
+    );
+    assert!(matches!(
+        FrameDecoder::new().decode_frame(&huge_single_segment),
+        Err(FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::Initialize(_)
+        ))
+    ));
+
+    // with a normal window the content size is not limited, it must not be used to reserve the output blindly
+    let huge_content = test_frame(
+        &[&[0xC0, 0][..], &u64::MAX.to_le_bytes()].concat(),
+        &[TestBlock::Rle(b'a', 4)],
+    );
+    let _ = FrameDecoder::new().decode_frame(&huge_content);
+    let _ = FrameDecoder::new().decode_slices(&[&huge_content[..10], &huge_content[10..]]);
+}
+
+#[test]
+fn test_zero_window_size() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    // single segment descriptor and a 1 byte frame content size of 0 which is also the window size
+    let zero_window = test_frame(&[0x20, 0], &[]);
+
+    // raw and rle blocks never reference the window so they can be decoded anyways
+    let literals_only = test_frame(
+        &[0x20, 0],
+        &[TestBlock::Raw(b"hello"), TestBlock::Rle(b'!', 4)],
+    );
+
+    let (frame, _) = crate::frame::read_frame_header(literals_only.as_slice()).unwrap();
+    frame.check_valid().unwrap();
+    assert_eq!(frame.header.window_size().unwrap(), 0);
+
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(literals_only.as_slice()).unwrap();
+    frame_dec
+        .decode_blocks(&literals_only[6..], BlockDecodingStrategy::All)
+        .unwrap();
+    assert!(frame_dec.is_finished());
+    assert_eq!(frame_dec.collect().unwrap(), b"hello!!!!");
+
+    // the blocks of z000068 start with a sequence that has a match, which needs a window
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let (_, header_size) = crate::frame::read_frame_header(content.as_slice()).unwrap();
+    let blocks_len: usize = crate::frame::inspect_blocks(&content)
+        .unwrap()
+        .iter()
+        .map(|block| 3 + block.content_size as usize)
+        .sum();
+    let mut with_matches = zero_window;
+    with_matches.extend_from_slice(&content[header_size as usize..][..blocks_len]);
+
+    frame_dec.reset(with_matches.as_slice()).unwrap();
+    let err = frame_dec
+        .decode_blocks(&with_matches[6..], BlockDecodingStrategy::All)
+        .unwrap_err();
+    assert!(err.to_string().contains("window size of 0"), "{}", err);
+}
+
+#[test]
+fn test_allow_reserved_bits() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    let mut content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+    // set the reserved bit of the frame descriptor
+    content[4] |= 0x8;
+
+    let mut frame_dec = FrameDecoder::new();
+    match frame_dec.reset(content.as_slice()) {
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::Initialize(msg),
+        )) => {
+            assert!(msg.contains("Reserved"), "{}", msg)
+        }
+        Err(e) => panic!("Wrong error: {}", e),
+        Ok(_) => panic!("Reserved bit was accepted"),
+    }
+
+    frame_dec.set_allow_reserved_bits(true);
+    let mut source = content.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap();
+    assert_eq!(frame_dec.collect().unwrap(), original);
+
+    frame_dec.set_allow_reserved_bits(false);
+    assert!(frame_dec.reset(content.as_slice()).is_err());
+}
+
+#[test]
+fn test_legacy_format() {
+    use crate::errors::{FrameDecoderError, FrameHeaderError};
+    use crate::frame;
+
+    assert_eq!(frame::legacy_version(0xFD2F_B51E), Some(1));
+    assert_eq!(frame::legacy_version(0xFD2F_B524), Some(4));
+    assert_eq!(frame::legacy_version(0xFD2F_B527), Some(7));
+    assert_eq!(frame::legacy_version(frame::MAGIC_NUM), None);
+
+    // a v0.7 frame header
+    let mut header = 0xFD2F_B527u32.to_le_bytes().to_vec();
+    header.extend_from_slice(&[0, 0, 0, 0]);
+    assert!(matches!(
+        frame::read_frame_header(header.as_slice()),
+        Err(FrameHeaderError::LegacyFormat { version: 7 })
+    ));
+    let mut frame_dec = crate::frame_decoder::FrameDecoder::new();
+    assert!(matches!(
+        frame_dec.reset(header.as_slice()),
+        Err(FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::LegacyFormat { version: 7 }
+        ))
+    ));
+}
+
+#[test]
+fn test_wrong_format() {
+    use crate::errors::{ErrorCategory, FrameDecoderError, FrameHeaderError};
+    use crate::frame;
+
+    // the start of `echo hello | gzip`
+    let gzip = [0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03];
+    assert_eq!(frame::other_format(&gzip), Some("gzip"));
+    assert_eq!(frame::other_format(b"\xFD7zXZ\x00"), Some("xz"));
+    assert_eq!(frame::other_format(b"BZh91AY&SY"), Some("bzip2"));
+    assert_eq!(frame::other_format(&frame::MAGIC_NUM.to_le_bytes()), None);
+    assert_eq!(frame::other_format(&[0x1F]), None);
+
+    assert!(matches!(
+        frame::read_frame_header(&gzip[..]),
+        Err(FrameHeaderError::WrongFormat { detected: "gzip" })
+    ));
+    // shorter than a zstd frame header, the magic bytes are enough
+    assert!(matches!(
+        frame::read_frame_header(&b"BZh"[..]),
+        Err(FrameHeaderError::WrongFormat { detected: "bzip2" })
+    ));
+
+    let mut frame_dec = crate::frame_decoder::FrameDecoder::new();
+    let err = frame_dec.reset(&gzip[..]).unwrap_err();
+    assert!(matches!(
+        err,
+        FrameDecoderError::InvalidData(crate::errors::InvalidDataError::WrongFormat {
+            detected: "gzip"
+        })
+    ));
+    assert_eq!(err.category(), ErrorCategory::InvalidData);
+    assert!(err.to_string().contains("gzip"), "{}", err);
+    assert!(matches!(
+        crate::decompress(&gzip),
+        Err(FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::WrongFormat { detected: "gzip" }
+        ))
+    ));
+}
+
+#[test]
+fn test_decoders_are_send_and_sync() {
+    // this only has to compile
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<crate::frame_decoder::FrameDecoder>();
+    #[cfg(feature = "streaming")]
+    assert_send_sync::<crate::streaming_decoder::StreamingDecoder<&[u8]>>();
+    #[cfg(all(feature = "io-read", feature = "streaming"))]
+    assert_send_sync::<crate::streaming_decoder::StreamingDecoder<std::fs::File>>();
+
+    // a decoder can be moved into another thread
+    let content = std::fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let mut frame_dec = crate::frame_decoder::FrameDecoder::new();
+    let handle = std::thread::spawn(move || {
+        let (output, _) = frame_dec.decode_frame(&content).unwrap();
+        output
+    });
+    assert!(handle.join().unwrap() == std::fs::read("./decodecorpus_files/z000068").unwrap());
+}
+
+#[test]
+fn test_frame_content_size_field() {
+    use crate::frame;
+
+    // (frame descriptor, frame content size field, expected content size)
+    // the window descriptor is only present without the single segment flag (0x20)
+    let cases: &[(u8, &[u8], u64)] = &[
+        // single segment frames with flag 0 have a 1 byte field
+        (0x20, &[0], 0),
+        (0x20, &[0xFF], 255),
+        // the 2 byte field is little endian plus 256
+        (0x40, &[0x00, 0x00], 256),
+        (0x40, &[0xFF, 0xFF], 65791),
+        (0x40, &[0x34, 0x12], 0x1234 + 256),
+        (0x60, &[0x01, 0x00], 257),
+        // the 4 and 8 byte fields are little endian without an offset
+        (0x80, &[0x78, 0x56, 0x34, 0x12], 0x1234_5678),
+        (0x80, &[0xFF, 0xFF, 0xFF, 0xFF], u64::from(u32::MAX)),
+        (
+            0xC0,
+            &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+            0x0807_0605_0403_0201,
+        ),
+        (0xE0, &[0xFF; 8], u64::MAX),
+    ];
+
+    for &(descriptor, field, expected) in cases {
+        let mut fields = vec![descriptor];
+        if descriptor & 0x20 == 0 {
+            fields.push(0);
+        }
+        fields.extend_from_slice(field);
+        let header = test_frame(&fields, &[]);
+
+        let (frame, header_size) = frame::read_frame_header(header.as_slice()).unwrap();
+        assert_eq!(header_size as usize, header.len());
+        assert_eq!(
+            frame.header.frame_content_size().unwrap(),
+            expected,
+            "descriptor: {:#x}, field: {:?}",
+            descriptor,
+            field
+        );
+        assert_eq!(frame::decompressed_size(&header).unwrap(), Some(expected));
+    }
+
+    // without the single segment flag, flag 0 means there is no content size
+    let header = test_frame(&[0, 0], &[]);
+    assert_eq!(frame::decompressed_size(&header).unwrap(), None);
+}
+
+#[test]
+fn test_frame_header_equality() {
+    use std::collections::HashSet;
+    use std::fs;
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let second = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let (frame1, _) = crate::frame::read_frame_header(first.as_slice()).unwrap();
+    let (frame2, _) = crate::frame::read_frame_header(first.as_slice()).unwrap();
+    let (frame3, _) = crate::frame::read_frame_header(second.as_slice()).unwrap();
+
+    assert_eq!(frame1.header, frame2.header);
+    assert_eq!(frame1.header.clone(), frame2.header);
+    assert_ne!(frame1.header, frame3.header);
+
+    // the same header without the checksum flag only differs in the descriptor
+    let mut no_checksum = first.clone();
+    no_checksum[4] &= !0x4;
+    let (frame4, _) = crate::frame::read_frame_header(no_checksum.as_slice()).unwrap();
+    assert_ne!(frame1.header, frame4.header);
+    assert_ne!(frame1.header.descriptor, frame4.header.descriptor);
+
+    let headers: HashSet<_> = [frame1.header, frame2.header, frame3.header, frame4.header]
+        .iter()
+        .cloned()
+        .collect();
+    assert_eq!(headers.len(), 3);
+}
+
+#[test]
+fn test_error_categories() {
+    use crate::errors::{ErrorCategory, FrameDecoderError};
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // z000068: 6 byte frame header, the first block header at 6 and its content up to 500 (literals first, the
+    // sequence bitstream last), the second block up to 585 and then the checksum
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    // the category always matches the top level variant, so callers can match on either
+    let category = |input: &[u8]| {
+        let err = FrameDecoder::new().decode_frame(input).unwrap_err();
+        match (&err, err.category()) {
+            (FrameDecoderError::UnexpectedEof { .. }, ErrorCategory::UnexpectedEof)
+            | (FrameDecoderError::InvalidData(_), ErrorCategory::InvalidData) => {}
+            _ => panic!("Error {:?} has the category {:?}", err, err.category()),
+        }
+        err.category()
+    };
+
+    // frame header
+    assert_eq!(category(&content[..3]), ErrorCategory::UnexpectedEof);
+    let mut legacy = content.clone();
+    legacy[..4].copy_from_slice(&0xFD2F_B527u32.to_le_bytes());
+    assert_eq!(category(&legacy), ErrorCategory::InvalidData);
+    let mut reserved_bit = content.clone();
+    reserved_bit[4] |= 0x8;
+    assert_eq!(category(&reserved_bit), ErrorCategory::InvalidData);
+
+    // block header
+    assert_eq!(category(&content[..7]), ErrorCategory::UnexpectedEof);
+    let mut reserved_block = content.clone();
+    reserved_block[6] |= 3 << 1;
+    assert_eq!(category(&reserved_block), ErrorCategory::InvalidData);
+
+    // literals section
+    assert_eq!(category(&content[..10]), ErrorCategory::UnexpectedEof);
+    let mut too_many_literals = content.clone();
+    // raw literals with a 20 bit size that is bigger than the block
+    too_many_literals[9..12].copy_from_slice(&[0xFC, 0xFF, 0xFF]);
+    assert_eq!(category(&too_many_literals), ErrorCategory::InvalidData);
+
+    // sequences section
+    assert_eq!(category(&content[..495]), ErrorCategory::UnexpectedEof);
+    let mut no_stop_bit = content.clone();
+    no_stop_bit[499] = 0;
+    assert_eq!(category(&no_stop_bit), ErrorCategory::InvalidData);
+
+    // checksum
+    assert_eq!(category(&content[..587]), ErrorCategory::UnexpectedEof);
+
+    // errors of the source and usage errors are neither
+    struct FailingSource;
+    impl std::io::Read for FailingSource {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "connection lost",
+            ))
+        }
+    }
+    let mut frame_dec = FrameDecoder::new();
+    let err = frame_dec.reset(FailingSource).unwrap_err();
+    assert!(matches!(err, FrameDecoderError::FailedToReadSource(_)));
+    assert_eq!(err.category(), ErrorCategory::Other);
+    assert_eq!(
+        frame_dec
+            .decode_blocks(&content[..], crate::BlockDecodingStrategy::All)
+            .unwrap_err()
+            .category(),
+        ErrorCategory::Other
+    );
+
+    // the same categories come out of the io::Read of the StreamingDecoder
+    #[cfg(feature = "streaming")]
+    {
+        let read_kind = |input: &[u8]| {
+            use std::io::Read;
+            let mut decoder = crate::StreamingDecoder::new(input).unwrap();
+            let mut output = Vec::new();
+            decoder.read_to_end(&mut output).unwrap_err().kind()
+        };
+        assert_eq!(
+            read_kind(&content[..495]),
+            std::io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(read_kind(&no_stop_bit), std::io::ErrorKind::InvalidData);
+    }
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn test_log_records() {
+    use std::cell::RefCell;
+    use std::fs;
+
+    thread_local! {
+        // tests run in parallel, so every test thread only sees its own records
+        static RECORDS: RefCell<Vec<(log::Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+    struct CaptureLogger;
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record<'_>) {
+            RECORDS.with(|r| {
+                r.borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+        fn flush(&self) {}
+    }
+    static LOGGER: CaptureLogger = CaptureLogger;
+    // another test might have installed it already
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    crate::decompress(&content).unwrap();
+
+    let take_records = || RECORDS.with(|r| r.borrow_mut().split_off(0));
+    let messages = |level| {
+        take_records()
+            .into_iter()
+            .filter(|(l, _)| *l == level)
+            .map(|(_, m)| m)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        take_records(),
+        vec![
+            (
+                log::Level::Debug,
+                "Frame header of 6 bytes: window size 3407872, content size None, dictionary id None, checksum true"
+                    .to_owned()
+            ),
+            (
+                log::Level::Trace,
+                "Decoded Compressed block 1 with 491 bytes of content, 919 bytes decoded so far".to_owned()
+            ),
+            (
+                log::Level::Trace,
+                "Decoded Compressed block 2 with 82 bytes of content, 1126 bytes decoded so far".to_owned()
+            ),
+            (log::Level::Debug, "Read frame checksum 7c4aff3e".to_owned()),
+            (
+                log::Level::Debug,
+                "Frame checksum matches the decoded content".to_owned()
+            ),
+        ]
+    );
+
+    let mut wrong_checksum = content.clone();
+    *wrong_checksum.last_mut().unwrap() ^= 1;
+    crate::decompress(&wrong_checksum).unwrap();
+    assert_eq!(
+        messages(log::Level::Debug)[1..],
+        [
+            "Read frame checksum 7d4aff3e".to_owned(),
+            "Frame checksum mismatch, the decoded content hashes to 7c4aff3e".to_owned()
+        ]
+    );
+}
+
+#[cfg(test)]
+fn decode_rle_sequence(
+    ll_code: u8,
+    of_code: u8,
+    ml_code: u8,
+    bitstream: &[u8],
+) -> Result<(u32, u32, u32), String> {
+    use crate::blocks::sequence_section::SequencesHeader;
+    use crate::decoding::scratch::FSEScratch;
+    use crate::decoding::sequence_section_decoder::decode_sequences;
+
+    // one sequence, all three codes are rle
+    let mut header = SequencesHeader::new();
+    assert_eq!(header.parse_from_header(&[1, 0x54]).unwrap(), 2);
+    let mut source = vec![ll_code, of_code, ml_code];
+    source.extend_from_slice(bitstream);
+
+    let mut scratch = FSEScratch::new();
+    let mut sequences = Vec::new();
+    decode_sequences(&header, &source, &mut scratch, &mut sequences)?;
+    assert_eq!(sequences.len(), 1);
+    Ok((sequences[0].ll, sequences[0].ml, sequences[0].of))
+}
+
+#[test]
+fn test_sequence_lengths_with_maximal_extra_bits() {
+    // below the end marker the reversed bitstream has 16 bits of match length and 16 bits of literal length,
+    // all set. Offset code 0 has no extra bits
+    let bitstream = [0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+    assert_eq!(
+        decode_rle_sequence(35, 0, 52, &bitstream).unwrap(),
+        (65536 + 0xFFFF, 65539 + 0xFFFF, 1)
+    );
+
+    // codes beyond the highest ones are rejected instead of reading 255 extra bits
+    let err = decode_rle_sequence(36, 0, 52, &bitstream).unwrap_err();
+    assert!(err.contains("literal length code: 36"), "{}", err);
+    let err = decode_rle_sequence(35, 0, 53, &bitstream).unwrap_err();
+    assert!(err.contains("match length code: 53"), "{}", err);
+}
+
 #[test]
 fn test_block_header_reading() {
     use crate::decoding;
@@ -52,12 +608,9 @@ fn test_frame_decoder() {
 #[test]
 fn test_decode_from_to() {
     use crate::frame_decoder;
-    use std::fs::File;
-    use std::io::Read;
-    let f = File::open("./decodecorpus_files/z000088.zst").unwrap();
     let mut frame_dec = frame_decoder::FrameDecoder::new();
 
-    let content: Vec<u8> = f.bytes().map(|x| x.unwrap()).collect();
+    let content: Vec<u8> = std::fs::read("./decodecorpus_files/z000088.zst").unwrap();
 
     let mut target = vec![0u8; 1024 * 1024];
 
@@ -114,8 +667,7 @@ fn test_decode_from_to() {
         None => println!("No checksums to test\n"),
     }
 
-    let original_f = File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = std::fs::read("./decodecorpus_files/z000088").unwrap();
 
     if original.len() != result.len() {
         panic!(
@@ -149,7 +701,6 @@ fn test_decode_from_to() {
 fn test_specific_file() {
     use crate::frame_decoder;
     use std::fs;
-    use std::io::Read;
 
     let path = "./decodecorpus_files/z000068.zst";
     let mut content = fs::File::open(path).unwrap();
@@ -172,8 +723,7 @@ fn test_specific_file() {
         .unwrap();
     let result = frame_dec.collect().unwrap();
 
-    let original_f = fs::File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = fs::read("./decodecorpus_files/z000088").unwrap();
 
     println!("Results for file: {}", path);
 
@@ -205,6 +755,30 @@ fn test_specific_file() {
     }
 }
 
+#[cfg(all(feature = "io-read", feature = "streaming"))]
+#[test]
+fn test_streaming_from_path() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::fs;
+    use std::io::Read;
+
+    let mut stream = StreamingDecoder::from_path("./decodecorpus_files/z000088.zst").unwrap();
+    let mut result = Vec::new();
+    stream.read_to_end(&mut result).unwrap();
+    assert!(result == fs::read("./decodecorpus_files/z000088").unwrap());
+
+    let missing = StreamingDecoder::from_path("./decodecorpus_files/does_not_exist.zst");
+    assert_eq!(missing.err().unwrap().kind(), std::io::ErrorKind::NotFound);
+    // a file that is not a zstd frame is rejected when opening it
+    let not_zstd = StreamingDecoder::from_path("./decodecorpus_files/z000088");
+    assert_eq!(
+        not_zstd.err().unwrap().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+// File is only a ByteSource through the impl for std::io::Read
+#[cfg(all(feature = "io-read", feature = "streaming"))]
 #[test]
 fn test_streaming() {
     use std::fs;
@@ -216,8 +790,7 @@ fn test_streaming() {
     let mut result = Vec::new();
     Read::read_to_end(&mut stream, &mut result).unwrap();
 
-    let original_f = fs::File::open("./decodecorpus_files/z000088").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = fs::read("./decodecorpus_files/z000088").unwrap();
 
     if original.len() != result.len() {
         panic!(
@@ -256,8 +829,7 @@ fn test_streaming() {
     let mut result = Vec::new();
     Read::read_to_end(&mut stream, &mut result).unwrap();
 
-    let original_f = fs::File::open("./decodecorpus_files/z000068").unwrap();
-    let original: Vec<u8> = original_f.bytes().map(|x| x.unwrap()).collect();
+    let original: Vec<u8> = fs::read("./decodecorpus_files/z000068").unwrap();
 
     println!("Results for file:");
 
@@ -289,7 +861,2942 @@ fn test_streaming() {
     }
 }
 
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_byte_source() {
+    use crate::decoding::source::ByteSource;
+    use std::fs;
+    use std::io::Read;
+
+    fn decode(source: impl ByteSource) -> Vec<u8> {
+        let mut stream = crate::streaming_decoder::StreamingDecoder::new(source).unwrap();
+        let mut result = Vec::new();
+        Read::read_to_end(&mut stream, &mut result).unwrap();
+        result
+    }
+
+    // this works without the io-read feature, where &[u8] has its own ByteSource impl
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+    assert!(decode(content.as_slice()) == original);
+    let mut source = content.as_slice();
+    assert!(decode(&mut source) == original);
+    assert!(source.is_empty());
+
+    // a source that only implements ByteSource can be borrowed, with io-read &mut S is only a ByteSource if S is a Read
+    #[cfg(not(feature = "io-read"))]
+    {
+        struct OnlyByteSource<'a>(&'a [u8]);
+        impl ByteSource for OnlyByteSource<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+                ByteSource::read(&mut self.0, buf)
+            }
+        }
+        let mut source = OnlyByteSource(&content);
+        assert!(decode(&mut source) == original);
+        assert!(source.0.is_empty());
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_reset() {
+    use std::fs;
+    use std::io::Read;
+
+    let mut content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    content.extend(fs::read("./decodecorpus_files/z000068.zst").unwrap());
+
+    let mut stream = crate::streaming_decoder::StreamingDecoder::new(content.as_slice()).unwrap();
+
+    let mut result = Vec::new();
+    Read::read_to_end(&mut stream, &mut result).unwrap();
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+    assert!(original == result, "First frame decoded wrong");
+
+    assert!(stream.reset().unwrap());
+    let mut result = Vec::new();
+    Read::read_to_end(&mut stream, &mut result).unwrap();
+    let original = fs::read("./decodecorpus_files/z000068").unwrap();
+    assert!(original == result, "Second frame decoded wrong");
+
+    // nothing left in the source
+    assert!(!stream.reset().unwrap());
+
+    // reset in the middle of a frame skips the rest of it, and skippable frames before the next one are skipped too
+    let mut content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    content.extend(skippable_frame(1, &[]));
+    content.extend(skippable_frame(1, &[0xAA; 5]));
+    content.extend(fs::read("./decodecorpus_files/z000068.zst").unwrap());
+    let mut stream = crate::streaming_decoder::StreamingDecoder::new(content.as_slice()).unwrap();
+    let mut start = [0u8; 10];
+    stream.read_exact(&mut start).unwrap();
+    assert_eq!(
+        start,
+        fs::read("./decodecorpus_files/z000088").unwrap()[..10]
+    );
+    assert!(stream.reset().unwrap());
+    let mut result = Vec::new();
+    Read::read_to_end(&mut stream, &mut result).unwrap();
+    assert!(result == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert!(!stream.reset().unwrap());
+
+    // right after creating the decoder nothing was decoded, the whole first frame is skipped
+    let mut stream = crate::streaming_decoder::StreamingDecoder::new(content.as_slice()).unwrap();
+    assert!(stream.reset().unwrap());
+    let mut result = Vec::new();
+    Read::read_to_end(&mut stream, &mut result).unwrap();
+    assert!(result == fs::read("./decodecorpus_files/z000068").unwrap());
+
+    // a skippable frame that is cut off
+    let mut cut = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    cut.extend(skippable_frame(0, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+    cut.truncate(cut.len() - 7);
+    let mut stream = crate::streaming_decoder::StreamingDecoder::new(cut.as_slice()).unwrap();
+    assert!(matches!(
+        stream.reset(),
+        Err(crate::errors::FrameDecoderError::UnexpectedEof {
+            bytes_missing: 7,
+            ..
+        })
+    ));
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_with_buffer() {
+    use crate::decoding::ringbuffer::RingBuffer;
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::fs;
+    use std::io::Read;
+
+    let mut buffer = RingBuffer::new();
+    let mut capacity = 0;
+    // z000000 is a single segment frame, the buffer is kept aside while it is decoded
+    for name in &["z000068", "z000088", "z000000", "z000068"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let mut decoder = StreamingDecoder::with_buffer(content.as_slice(), buffer).unwrap();
+        let mut result = Vec::new();
+        decoder.read_to_end(&mut result).unwrap();
+        assert!(result == fs::read(format!("./decodecorpus_files/{}", name)).unwrap());
+
+        buffer = decoder.into_buffer();
+        assert!(buffer.is_empty());
+        // the allocation is passed along instead of being replaced by a new one
+        assert!(buffer.capacity() >= capacity);
+        capacity = buffer.capacity();
+    }
+    assert!(capacity > 0);
+}
+
+#[test]
+fn test_decode_slices() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let second = fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    let mut input = first.clone();
+    input.extend_from_slice(&second);
+
+    // five separately allocated pieces, split inside the magic number, inside the content of the first block,
+    // inside the header of the second block and inside the checksum
+    let bounds = [0, 3, 250, 501, 587, input.len()];
+    let pieces: Vec<Vec<u8>> = bounds
+        .windows(2)
+        .map(|w| input[w[0]..w[1]].to_vec())
+        .collect();
+    let slices: Vec<&[u8]> = pieces.iter().map(|p| p.as_slice()).collect();
+    assert_eq!(slices.len(), 5);
+
+    let mut frame_dec = FrameDecoder::new();
+    let (output, consumed) = frame_dec.decode_slices(&slices).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert_eq!(consumed, first.len());
+    assert_eq!(
+        frame_dec.get_checksum_from_data(),
+        frame_dec.get_calculated_checksum()
+    );
+
+    // the second frame starts in the middle of the last slice
+    let rest = [&slices[4][consumed - 587..]];
+    let (output, consumed) = frame_dec.decode_slices(&rest).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000019").unwrap());
+    assert_eq!(consumed, second.len());
+}
+
+#[test]
+fn test_decode_to_ring_buffer() {
+    use crate::decoding::ringbuffer::RingBuffer;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // the caller owns the buffer and already has some bytes in it, some of which were consumed
+    let mut target = RingBuffer::new();
+    target.extend(b"already consumed, still in the buffer");
+    target.drain(b"already consumed, ".len());
+
+    let mut expected = b"still in the buffer".to_vec();
+    let mut frame_dec = FrameDecoder::new();
+    assert!(matches!(
+        frame_dec.decode_to_ring_buffer(&[][..], &mut target),
+        Err(crate::errors::FrameDecoderError::NotYetInitialized)
+    ));
+
+    for name in &["z000088", "z000068"] {
+        let mut content = fs::File::open(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        frame_dec.reset(&mut content).unwrap();
+        let appended = frame_dec
+            .decode_to_ring_buffer(&mut content, &mut target)
+            .unwrap();
+
+        let original = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+        assert_eq!(appended, original.len());
+        expected.extend_from_slice(&original);
+    }
+
+    assert_eq!(target.len(), expected.len());
+    assert!(target.contiguous() == expected.as_slice());
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_chunks() {
+    use std::fs;
+
+    for name in &["z000088", "z000068", "z000000"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let stream = crate::streaming_decoder::StreamingDecoder::new(content.as_slice()).unwrap();
+
+        let mut result = Vec::new();
+        for chunk in stream.chunks() {
+            let chunk = chunk.unwrap();
+            assert!(!chunk.is_empty());
+            result.extend(chunk);
+        }
+
+        let original = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+        assert!(
+            original == result,
+            "Chunks differ from original for {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_decode_append() {
+    use crate::frame_decoder;
+    use std::fs;
+
+    let names = ["z000088", "z000068", "z000000"];
+    let mut frame_dec = frame_decoder::FrameDecoder::new();
+
+    let mut input = Vec::new();
+    let mut expected = b"existing content".to_vec();
+    for name in &names {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let mut single = Vec::new();
+        frame_dec.decode_append(&content, &mut single).unwrap();
+        expected.extend(single);
+        input.extend(content);
+    }
+
+    let mut out = b"existing content".to_vec();
+    let mut pos = 0;
+    for _ in &names {
+        pos += frame_dec.decode_append(&input[pos..], &mut out).unwrap();
+    }
+    assert_eq!(pos, input.len());
+    assert!(
+        out == expected,
+        "Appended output differs from the single decodes"
+    );
+}
+
+#[test]
+fn test_single_segment() {
+    use crate::frame_decoder;
+    use std::fs;
+
+    // these frames have the single_segment_flag set and get decoded without the ringbuffer
+    for name in &["z000000", "z000043"] {
+        let mut content = fs::File::open(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let mut frame_dec = frame_decoder::FrameDecoder::new();
+        frame_dec.reset(&mut content).unwrap();
+        frame_dec
+            .decode_blocks(&mut content, frame_decoder::BlockDecodingStrategy::All)
+            .unwrap();
+        let result = frame_dec.collect().unwrap();
+
+        let original = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+        assert!(
+            original == result,
+            "Result differs from original for {}",
+            name
+        );
+        assert_eq!(
+            frame_dec.get_checksum_from_data(),
+            frame_dec.get_calculated_checksum()
+        );
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_tiny_single_segment_frames() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::io::Read;
+
+    // a single segment descriptor with a 1 byte frame content size and a last rle block of 1 byte
+    let size_1 = test_frame(&[0x20, 1], &[TestBlock::Rle(b'x', 1)]);
+
+    // raw block of 3 bytes, then a last rle block of 2 bytes
+    let size_5 = test_frame(
+        &[0x20, 5],
+        &[TestBlock::Raw(b"abc"), TestBlock::Rle(b'd', 2)],
+    );
+
+    // the 2 byte frame content size has an offset of 256, so 0 means 256.
+    // raw literals "ab" then one sequence with rle codes: literal length 2, offset code 2, match length code 43.
+    // The bitstream holds the offset extra bits 0b01 (offset 2) and the match length extra bits 123 (254 bytes)
+    let block = [0x10, b'a', b'b', 1, 0x54, 2, 2, 43, 0xFB, 0x02];
+    let size_256 = test_frame(&[0x60, 0, 0], &[TestBlock::Compressed(&block)]);
+
+    let expected_256: Vec<u8> = b"ab".iter().copied().cycle().take(256).collect();
+    let cases: [(&[u8], &[u8]); 3] = [
+        (&size_1, b"x"),
+        (&size_5, b"abcdd"),
+        (&size_256, &expected_256),
+    ];
+
+    for (frame, expected) in cases.iter() {
+        let (header, _) = crate::frame::read_frame_header(*frame).unwrap();
+        assert!(header.header.descriptor.single_segment_flag());
+        // the window is exactly the content, there is no minimum window size for single segment frames
+        assert_eq!(header.header.window_size().unwrap(), expected.len() as u64);
+
+        assert_eq!(&crate::decompress(frame).unwrap(), expected);
+
+        // read in small pieces so the window is drained while decoding
+        let mut decoder = StreamingDecoder::new(*frame).unwrap();
+        let mut result = Vec::new();
+        let mut buf = [0u8; 3];
+        loop {
+            let read = decoder.read(&mut buf).unwrap();
+            if read == 0 {
+                break;
+            }
+            result.extend_from_slice(&buf[..read]);
+        }
+        assert_eq!(&result, expected);
+    }
+}
+
+#[test]
+fn test_prime_window() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    // descriptor without content size and a window of 1kb
+    let compressed_block = |block: &[u8]| test_frame(&[0, 0], &[TestBlock::Compressed(block)]);
+    // no literals and one sequence with rle codes: literal length 0, offset code 2, match length code 2.
+    // The offset extra bits 0b10 make it offset 3 with a match length of 5
+    let short_match = compressed_block(&[0, 1, 0x54, 0, 2, 2, 0x06]);
+    // the same with offset code 10 and the extra bits 4, which is offset 1025
+    let long_match = compressed_block(&[0, 1, 0x54, 0, 10, 2, 0x04, 0x04]);
+
+    let mut frame_dec = FrameDecoder::new();
+    assert!(matches!(
+        frame_dec.prime_window(b"xyz"),
+        Err(crate::errors::FrameDecoderError::NotYetInitialized)
+    ));
+
+    frame_dec.reset(short_match.as_slice()).unwrap();
+    frame_dec.prime_window(b"xyz").unwrap();
+    frame_dec
+        .decode_blocks(&short_match[6..], BlockDecodingStrategy::All)
+        .unwrap();
+    assert_eq!(frame_dec.collect().unwrap(), b"xyzxy");
+
+    // without the primed content the match has nothing to reference
+    frame_dec.reset(short_match.as_slice()).unwrap();
+    assert!(frame_dec
+        .decode_blocks(&short_match[6..], BlockDecodingStrategy::All)
+        .is_err());
+
+    // only the trailing 1kb of oversized data is kept
+    let mut data = vec![b'a'; 2000];
+    data.extend_from_slice(b"xyz");
+    frame_dec.reset(short_match.as_slice()).unwrap();
+    frame_dec.prime_window(&data).unwrap();
+    frame_dec
+        .decode_blocks(&short_match[6..], BlockDecodingStrategy::All)
+        .unwrap();
+    assert_eq!(frame_dec.collect().unwrap(), b"xyzxy");
+
+    frame_dec.reset(long_match.as_slice()).unwrap();
+    frame_dec.prime_window(&data).unwrap();
+    let err = frame_dec
+        .decode_blocks(&long_match[6..], BlockDecodingStrategy::All)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("1025 bytes from the dictionary"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_truncated_frames() {
+    use crate::errors::{EofContext, FrameDecoderError};
+    use crate::frame_decoder;
+    use std::fs;
+
+    fn decode_truncated(content: &[u8], len: usize) -> FrameDecoderError {
+        let mut source = &content[..len];
+        let mut frame_dec = frame_decoder::FrameDecoder::new();
+        if let Err(e) = frame_dec.reset(&mut source) {
+            return e;
+        }
+        if let Err(e) =
+            frame_dec.decode_blocks(&mut source, frame_decoder::BlockDecodingStrategy::All)
+        {
+            return e;
+        }
+        panic!("Decoding a frame truncated to {} bytes did not fail", len);
+    }
+
+    fn assert_eof(content: &[u8], len: usize, expected: EofContext, expected_missing: usize) {
+        match decode_truncated(content, len) {
+            FrameDecoderError::UnexpectedEof {
+                context,
+                bytes_missing,
+            } => {
+                assert_eq!(context, expected, "Wrong context for truncation at {}", len);
+                assert_eq!(
+                    bytes_missing, expected_missing,
+                    "Wrong number of missing bytes for truncation at {}",
+                    len
+                );
+            }
+            e => panic!(
+                "Expected UnexpectedEof for truncation at {}, got: {}",
+                len, e
+            ),
+        }
+    }
+
+    // 6 byte frame header, then a compressed block with 21195 bytes of content starting at offset 9.
+    // The literals section of that block ends at offset 16346
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    assert_eof(&content, 3, EofContext::FrameHeader, 2);
+    assert_eof(&content, 5, EofContext::FrameHeader, 1);
+    assert_eof(&content, 7, EofContext::BlockHeader, 2);
+    assert_eof(&content, 9 + 100, EofContext::Literals, 21195 - 100);
+    assert_eof(&content, 17000, EofContext::Sequences, 9 + 21195 - 17000);
+
+    // 9 byte frame header, then a raw block with 117308 bytes of content
+    let content = fs::read("./decodecorpus_files/z000043.zst").unwrap();
+    assert_eof(&content, 12 + 1000, EofContext::BlockContent, 117308 - 1000);
+}
+
+#[test]
+fn test_decompressed_size() {
+    use crate::frame;
+    use std::fs;
+
+    let mut with_size = 0;
+    let mut without_size = 0;
+    let mut files: Vec<_> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    for path in files {
+        let content = fs::read(&path).unwrap();
+        let original_len = fs::read(path.with_extension("")).unwrap().len() as u64;
+
+        match frame::decompressed_size(&content).unwrap() {
+            Some(size) => {
+                assert_eq!(size, original_len, "Wrong declared size for {:?}", path);
+                with_size += 1;
+            }
+            None => without_size += 1,
+        }
+        assert_eq!(
+            frame::scan_decompressed_size(&content).unwrap(),
+            original_len,
+            "Wrong scanned size for {:?}",
+            path
+        );
+    }
+
+    // make sure both kinds of frames were tested
+    assert!(with_size > 0);
+    assert!(without_size > 0);
+}
+
+#[test]
+fn test_inspect_blocks() {
+    use crate::blocks::block::BlockType;
+    use crate::frame;
+    use std::fs;
+
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let original_len = fs::read("./decodecorpus_files/z000068").unwrap().len() as u64;
+    let blocks = frame::inspect_blocks(&content).unwrap();
+    assert_eq!(blocks.len(), 2);
+
+    let second = &blocks[1];
+    assert!(second.block_type == BlockType::Compressed);
+    assert_eq!(second.content_size, 82);
+    assert_eq!(second.literals_length, 179);
+    assert_eq!(second.sequences.len(), 8);
+    let dumped: Vec<(u32, u32, u32)> = second
+        .sequences
+        .iter()
+        .map(|seq| (seq.ll, seq.ml, seq.of))
+        .collect();
+    assert_eq!(
+        dumped,
+        vec![
+            (0, 3, 2),
+            (68, 5, 169),
+            (0, 3, 733),
+            (22, 4, 451),
+            (0, 3, 301),
+            (0, 3, 441),
+            (41, 3, 982),
+            (48, 4, 1),
+        ]
+    );
+
+    // all literals and matches together make up the decoded frame
+    let mut total = 0;
+    for block in &blocks {
+        let literals_used: u64 = block.sequences.iter().map(|seq| u64::from(seq.ll)).sum();
+        assert!(literals_used <= u64::from(block.literals_length));
+        let match_bytes: u64 = block.sequences.iter().map(|seq| u64::from(seq.ml)).sum();
+        total += u64::from(block.literals_length) + match_bytes;
+    }
+    assert_eq!(total, original_len);
+}
+
+#[test]
+fn test_inspect_blocks_regenerated_size() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    for name in &["z000068", "z000088", "z000033", "z000000"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let blocks = crate::frame::inspect_blocks(&content).unwrap();
+
+        let mut source = content.as_slice();
+        let mut frame_dec = FrameDecoder::new();
+        frame_dec.reset(&mut source).unwrap();
+        for block in &blocks {
+            let before = frame_dec.bytes_decoded();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                .unwrap();
+            assert_eq!(
+                frame_dec.bytes_decoded() - before,
+                u64::from(block.regenerated_size),
+                "{}",
+                name
+            );
+        }
+        assert!(frame_dec.is_finished());
+    }
+}
+
+#[test]
+fn test_decode_blocks_raw() {
+    use crate::frame;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // the blocks of a corpus frame without its header and checksum, and its window size
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    let (frame, header_size) = frame::read_frame_header(content.as_slice()).unwrap();
+    assert!(frame.header.descriptor.content_checksum_flag());
+    let window_size = frame.header.window_size().unwrap();
+    let blocks = &content[header_size as usize..content.len() - 4];
+
+    let mut frame_dec = FrameDecoder::new();
+    let output = frame_dec.decode_blocks_raw(blocks, window_size).unwrap();
+    assert!(output == original);
+    assert!(frame_dec.is_finished());
+    assert_eq!(frame_dec.bytes_read_from_source(), blocks.len() as u64);
+
+    // the decoder can go on with normal frames afterwards
+    let (output, _) = frame_dec.decode_frame(&content).unwrap();
+    assert!(output == original);
+
+    // the window is checked like the one of a frame header
+    match frame_dec.decode_blocks_raw(blocks, 1 << 40) {
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::Initialize(msg),
+        )) => {
+            assert!(msg.contains("window_sizes"), "{}", msg)
+        }
+        other => panic!("{:?}", other.map(|output| output.len())),
+    }
+}
+
+#[test]
+fn test_decode_headerless() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+
+    // store the header and the body separately, like a container format would
+    let (frame, header_size) = frame::read_frame_header(content.as_slice()).unwrap();
+    let header = frame.header;
+    let body = &content[header_size as usize..];
+
+    let mut frame_dec = FrameDecoder::new();
+    assert_eq!(
+        frame_dec.decode_headerless(&header, body).unwrap(),
+        body.len()
+    );
+    assert!(frame_dec.is_finished());
+    assert_eq!(frame_dec.collect().unwrap(), original);
+    assert_eq!(
+        frame_dec.computed_checksum(),
+        frame_dec.get_checksum_from_data()
+    );
+
+    // a single segment header with a content size of 10 bytes can not belong to a body starting with a big raw block
+    let small_header = test_frame(&[0x20, 10], &[]);
+    let (small_frame, _) = frame::read_frame_header(small_header.as_slice()).unwrap();
+    let content = fs::read("./decodecorpus_files/z000043.zst").unwrap();
+    let (_, header_size) = frame::read_frame_header(content.as_slice()).unwrap();
+    match frame_dec.decode_headerless(&small_frame.header, &content[header_size as usize..]) {
+        Err(FrameDecoderError::InvalidData(crate::errors::InvalidDataError::Initialize(msg))) => {
+            assert!(msg.contains("First block"), "{}", msg)
+        }
+        Err(e) => panic!("Wrong error: {}", e),
+        Ok(_) => panic!("Mismatched header was accepted"),
+    }
+}
+
+#[test]
+fn test_current_hash() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+    use std::hash::Hasher;
+    use twox_hash::XxHash64;
+
+    let hash_of = |data: &[u8]| {
+        let mut hash = XxHash64::with_seed(0);
+        hash.write(data);
+        hash.finish()
+    };
+
+    for name in &["z000088", "z000068"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let original = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+
+        let mut frame_dec = FrameDecoder::new();
+        let mut source = content.as_slice();
+        frame_dec.reset(&mut source).unwrap();
+        assert_eq!(frame_dec.current_hash(), Some(hash_of(&[])));
+
+        // every block ends at a known position in the original
+        let mut block_end = 0;
+        let mut collected = Vec::new();
+        for block in crate::frame::inspect_blocks(&content).unwrap() {
+            let match_bytes: u64 = block.sequences.iter().map(|seq| u64::from(seq.ml)).sum();
+            block_end += (u64::from(block.literals_length) + match_bytes) as usize;
+
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                .unwrap();
+            assert_eq!(
+                frame_dec.current_hash(),
+                Some(hash_of(&original[..block_end])),
+                "Wrong hash in {} after block {}",
+                name,
+                frame_dec.blocks_decoded()
+            );
+            // collecting does not change the hash
+            collected.extend(frame_dec.collect().unwrap_or_default());
+            assert_eq!(
+                frame_dec.current_hash(),
+                Some(hash_of(&original[..block_end]))
+            );
+        }
+        assert!(frame_dec.is_finished());
+        collected.extend(frame_dec.collect().unwrap());
+        assert_eq!(collected, original);
+        assert_eq!(
+            frame_dec.current_hash().map(|hash| hash as u32),
+            frame_dec.computed_checksum()
+        );
+    }
+
+    // without a checksum in the frame there is no hash unless it is forced
+    let mut content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    content[4] &= !0x4;
+    content.truncate(content.len() - 4);
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(content.as_slice()).unwrap();
+    assert_eq!(frame_dec.current_hash(), None);
+    frame_dec.set_force_checksum(true);
+    frame_dec.reset(content.as_slice()).unwrap();
+    assert_eq!(frame_dec.current_hash(), Some(hash_of(&[])));
+}
+
+#[cfg(all(feature = "io-read", feature = "streaming"))]
+#[test]
+fn test_seekable_streaming_decoder() {
+    use crate::seekable_streaming_decoder::SeekableStreamingDecoder;
+    use std::fs;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    // remembers every position that was read
+    struct TrackingReader {
+        inner: Cursor<Vec<u8>>,
+        read_positions: Vec<(u64, usize)>,
+    }
+    impl Read for TrackingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let pos = self.inner.position();
+            let read = self.inner.read(buf)?;
+            self.read_positions.push((pos, read));
+            Ok(read)
+        }
+    }
+    impl Seek for TrackingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let second = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let mut expected = fs::read("./decodecorpus_files/z000068").unwrap();
+    expected.extend(fs::read("./decodecorpus_files/z000088").unwrap());
+
+    let payload_size = 1024 * 1024;
+    let mut data = first.clone();
+    let payload_start = data.len() as u64 + 8;
+    data.extend(skippable_frame(0, &vec![0xAB; payload_size]));
+    let payload_end = data.len() as u64;
+    data.extend_from_slice(&second);
+
+    let mut source = TrackingReader {
+        inner: Cursor::new(data),
+        read_positions: Vec::new(),
+    };
+    let mut decoder = SeekableStreamingDecoder::new(&mut source).unwrap();
+    let mut result = Vec::new();
+    decoder.read_to_end(&mut result).unwrap();
+    assert!(result == expected);
+    assert_eq!(decoder.skipped_bytes(), 8 + payload_size as u64);
+    drop(decoder);
+
+    for (pos, len) in &source.read_positions {
+        let end = pos + *len as u64;
+        assert!(
+            end <= payload_start || *pos >= payload_end,
+            "Read {}..{} of the skippable frame",
+            pos,
+            end
+        );
+    }
+
+    // a source without any zstd frame
+    let only_skippable = skippable_frame(15, &[1, 2, 3, 4]);
+    assert!(matches!(
+        SeekableStreamingDecoder::new(Cursor::new(only_skippable)),
+        Err(crate::errors::FrameDecoderError::UnexpectedEof { .. })
+    ));
+
+    // a skippable frame after the last zstd frame that is cut off is not the clean end of the source
+    let mut truncated = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    truncated.extend(skippable_frame(0, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+    truncated.truncate(truncated.len() - 7);
+    let mut decoder = SeekableStreamingDecoder::new(Cursor::new(truncated)).unwrap();
+    let mut output = Vec::new();
+    let err = decoder.read_to_end(&mut output).unwrap_err();
+    assert!(err.to_string().contains("7 more bytes"), "{}", err);
+    assert_eq!(output, fs::read("./decodecorpus_files/z000068").unwrap());
+}
+
+#[test]
+fn test_archive() {
+    use crate::archive::{Archive, ArchiveEntryKind};
+    use std::fs;
+
+    // z000019 does not declare its size, so it is scanned while listing
+    let names = ["z000068", "z000088", "z000019"];
+    let metadata = b"names: z000068 z000088 z000019".to_vec();
+    let mut data = Vec::new();
+    let mut offsets = Vec::new();
+    for (idx, name) in names.iter().enumerate() {
+        if idx == 1 {
+            offsets.push(data.len() as u64);
+            data.extend(skippable_frame(3, &metadata));
+        }
+        offsets.push(data.len() as u64);
+        data.extend(fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap());
+    }
+    let path = std::env::temp_dir().join(format!("ruzstd_archive_{}.zst", std::process::id()));
+    fs::write(&path, &data).unwrap();
+    let originals: Vec<Vec<u8>> = names
+        .iter()
+        .map(|name| fs::read(format!("./decodecorpus_files/{}", name)).unwrap())
+        .collect();
+
+    let mut archive = Archive::new(fs::File::open(&path).unwrap());
+    let entries = archive.entries().unwrap().to_vec();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(
+        entries.iter().map(|e| e.offset).collect::<Vec<_>>(),
+        offsets
+    );
+    assert_eq!(
+        entries.iter().map(|e| e.compressed_size).sum::<u64>(),
+        data.len() as u64
+    );
+    assert_eq!(
+        entries[1].kind,
+        ArchiveEntryKind::Skippable {
+            magic: crate::frame::SKIPPABLE_MAGIC_NUM_MIN + 3
+        }
+    );
+    for (entry_idx, original) in [(0, &originals[0]), (2, &originals[1]), (3, &originals[2])] {
+        assert_eq!(
+            entries[entry_idx].kind,
+            ArchiveEntryKind::Frame {
+                decompressed_size: Some(original.len() as u64)
+            }
+        );
+    }
+
+    // out of order, and the same frame twice
+    assert!(archive.read_frame(3).unwrap() == originals[2]);
+    assert!(archive.read_frame(0).unwrap() == originals[0]);
+    assert_eq!(archive.read_frame(1).unwrap(), metadata);
+    assert!(archive.read_frame(2).unwrap() == originals[1]);
+    assert!(archive.read_frame(0).unwrap() == originals[0]);
+
+    // a source that ends in the middle of the last frame can not be listed
+    let mut truncated = Archive::new(std::io::Cursor::new(&data[..data.len() - 2]));
+    assert!(matches!(
+        truncated.entries(),
+        Err(crate::errors::FrameDecoderError::UnexpectedEof { .. })
+    ));
+
+    // an entry whose content does not match its checksum
+    let mut corrupted = data.clone();
+    let second_end = (offsets[2] + entries[2].compressed_size) as usize;
+    corrupted[second_end - 1] ^= 1;
+    let mut corrupted = Archive::new(std::io::Cursor::new(corrupted));
+    assert!(matches!(
+        corrupted.read_frame(2),
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::ChecksumMismatch { .. }
+        ))
+    ));
+    assert!(corrupted.read_frame(3).unwrap() == originals[2]);
+
+    // z000019 is one rle block, turned into a raw block its header claims 7909 bytes the source does not have
+    let mut lying = data.clone();
+    lying[offsets[3] as usize + 6] &= !0x6;
+    let mut lying = Archive::new(std::io::Cursor::new(lying));
+    assert!(matches!(
+        lying.entries(),
+        Err(crate::errors::FrameDecoderError::UnexpectedEof {
+            context: crate::errors::EofContext::BlockContent,
+            bytes_missing: 7904,
+        })
+    ));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_decode_frame() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let second = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let mut input = first.clone();
+    input.extend_from_slice(&second);
+
+    let mut frame_dec = FrameDecoder::new();
+    let (output, trailing) = frame_dec.decode_frame(&input).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert_eq!(trailing, second.len());
+
+    let rest = &input[input.len() - trailing..];
+    let (output, trailing) = frame_dec.decode_frame(rest).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000088").unwrap());
+    assert_eq!(trailing, 0);
+}
+
+#[test]
+fn test_decompress() {
+    use std::fs;
+
+    let compressed = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let mut input = compressed.clone();
+    input.extend_from_slice(&compressed);
+    // only the first frame is decoded
+    let output = crate::decompress(&input).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+
+    assert!(matches!(
+        crate::decompress(&compressed[..100]),
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::BlockBody(_)
+        )) | Err(crate::errors::FrameDecoderError::UnexpectedEof { .. })
+    ));
+
+    // corruption that only the checksum can notice
+    let mut wrong_checksum = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    *wrong_checksum.last_mut().unwrap() ^= 1;
+    for result in [
+        crate::decompress(&wrong_checksum),
+        crate::decode_pooled(&wrong_checksum),
+    ] {
+        assert!(matches!(
+            result,
+            Err(crate::errors::FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::ChecksumMismatch { .. }
+            ))
+        ));
+    }
+}
+
+#[test]
+fn test_one_shot_checksum_mismatch() {
+    use crate::errors::{FrameDecoderError, InvalidDataError};
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+    let mut frame = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    *frame.last_mut().unwrap() ^= 1;
+    let is_mismatch = |e: FrameDecoderError| {
+        matches!(
+            e,
+            FrameDecoderError::InvalidData(InvalidDataError::ChecksumMismatch { .. })
+        )
+    };
+
+    let mut frame_dec = FrameDecoder::new();
+    assert!(is_mismatch(frame_dec.decode_frame(&frame).unwrap_err()));
+    let mut out = Vec::new();
+    assert!(is_mismatch(
+        frame_dec.decode_append(&frame, &mut out).unwrap_err()
+    ));
+    // the content is still appended
+    assert!(out == original);
+    let (first, second) = frame.split_at(1000);
+    assert!(is_mismatch(
+        frame_dec.decode_slices(&[first, second]).unwrap_err()
+    ));
+    assert!(is_mismatch(
+        frame_dec
+            .decode_known_size(&frame, original.len())
+            .unwrap_err()
+    ));
+    let mut target = vec![0u8; original.len()].into_boxed_slice();
+    assert!(is_mismatch(
+        frame_dec
+            .decompress_into_boxed(&frame, &mut target)
+            .unwrap_err()
+    ));
+    assert!(is_mismatch(
+        frame_dec
+            .decode_with_progress(&frame, |_, _| {})
+            .unwrap_err()
+    ));
+    assert!(is_mismatch(
+        frame_dec.decode_to_writer(&frame, Vec::new()).unwrap_err()
+    ));
+
+    // the intact frame still decodes with all of them
+    *frame.last_mut().unwrap() ^= 1;
+    assert!(frame_dec.decode_frame(&frame).unwrap().0 == original);
+    assert!(frame_dec.decode_to_writer(&frame, Vec::new()).is_ok());
+}
+
+#[test]
+fn test_decode_pooled() {
+    use std::fs;
+
+    // a big frame first so the smaller ones reuse its buffers, and a broken one in between
+    let big = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let small = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let tiny = fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    assert_eq!(
+        crate::decode_pooled(&big).unwrap(),
+        fs::read("./decodecorpus_files/z000088").unwrap()
+    );
+    assert!(crate::decode_pooled(&small[..100]).is_err());
+    for _ in 0..3 {
+        assert_eq!(
+            crate::decode_pooled(&small).unwrap(),
+            fs::read("./decodecorpus_files/z000068").unwrap()
+        );
+        assert_eq!(
+            crate::decode_pooled(&tiny).unwrap(),
+            fs::read("./decodecorpus_files/z000019").unwrap()
+        );
+    }
+
+    // every thread has its own decoder
+    let handle = std::thread::spawn(move || crate::decode_pooled(&small).unwrap());
+    assert_eq!(
+        handle.join().unwrap(),
+        fs::read("./decodecorpus_files/z000068").unwrap()
+    );
+}
+
+#[test]
+fn test_zero_window_memory() {
+    use crate::decoding::ringbuffer::RingBuffer;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // z000068 is a multi-segment frame, z000019 a single-segment one
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.set_zero_window_memory(true);
+    for name in ["z000068", "z000019", "z000068"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let (output, _) = frame_dec.decode_frame(&content).unwrap();
+        assert!(output == fs::read(format!("./decodecorpus_files/{}", name)).unwrap());
+    }
+
+    // also applies to a ring buffer given to the decoder
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.set_zero_window_memory(true);
+    frame_dec.use_ring_buffer(RingBuffer::new());
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let (output, _) = frame_dec.decode_frame(&content).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert!(frame_dec.take_ring_buffer().capacity() >= 3407872);
+}
+
+#[test]
+fn test_decode_known_size() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut files: Vec<_> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    // the same decoder alternates between the ringbuffer and the flat buffer
+    let mut frame_dec = FrameDecoder::new();
+    for path in files {
+        let content = fs::read(&path).unwrap();
+        let (expected, _) = frame_dec.decode_frame(&content).unwrap();
+        let output = frame_dec
+            .decode_known_size(&content, expected.len())
+            .unwrap();
+        assert!(output == expected, "Wrong output for {:?}", path);
+
+        match frame_dec.decode_known_size(&content, expected.len() + 1) {
+            Err(FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::ContentSizeMismatch { expected: e, .. },
+            )) => {
+                assert_eq!(e, expected.len() as u64 + 1)
+            }
+            _ => panic!("Too big size was accepted for {:?}", path),
+        }
+        if !expected.is_empty() {
+            assert!(
+                matches!(
+                    frame_dec.decode_known_size(&content, expected.len() - 1),
+                    Err(FrameDecoderError::InvalidData(
+                        crate::errors::InvalidDataError::ContentSizeMismatch { .. }
+                    ))
+                ),
+                "Too small size was accepted for {:?}",
+                path
+            );
+        }
+    }
+}
+
+#[test]
+fn test_decompress_into_boxed() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut files: Vec<_> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut frames = Vec::new();
+    for path in files {
+        let content = fs::read(&path).unwrap();
+        let (expected, _) = frame_dec.decode_frame(&content).unwrap();
+        frames.push((path, content, expected));
+    }
+
+    // one box that fits the biggest frame is reused for all of them
+    let max_len = frames.iter().map(|(_, _, e)| e.len()).max().unwrap();
+    let mut target = vec![0u8; max_len].into_boxed_slice();
+    let target_ptr = target.as_ptr();
+    for (path, content, expected) in &frames {
+        let written = frame_dec
+            .decompress_into_boxed(content, &mut target)
+            .unwrap();
+        assert_eq!(written, expected.len(), "Wrong length for {:?}", path);
+        assert!(
+            target[..written] == expected[..],
+            "Wrong output for {:?}",
+            path
+        );
+    }
+    assert_eq!(target.as_ptr(), target_ptr);
+    assert_eq!(target.len(), max_len);
+
+    // a box that is one byte too small is rejected, whether the frame declares its size or not
+    for (path, content, expected) in frames.iter().filter(|(_, _, e)| !e.is_empty()) {
+        let mut small = vec![0u8; expected.len() - 1].into_boxed_slice();
+        assert!(
+            matches!(
+                frame_dec.decompress_into_boxed(content, &mut small),
+                Err(FrameDecoderError::TargetTooSmall)
+            ),
+            "Too small box was accepted for {:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_decode_with_progress() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut frame_dec = FrameDecoder::new();
+    for name in ["z000019", "z000068", "z000088"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let (expected, _) = frame_dec.decode_frame(&content).unwrap();
+        let num_blocks = frame_dec.blocks_decoded();
+        let declared = frame_dec.content_size();
+
+        let mut calls = Vec::new();
+        let output = frame_dec
+            .decode_with_progress(&content, |decoded, total| calls.push((decoded, total)))
+            .unwrap();
+        assert!(output == expected, "Wrong output for {}", name);
+        assert_eq!(
+            calls.len() as u64,
+            num_blocks,
+            "Wrong number of calls for {}",
+            name
+        );
+        assert!(calls.iter().all(|&(_, total)| total == declared));
+        assert!(calls.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(calls.last().unwrap().0, expected.len() as u64);
+    }
+}
+
+#[test]
+fn test_trailing_bytes_in_compressed_block() {
+    use crate::errors::{ErrorCategory, FrameDecoderError};
+    use crate::frame_decoder::FrameDecoder;
+
+    // a compressed block with the 3 raw literals "abc" and no sequences, optionally followed by junk
+    // that is still inside of the declared block size
+    let frame_with_junk = |junk: &[u8]| {
+        let mut content = vec![3 << 3];
+        content.extend_from_slice(b"abc");
+        content.push(0);
+        content.extend_from_slice(junk);
+        test_frame(&[0, 0], &[TestBlock::Compressed(&content)])
+    };
+
+    let mut frame_dec = FrameDecoder::new();
+    let (output, _) = frame_dec.decode_frame(&frame_with_junk(&[])).unwrap();
+    assert_eq!(output, b"abc");
+    assert_eq!(
+        crate::frame::scan_decompressed_size(&frame_with_junk(&[])).unwrap(),
+        3
+    );
+
+    for junk in [&[0xFF][..], &[0, 0, 0]] {
+        let frame = frame_with_junk(junk);
+        match frame_dec.decode_frame(&frame) {
+            Err(
+                e @ FrameDecoderError::InvalidData(crate::errors::InvalidDataError::BlockBody(_)),
+            ) => {
+                assert_eq!(e.category(), ErrorCategory::InvalidData)
+            }
+            other => panic!("Trailing bytes were accepted: {:?}", other),
+        }
+        assert!(crate::frame::scan_decompressed_size(&frame).is_err());
+    }
+}
+
+#[test]
+fn test_into_window() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    // frame A is one raw block, frame B has no literals and one sequence with rle codes:
+    // literal length 0, offset code 2 with the extra bits 0b10 (offset 3) and match length 5
+    let frame_a = test_frame(&[0, 0], &[TestBlock::Raw(b"abcdefgh")]);
+    let frame_b = test_frame(
+        &[0, 0],
+        &[TestBlock::Compressed(&[0, 1, 0x54, 0, 2, 2, 0x06])],
+    );
+
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(frame_a.as_slice()).unwrap();
+    frame_dec
+        .decode_blocks(&frame_a[6..], BlockDecodingStrategy::All)
+        .unwrap();
+    let window = frame_dec.into_window();
+    assert_eq!(window, b"abcdefgh");
+
+    // the back reference of frame B resolves into the window of frame A
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(frame_b.as_slice()).unwrap();
+    frame_dec.prime_window(&window).unwrap();
+    frame_dec
+        .decode_blocks(&frame_b[6..], BlockDecodingStrategy::All)
+        .unwrap();
+    assert_eq!(frame_dec.collect().unwrap(), b"fghfg");
+
+    // collecting while the frame is not finished keeps the window, so the returned bytes complete the output
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let expected = fs::read("./decodecorpus_files/z000088").unwrap();
+    let mut source = content.as_slice();
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.reset(&mut source).unwrap();
+    let (frame, _) = crate::frame::read_frame_header(content.as_slice()).unwrap();
+    let window_size = frame.header.window_size().unwrap() as usize;
+    let mut output = Vec::new();
+    while !frame_dec.is_finished() {
+        output.extend(frame_dec.collect().unwrap_or_default());
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap();
+    }
+    let window = frame_dec.into_window();
+    assert!(window.len() >= usize::min(window_size, expected.len()));
+    output.extend_from_slice(&window);
+    assert!(output == expected);
+}
+
+#[test]
+fn test_default_decoder() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // no frame yet, just like a decoder from new()
+    let mut frame_dec = FrameDecoder::default();
+    let new_dec = FrameDecoder::new();
+    assert_eq!(frame_dec.is_finished(), new_dec.is_finished());
+    assert_eq!(frame_dec.content_size(), new_dec.content_size());
+    assert_eq!(frame_dec.blocks_decoded(), 0);
+    assert_eq!(frame_dec.can_collect(), 0);
+
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let (output, _) = frame_dec.decode_frame(&content).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+}
+
+#[test]
+fn test_window_size_cap() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+
+    // single segment frames use their content size as window, with 4 or 8 bytes of content size
+    let single_segment = |content_size: u64| {
+        let mut header = Vec::new();
+        if content_size <= u64::from(u32::MAX) {
+            header.push(2 << 6 | 1 << 5);
+            header.extend_from_slice(&(content_size as u32).to_le_bytes());
+        } else {
+            header.push(3 << 6 | 1 << 5);
+            header.extend_from_slice(&content_size.to_le_bytes());
+        }
+        // an empty raw block that is the last one
+        test_frame(&header, &[TestBlock::Raw(&[])])
+    };
+    // exponent 31 in the window descriptor, about 2TiB
+    let big_descriptor = test_frame(&[0, 31 << 3], &[TestBlock::Raw(&[])]);
+    let max = 100 * 1024 * 1024;
+
+    let mut frame_dec = FrameDecoder::new();
+    for frame in [
+        single_segment(max + 1),
+        single_segment(1 << 40),
+        big_descriptor,
+    ] {
+        let (header, _) = crate::frame::read_frame_header(frame.as_slice()).unwrap();
+        assert!(matches!(
+            frame_dec.can_decode(&header.header),
+            Err(FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::Initialize(_)
+            ))
+        ));
+        assert!(matches!(
+            frame_dec.reset(frame.as_slice()),
+            Err(FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::Initialize(_)
+            ))
+        ));
+        assert!(matches!(
+            frame_dec.decode_headerless(&header.header, &frame[frame.len() - 3..]),
+            Err(FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::Initialize(_)
+            ))
+        ));
+    }
+
+    // the cap itself is allowed, can_decode does not allocate the window
+    let frame = single_segment(max);
+    let (header, _) = crate::frame::read_frame_header(frame.as_slice()).unwrap();
+    frame_dec.can_decode(&header.header).unwrap();
+}
+
+// needs an allocation far bigger than the address space, which only fails cleanly on 64 bit targets
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_window_allocation_failure() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    // a single segment frame whose window is its content size of 4EiB
+    let frame = test_frame(
+        &[&[3 << 6 | 1 << 5][..], &(1u64 << 62).to_le_bytes()].concat(),
+        &[TestBlock::Raw(&[])],
+    );
+
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.set_max_window_size(u64::MAX);
+    for _ in 0..2 {
+        assert!(matches!(
+            frame_dec.reset(frame.as_slice()),
+            Err(FrameDecoderError::FailedToAllocate(_))
+        ));
+        // the failed frame can not be decoded
+        assert!(matches!(
+            frame_dec.decode_blocks(&mut &frame[frame.len() - 3..], BlockDecodingStrategy::All),
+            Err(FrameDecoderError::NotYetInitialized)
+        ));
+        // and the decoder still works for frames that fit
+        let content = std::fs::read("./decodecorpus_files/z000068.zst").unwrap();
+        let expected = std::fs::read("./decodecorpus_files/z000068").unwrap();
+        assert_eq!(frame_dec.decode_frame(&content).unwrap().0, expected);
+    }
+}
+
+#[test]
+fn test_next_block_into() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut out = vec![0u8; 128 * 1024];
+    assert!(matches!(
+        frame_dec.next_block_into(&mut &[][..], &mut out),
+        Err(FrameDecoderError::NotYetInitialized)
+    ));
+
+    for name in ["z000068", "z000088", "z000019"] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let expected = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+        let mut input = content.as_slice();
+        frame_dec.reset(&mut input).unwrap();
+
+        let mut output = Vec::new();
+        let mut blocks = 0;
+        while let Some(produced) = frame_dec.next_block_into(&mut input, &mut out).unwrap() {
+            output.extend_from_slice(&out[..produced]);
+            blocks += 1;
+        }
+        assert!(output == expected, "Wrong output for {}", name);
+        assert_eq!(blocks, frame_dec.blocks_decoded());
+        assert!(input.is_empty());
+        assert_eq!(
+            frame_dec.get_checksum_from_data(),
+            frame_dec.get_calculated_checksum()
+        );
+        assert_eq!(
+            frame_dec.next_block_into(&mut input, &mut out).unwrap(),
+            None
+        );
+    }
+
+    // a target that can not hold the biggest possible block is rejected up front
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let mut input = content.as_slice();
+    frame_dec.reset(&mut input).unwrap();
+    let header_end = input.len();
+    assert!(matches!(
+        frame_dec.next_block_into(&mut input, &mut out[..1000]),
+        Err(FrameDecoderError::TargetTooSmall)
+    ));
+    assert_eq!(input.len(), header_end);
+}
+
+#[test]
+fn test_with_initial_capacity() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = std::fs::read("./decodecorpus_files/z000033").unwrap();
+
+    let decode = |decoder: &mut FrameDecoder| {
+        let mut source = frame.as_slice();
+        decoder.reset(&mut source).unwrap();
+        decoder
+            .decode_blocks(&mut source, BlockDecodingStrategy::All)
+            .unwrap();
+        assert_eq!(decoder.collect().unwrap(), original);
+    };
+
+    // without a preallocated buffer the ringbuffer has to grow past the window while the frame is decoded
+    let mut decoder = FrameDecoder::new();
+    decode(&mut decoder);
+    let grown = decoder.take_ring_buffer().capacity();
+
+    // with enough room for the whole frame it stays the allocation that was made up front, also after a reset
+    let mut decoder = FrameDecoder::with_initial_capacity(original.len());
+    let ring = decoder.take_ring_buffer();
+    let initial = ring.capacity();
+    assert!(initial >= original.len());
+    decoder.use_ring_buffer(ring);
+    decode(&mut decoder);
+    decode(&mut decoder);
+    let after = decoder.take_ring_buffer().capacity();
+    assert_eq!(after, initial);
+    assert!(grown > 0);
+}
+
+#[test]
+fn test_decode_with_hash() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::hash::Hasher;
+
+    let xxh64 = |data: &[u8]| {
+        let mut hasher = twox_hash::XxHash64::with_seed(0);
+        hasher.write(data);
+        hasher.finish()
+    };
+    let mut decoder = FrameDecoder::new();
+
+    // the corpus frames all have a checksum
+    for name in ["z000003", "z000033", "z000088"] {
+        let frame = std::fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let original = std::fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+        let (output, hash) = decoder.decode_with_hash(&frame).unwrap();
+        assert_eq!(output, original);
+        assert_eq!(hash, xxh64(&original));
+        assert_eq!(decoder.get_checksum_from_data(), Some(hash as u32));
+    }
+
+    // one raw block and no checksum flag
+    let content = b"hashed without a checksum";
+    let mut frame = vec![0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x00];
+    let block_header = ((content.len() as u32) << 3) | 1;
+    frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+    frame.extend_from_slice(content);
+    let (output, hash) = decoder.decode_with_hash(&frame).unwrap();
+    assert_eq!(output, content);
+    assert_eq!(hash, xxh64(content));
+    assert_eq!(decoder.get_checksum_from_data(), None);
+    // forcing the hash is only for this frame
+    decoder.reset(&mut frame.as_slice()).unwrap();
+    assert_eq!(decoder.current_hash(), None);
+
+    let mut frame = std::fs::read("./decodecorpus_files/z000003.zst").unwrap();
+    let last = frame.len() - 1;
+    frame[last] ^= 0xFF;
+    match decoder.decode_with_hash(&frame) {
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::ChecksumMismatch {
+                expected,
+                calculated,
+            },
+        )) => assert_eq!(expected ^ 0xFF00_0000, calculated),
+        other => panic!(
+            "expected a checksum mismatch, got {:?}",
+            other.map(|(_, h)| h)
+        ),
+    }
+}
+
+#[test]
+fn test_blocks_of_maximum_size() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    fn block_header(block_type: u32, size: usize, last: bool) -> [u8; 3] {
+        let header = ((size as u32) << 3) | (block_type << 1) | u32::from(last);
+        [header as u8, (header >> 8) as u8, (header >> 16) as u8]
+    }
+
+    // window descriptors for 64kb, 128kb and 1mb windows in multi-segment frames.
+    // The biggest block is as big as the window, but never bigger than 128kb
+    for (window_descriptor, max_block_size) in [
+        (6u8 << 3, 64 * 1024),
+        (7 << 3, 128 * 1024),
+        (10 << 3, 128 * 1024),
+    ] {
+        let mut frame = test_frame(&[0, window_descriptor], &[]);
+        let mut expected = Vec::new();
+
+        let raw: Vec<u8> = (0..max_block_size).map(|i| (i % 251) as u8).collect();
+        frame.extend_from_slice(&block_header(0, max_block_size, false));
+        frame.extend_from_slice(&raw);
+        expected.extend_from_slice(&raw);
+
+        frame.extend_from_slice(&block_header(1, max_block_size, false));
+        frame.push(b'r');
+        expected.resize(expected.len() + max_block_size, b'r');
+
+        // compressed block with rle literals of the maximum size and no sequences, the literals header has the
+        // 20 bit size format
+        let literals_header = [
+            1 | (3 << 2) | ((max_block_size & 0xF) << 4) as u8,
+            (max_block_size >> 4) as u8,
+            (max_block_size >> 12) as u8,
+        ];
+        frame.extend_from_slice(&block_header(2, 5, false));
+        frame.extend_from_slice(&literals_header);
+        frame.extend_from_slice(&[b'c', 0]);
+        expected.resize(expected.len() + max_block_size, b'c');
+
+        frame.extend_from_slice(&block_header(0, max_block_size, true));
+        frame.extend_from_slice(&raw);
+        expected.extend_from_slice(&raw);
+
+        let (output, _) = FrameDecoder::new().decode_frame(&frame).unwrap();
+        assert_eq!(output.len(), expected.len());
+        assert_eq!(output, expected);
+
+        // one block at a time, so the ringbuffer is drained down to the window between the blocks
+        let mut decoder = FrameDecoder::new();
+        let mut source = frame.as_slice();
+        decoder.reset(&mut source).unwrap();
+        let mut output = Vec::new();
+        while !decoder.is_finished() {
+            decoder
+                .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+                .unwrap();
+            output.extend(decoder.collect().unwrap_or_default());
+        }
+        output.extend(decoder.collect().unwrap_or_default());
+        assert_eq!(output, expected);
+        assert!(source.is_empty());
+    }
+
+    // one byte more is rejected for each block type
+    for block_type in 0..3 {
+        let mut frame = test_frame(&[0, 7 << 3], &[]);
+        frame.extend_from_slice(&block_header(block_type, 128 * 1024 + 1, true));
+        frame.resize(frame.len() + 128 * 1024 + 1, 0);
+        match FrameDecoder::new().decode_frame(&frame) {
+            Err(crate::errors::FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::BlockHeader(m),
+            )) => {
+                assert!(m.contains("absolute maximum"), "{}", m)
+            }
+            other => panic!(
+                "block type {} was not rejected: {:?}",
+                block_type,
+                other.map(|(o, _)| o.len())
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_read_available() {
+    use crate::streaming_decoder::StreamingDecoder;
+
+    // returns 0 once it reached what the test made available so far, like a pipe that has nothing to read right now
+    struct Partial {
+        data: Vec<u8>,
+        pos: usize,
+        available: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+    impl crate::decoding::source::ByteSource for Partial {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+            let amount = usize::min(buf.len(), self.available.get() - self.pos);
+            buf[..amount].copy_from_slice(&self.data[self.pos..][..amount]);
+            self.pos += amount;
+            Ok(amount)
+        }
+    }
+
+    // 1kb window and four raw blocks of 1kb
+    let original: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+    let blocks: Vec<TestBlock<'_>> = original.chunks(1024).map(TestBlock::Raw).collect();
+    let frame = test_frame(&[0, 0], &blocks);
+
+    // the header and two and a half blocks. Two blocks are decoded, the last 1kb of them is the window
+    let available = std::rc::Rc::new(std::cell::Cell::new(6 + 2 * 1027 + 500));
+    let mut decoder = StreamingDecoder::new(Partial {
+        data: frame.clone(),
+        pos: 0,
+        available: available.clone(),
+    })
+    .unwrap();
+    let mut output = Vec::new();
+    assert_eq!(decoder.read_available(&mut output).unwrap(), 1024);
+    assert_eq!(output, &original[..1024]);
+    assert_eq!(decoder.decoder.bytes_decoded(), 2048);
+    // nothing new arrived
+    assert_eq!(decoder.read_available(&mut output).unwrap(), 0);
+
+    // the rest of the third block, the held back bytes of it are used
+    available.set(6 + 3 * 1027);
+    assert_eq!(decoder.read_available(&mut output).unwrap(), 1024);
+    assert_eq!(output, &original[..2048]);
+
+    // read continues with the input that is already there
+    available.set(frame.len() - 10);
+    assert_eq!(decoder.read_available(&mut output).unwrap(), 0);
+    available.set(frame.len());
+    let mut rest = Vec::new();
+    std::io::Read::read_to_end(&mut decoder, &mut rest).unwrap();
+    output.extend(rest);
+    assert_eq!(output, original);
+
+    // a corpus frame that becomes available a few bytes at a time
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = std::fs::read("./decodecorpus_files/z000033").unwrap();
+    available.set(100);
+    let mut decoder = StreamingDecoder::new(Partial {
+        data: frame.clone(),
+        pos: 0,
+        available: available.clone(),
+    })
+    .unwrap();
+    let mut output = Vec::new();
+    while available.get() < frame.len() {
+        decoder.read_available(&mut output).unwrap();
+        assert_eq!(output, &original[..output.len()]);
+        available.set(usize::min(frame.len(), available.get() + 3000));
+    }
+    decoder.read_available(&mut output).unwrap();
+    assert!(decoder.decoder.is_finished());
+    assert_eq!(output, original);
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_read_available_small_chunks() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // hands out one chunk of at most 100 bytes per read, and only after the test allowed it. Reading when nothing
+    // was allowed is where a socket or pipe would block
+    struct Chunked {
+        data: Vec<u8>,
+        pos: usize,
+        allowed_reads: Rc<Cell<usize>>,
+    }
+    impl crate::decoding::source::ByteSource for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+            assert!(
+                self.allowed_reads.get() > 0,
+                "read_available read again after a short read"
+            );
+            self.allowed_reads.set(self.allowed_reads.get() - 1);
+            let amount = usize::min(usize::min(buf.len(), 100), self.data.len() - self.pos);
+            buf[..amount].copy_from_slice(&self.data[self.pos..][..amount]);
+            self.pos += amount;
+            Ok(amount)
+        }
+    }
+
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = std::fs::read("./decodecorpus_files/z000033").unwrap();
+    let allowed_reads = Rc::new(Cell::new(usize::MAX));
+    let mut decoder = StreamingDecoder::new(Chunked {
+        data: frame.clone(),
+        pos: 0,
+        allowed_reads: allowed_reads.clone(),
+    })
+    .unwrap();
+    allowed_reads.set(0);
+
+    let mut output = Vec::new();
+    let mut calls = 0;
+    while !decoder.decoder.is_finished() {
+        allowed_reads.set(1);
+        decoder.read_available(&mut output).unwrap();
+        assert_eq!(allowed_reads.get(), 0);
+        assert_eq!(output, &original[..output.len()]);
+        calls += 1;
+        assert!(calls <= frame.len() / 100 + 1);
+    }
+    decoder.read_available(&mut output).unwrap();
+    assert_eq!(output, original);
+
+    // read_available reads ahead into the next frame, into_parts hands these bytes back
+    let mut frames = frame.clone();
+    frames.extend(std::fs::read("./decodecorpus_files/z000068.zst").unwrap());
+    let mut decoder = StreamingDecoder::new(frames.as_slice()).unwrap();
+    let mut output = Vec::new();
+    decoder.read_available(&mut output).unwrap();
+    assert_eq!(output, original);
+    let (frame_decoder, source, read_ahead) = decoder.into_parts();
+    assert!(!read_ahead.is_empty());
+    let mut rest = read_ahead;
+    rest.extend_from_slice(source);
+    assert_eq!(rest, &frames[frame.len()..]);
+    let mut decoder = StreamingDecoder::new_with_decoder(rest.as_slice(), frame_decoder).unwrap();
+    let mut output = Vec::new();
+    decoder.read_available(&mut output).unwrap();
+    assert_eq!(
+        output,
+        std::fs::read("./decodecorpus_files/z000068").unwrap()
+    );
+}
+
+#[test]
+fn test_use_content_hasher() {
+    use crate::decoding::content_hasher::ContentHasher;
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+
+    #[derive(Default)]
+    struct CountingHasher {
+        bytes: u64,
+    }
+    impl ContentHasher for CountingHasher {
+        fn update(&mut self, data: &[u8]) {
+            self.bytes += data.len() as u64;
+        }
+        fn finish(&self) -> u64 {
+            self.bytes
+        }
+    }
+
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = std::fs::read("./decodecorpus_files/z000033").unwrap();
+
+    let mut decoder = FrameDecoder::new();
+    decoder.use_content_hasher::<CountingHasher>();
+    // twice to see that every frame gets a new hasher
+    for _ in 0..2 {
+        let mut source = frame.as_slice();
+        decoder.reset(&mut source).unwrap();
+        decoder
+            .decode_blocks(&mut source, BlockDecodingStrategy::All)
+            .unwrap();
+        // the bytes that are still in the decoder can not be hashed on a copy of a custom hasher
+        assert_eq!(decoder.current_hash(), None);
+        assert_eq!(decoder.collect().unwrap(), original);
+        assert_eq!(decoder.current_hash(), Some(original.len() as u64));
+        assert_eq!(decoder.computed_checksum(), Some(original.len() as u32));
+    }
+
+    // another implementation of XXH64 matches the checksums
+    #[derive(Default)]
+    struct OtherXxh64(twox_hash::XxHash64);
+    impl ContentHasher for OtherXxh64 {
+        fn update(&mut self, data: &[u8]) {
+            std::hash::Hasher::write(&mut self.0, data);
+        }
+        fn finish(&self) -> u64 {
+            std::hash::Hasher::finish(&self.0)
+        }
+    }
+    let (_, builtin) = FrameDecoder::new().decode_with_hash(&frame).unwrap();
+    let mut decoder = FrameDecoder::new();
+    decoder.use_content_hasher::<OtherXxh64>();
+    let (output, hash) = decoder.decode_with_hash(&frame).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(hash, builtin);
+}
+
+/// A frame of about 5mb: the blocks of z000033 five times in a row without its content size and checksum.
+/// The output of the repetitions differs because their matches reach back into the one before
+#[cfg(test)]
+fn five_times_z000033() -> Vec<u8> {
+    let content = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let (_, header_size) = crate::frame::read_frame_header(content.as_slice()).unwrap();
+    // multi-segment, so the window descriptor directly follows the frame descriptor
+    let window_descriptor = content[5];
+    let mut blocks = Vec::new();
+    let mut rest = &content[header_size as usize..];
+    loop {
+        let header = u32::from_le_bytes([rest[0], rest[1], rest[2], 0]);
+        let block_size = match (header >> 1) & 3 {
+            1 => 1,
+            _ => (header >> 3) as usize,
+        };
+        let mut block = rest[..3 + block_size].to_vec();
+        block[0] &= !1;
+        blocks.push(block);
+        rest = &rest[3 + block_size..];
+        if header & 1 == 1 {
+            break;
+        }
+    }
+    let mut big_frame = test_frame(&[0, window_descriptor], &[]);
+    for _ in 0..5 {
+        for block in &blocks {
+            big_frame.extend_from_slice(block);
+        }
+    }
+    let last_block_start = big_frame.len() - blocks.last().unwrap().len();
+    big_frame[last_block_start] |= 1;
+    big_frame
+}
+
+#[test]
+fn test_decode_range() {
+    use crate::frame_decoder::FrameDecoder;
+
+    let big_frame = five_times_z000033();
+    let mut decoder = FrameDecoder::new();
+    let (full, _) = decoder.decode_frame(&big_frame).unwrap();
+    assert!(full.len() > 5_000_000);
+
+    const MB: u64 = 1024 * 1024;
+    let range = decoder.decode_range(&big_frame, MB, 100).unwrap();
+    assert_eq!(range, &full[MB as usize..][..100]);
+
+    // the start, the end of the frame, a range that ends after it and an empty one
+    let end = full.len() as u64;
+    for (start, len) in [(0, 1000), (end - 100, 100), (end - 10, 100), (3 * MB, 0)] {
+        let range = decoder.decode_range(&big_frame, start, len).unwrap();
+        let expected = &full[start as usize..usize::min(full.len(), start as usize + len)];
+        assert_eq!(range, expected, "{} {}", start, len);
+    }
+    assert!(decoder
+        .decode_range(&big_frame, end + 5, 10)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_last_block_flag() {
+    use crate::errors::{EofContext, FrameDecoderError};
+    use crate::frame_decoder::FrameDecoder;
+
+    // where the header of the last block of a corpus frame starts
+    fn last_block_start(frame: &[u8]) -> usize {
+        let (_, header_size) = crate::frame::read_frame_header(frame).unwrap();
+        let mut pos = header_size as usize;
+        loop {
+            let header = u32::from_le_bytes([frame[pos], frame[pos + 1], frame[pos + 2], 0]);
+            if header & 1 == 1 {
+                return pos;
+            }
+            pos += 3 + match (header >> 1) & 3 {
+                1 => 1,
+                _ => (header >> 3) as usize,
+            };
+        }
+    }
+
+    // without the flag the checksum is read as the next block header, which is invalid here
+    let content = std::fs::read("./decodecorpus_files/z000003.zst").unwrap();
+    let mut no_last_block = content.clone();
+    no_last_block[last_block_start(&content)] &= !1;
+    match FrameDecoder::new().decode_frame(&no_last_block) {
+        Err(FrameDecoderError::InvalidData(crate::errors::InvalidDataError::BlockHeader(m))) => {
+            assert!(m.contains("not marked as the last block"), "{}", m)
+        }
+        other => panic!(
+            "missing last block flag was not noticed: {:?}",
+            other.map(|(o, _)| o.len())
+        ),
+    }
+
+    // a frame without checksum that ends without the flag
+    let mut frame = test_frame(&[0, 0], &[]);
+    frame.extend_from_slice(&[5 << 3, 0, 0]);
+    frame.extend_from_slice(b"hello");
+    assert!(matches!(
+        FrameDecoder::new().decode_frame(&frame),
+        Err(FrameDecoderError::UnexpectedEof {
+            context: EofContext::BlockHeader,
+            bytes_missing: 3
+        })
+    ));
+
+    // the decoder stops after the last block, a block after it is not part of the frame
+    let original = std::fs::read("./decodecorpus_files/z000003").unwrap();
+    let mut block_after_last = content.clone();
+    block_after_last.extend_from_slice(&[(1 << 3) | 1, 0, 0, b'x']);
+    let (output, remaining) = FrameDecoder::new().decode_frame(&block_after_last).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(remaining, 4);
+    // as the start of the next frame it is not valid
+    assert!(FrameDecoder::new()
+        .decode_frame(&block_after_last[content.len()..])
+        .is_err());
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn test_profile() {
+    use crate::blocks::block::BlockType;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut frame_dec = FrameDecoder::new();
+    assert!(frame_dec.profile().is_none());
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let (output, _) = frame_dec.decode_frame(&content).unwrap();
+    let blocks = crate::frame::inspect_blocks(&content).unwrap();
+    let compressed = blocks
+        .iter()
+        .filter(|block| block.block_type == BlockType::Compressed);
+    let literals: u64 = compressed
+        .clone()
+        .map(|block| u64::from(block.literals_length))
+        .sum();
+    let sequences: u64 = compressed.map(|block| block.sequences.len() as u64).sum();
+
+    let profile = *frame_dec.profile().unwrap();
+    assert_eq!(profile.total_bytes(), output.len() as u64);
+    assert_eq!(profile.literals_bytes, literals);
+    assert_eq!(profile.sequences, sequences);
+    assert!(profile.sequences > 0);
+    assert!(profile.literals_bytes <= profile.execution_bytes);
+    assert!(profile.total_time() > std::time::Duration::ZERO);
+    assert!(profile.total_time() >= profile.execution_time);
+
+    // raw and rle blocks are counted on their own
+    let content = fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    let (output, _) = frame_dec.decode_frame(&content).unwrap();
+    let profile = frame_dec.profile().unwrap();
+    assert_eq!(profile.total_bytes(), output.len() as u64);
+    assert_eq!(profile.sequences, 0);
+
+    frame_dec.reset(&content[..]).unwrap();
+    assert_eq!(
+        *frame_dec.profile().unwrap(),
+        crate::decoding::profile::DecodeProfile::default()
+    );
+}
+
+#[test]
+fn test_long_window() {
+    use crate::errors::{FrameDecoderError, InvalidDataError};
+    use crate::frame_decoder::FrameDecoder;
+
+    // long27.zst was made with `zstd --long=27 -c < input`, reading from stdin keeps the 128MiB window in the header.
+    // The input is 64KiB of pseudo random bytes, 8MiB of zeros and the same 64KiB again, so the second copy can only be
+    // found as a match reaching back over 8MiB
+    let mut random = Vec::with_capacity(64 * 1024);
+    let mut x: u64 = 1;
+    for _ in 0..64 * 1024 {
+        x = x
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        random.push((x >> 56) as u8);
+    }
+    let mut expected = random.clone();
+    expected.resize(expected.len() + 8 * 1024 * 1024, 0);
+    expected.extend_from_slice(&random);
+
+    let frame = std::fs::read("./long_window_files/long27.zst").unwrap();
+    let mut frame_dec = FrameDecoder::new();
+    match frame_dec.decode_frame(&frame) {
+        Err(FrameDecoderError::InvalidData(InvalidDataError::Initialize(msg))) => {
+            assert!(msg.contains("134217728"), "{}", msg)
+        }
+        other => panic!(
+            "{:?}",
+            other.map(|(output, trailing)| (output.len(), trailing))
+        ),
+    }
+
+    frame_dec.set_max_window_size(128 * 1024 * 1024);
+    let (output, trailing) = frame_dec.decode_frame(&frame).unwrap();
+    assert_eq!(trailing, 0);
+    assert_eq!(frame_dec.window_size(), Some(128 * 1024 * 1024));
+    assert!(output == expected);
+    assert!(frame_dec.max_offset_used() > 8 * 1024 * 1024);
+    // the random half can not be compressed, so the frame is only as small as this because of the long match
+    assert!(frame.len() < 70 * 1024, "{}", frame.len());
+}
+
+#[test]
+fn test_max_window_size_ceiling() {
+    use crate::errors::{FrameDecoderError, InvalidDataError};
+    use crate::frame_decoder::FrameDecoder;
+
+    // a single segment frame with the biggest content size, which is its window
+    let frame = test_frame(
+        &[&[3 << 6 | 1 << 5][..], &u64::MAX.to_le_bytes()].concat(),
+        &[TestBlock::Raw(&[])],
+    );
+
+    // even without a limit the window has to be possible to allocate, so this fails before allocating anything
+    let mut frame_dec = FrameDecoder::new();
+    frame_dec.set_max_window_size(u64::MAX);
+    match frame_dec.reset(frame.as_slice()) {
+        Err(FrameDecoderError::InvalidData(InvalidDataError::Initialize(msg))) => {
+            assert!(msg.contains(&isize::MAX.to_string()), "{}", msg)
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_source_returns_zero() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::fs;
+    use std::io::Read;
+
+    // returns Ok(0) once at zero_at and continues with the rest of the data after that, like some pipes and sockets do
+    struct Hiccup {
+        data: Vec<u8>,
+        pos: usize,
+        zero_at: Option<usize>,
+    }
+    impl crate::decoding::source::ByteSource for Hiccup {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+            let mut end = usize::min(self.data.len(), self.pos + usize::min(buf.len(), 100));
+            if let Some(zero_at) = self.zero_at {
+                if self.pos == zero_at {
+                    self.zero_at = None;
+                    return Ok(0);
+                }
+                if self.pos < zero_at {
+                    end = usize::min(end, zero_at);
+                }
+            }
+            let amount = end - self.pos;
+            buf[..amount].copy_from_slice(&self.data[self.pos..end]);
+            self.pos = end;
+            Ok(amount)
+        }
+    }
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    for zero_at in [100, content.len() / 2, content.len() - 4, content.len() - 1] {
+        let mut decoder = StreamingDecoder::new(Hiccup {
+            data: content.clone(),
+            pos: 0,
+            zero_at: Some(zero_at),
+        })
+        .unwrap();
+        let mut output = Vec::new();
+        let err = decoder.read_to_end(&mut output).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof, "{}", err);
+        assert!(output.len() < original.len());
+        assert_eq!(&output[..], &original[..output.len()]);
+
+        // the frame is broken now, reading on must not look like it ended
+        let mut buf = [0; 100];
+        let err = decoder.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof, "{}", err);
+    }
+
+    // a source that is complete decodes as usual
+    let mut decoder = StreamingDecoder::new(Hiccup {
+        data: content.clone(),
+        pos: 0,
+        zero_at: None,
+    })
+    .unwrap();
+    let mut output = Vec::new();
+    decoder.read_to_end(&mut output).unwrap();
+    assert_eq!(output, original);
+}
+
+#[test]
+fn test_decode_to_writer() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // counts the writes to see that the small blocks get coalesced
+    struct CountingWriter {
+        data: Vec<u8>,
+        writes: usize,
+    }
+    impl std::io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // about 5mb in blocks of at most 128kb
+    let big_frame = five_times_z000033();
+    let mut frame_dec = FrameDecoder::new();
+    let (expected, _) = frame_dec.decode_frame(&big_frame).unwrap();
+    let mut writer = CountingWriter {
+        data: Vec::new(),
+        writes: 0,
+    };
+    let consumed = frame_dec.decode_to_writer(&big_frame, &mut writer).unwrap();
+    assert_eq!(consumed, big_frame.len());
+    assert_eq!(frame_dec.bytes_decoded(), expected.len() as u64);
+    assert!(writer.data == expected);
+    assert!(writer.writes <= 6, "{}", writer.writes);
+
+    // the checksum is checked, and only the frame at the start of input is decoded
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    let mut out = Vec::new();
+    assert_eq!(
+        frame_dec.decode_to_writer(&content, &mut out).unwrap(),
+        content.len()
+    );
+    assert!(out == original);
+    assert_eq!(
+        frame_dec.get_checksum_from_data(),
+        frame_dec.get_calculated_checksum()
+    );
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let mut input = first.clone();
+    input.extend_from_slice(&content);
+    let mut out = Vec::new();
+    let consumed = frame_dec.decode_to_writer(&input, &mut out).unwrap();
+    assert_eq!(consumed, first.len());
+    assert_eq!(out, fs::read("./decodecorpus_files/z000068").unwrap());
+
+    // a writer that fails
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(matches!(
+        frame_dec.decode_to_writer(&content, Full),
+        Err(crate::errors::FrameDecoderError::FailedToDrainDecodebuffer)
+    ));
+}
+
+#[test]
+fn test_collect_to_writer_partial_writes() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    // takes one byte per call and interrupts every other call
+    struct Trickle {
+        data: Vec<u8>,
+        calls: usize,
+    }
+    impl std::io::Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.calls += 1;
+            if self.calls % 2 == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "interrupted",
+                ));
+            }
+            self.data.push(buf[0]);
+            Ok(1)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    let mut frame_dec = FrameDecoder::new();
+    let mut source = content.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap();
+    let mut writer = Trickle {
+        data: Vec::new(),
+        calls: 0,
+    };
+    assert_eq!(
+        frame_dec.collect_to_writer(&mut writer).unwrap(),
+        original.len()
+    );
+    assert!(writer.data == original);
+
+    let mut writer = Trickle {
+        data: Vec::new(),
+        calls: 0,
+    };
+    frame_dec.decode_to_writer(&content, &mut writer).unwrap();
+    assert!(writer.data == original);
+
+    // a writer that accepts nothing must end in an error instead of a loop
+    struct Zero;
+    impl std::io::Write for Zero {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Ok(0)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut source = content.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    frame_dec
+        .decode_blocks(&mut source, BlockDecodingStrategy::All)
+        .unwrap();
+    assert_eq!(
+        frame_dec.collect_to_writer(Zero).unwrap_err().kind(),
+        std::io::ErrorKind::WriteZero
+    );
+    assert!(matches!(
+        frame_dec.decode_to_writer(&content, Zero),
+        Err(crate::errors::FrameDecoderError::FailedToDrainDecodebuffer)
+    ));
+}
+
+#[test]
+fn test_decode_best_effort() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    let mut frame_dec = FrameDecoder::new();
+    let (output, err) = frame_dec.decode_best_effort(&content);
+    assert!(err.is_none());
+    assert!(output == original);
+
+    // where the blocks start in the frame and how much output came before each of them
+    let (_, header_size) = crate::frame::read_frame_header(content.as_slice()).unwrap();
+    let mut block_starts = Vec::new();
+    let mut pos = header_size as usize;
+    let mut decoded = 0;
+    for block in crate::frame::inspect_blocks(&content).unwrap() {
+        block_starts.push((pos, decoded));
+        pos += 3 + block.content_size as usize;
+        decoded += block.regenerated_size as usize;
+    }
+    assert!(block_starts.len() > 10);
+
+    // a reserved block type in the middle of the frame, everything before that block is recovered
+    let (block_start, decoded_before) = block_starts[block_starts.len() / 2];
+    let mut corrupt = content.clone();
+    corrupt[block_start] |= 3 << 1;
+    let (output, err) = frame_dec.decode_best_effort(&corrupt);
+    assert!(
+        matches!(
+            err,
+            Some(FrameDecoderError::InvalidData(
+                crate::errors::InvalidDataError::BlockHeader(_)
+            ))
+        ),
+        "{:?}",
+        err
+    );
+    assert_eq!(output.len(), decoded_before);
+    assert!(output == original[..decoded_before]);
+
+    // a flipped byte inside of a block. It may only be noticed a few blocks later or by the checksum,
+    // but all returned bytes from before that block are right
+    let (block_start, decoded_before) = block_starts[block_starts.len() / 3];
+    let mut corrupt = content.clone();
+    corrupt[block_start + 10] ^= 0x55;
+    let (output, err) = frame_dec.decode_best_effort(&corrupt);
+    assert!(err.is_some());
+    assert!(output.len() >= decoded_before);
+    assert!(output[..decoded_before] == original[..decoded_before]);
+
+    // the frame is cut off in the middle of a block
+    let (block_start, decoded_before) = block_starts[2];
+    let (output, err) = frame_dec.decode_best_effort(&content[..block_start + 5]);
+    assert!(
+        matches!(err, Some(FrameDecoderError::UnexpectedEof { .. })),
+        "{:?}",
+        err
+    );
+    assert!(output == original[..decoded_before]);
+
+    // nothing at all can be decoded without a frame header
+    let (output, err) = frame_dec.decode_best_effort(&content[..3]);
+    assert!(output.is_empty() && err.is_some());
+}
+
+#[test]
+fn test_fse_table_index_extremes() {
+    use crate::decoding::bit_reader_reverse::BitReaderReversed;
+    use crate::fse::{FSEDecoder, FSETable};
+
+    // all ones in the bitstream make every state update go to the highest state its entry allows
+    let ones = [0xFFu8; 64];
+    let acc_log = 9;
+    let mut distributions: Vec<Vec<i32>> = vec![
+        vec![512],
+        vec![-1, 511],
+        vec![0, 0, 0, 511, -1],
+        vec![2; 256],
+    ];
+    let mut many_small = vec![-1; 255];
+    many_small.push(512 - 255);
+    distributions.push(many_small);
+
+    let mut table = FSETable::new();
+    for probs in &distributions {
+        table.build_from_probabilities(acc_log, probs).unwrap();
+        assert_eq!(table.decode.len(), 1 << acc_log);
+        for state in 0..table.decode.len() {
+            let entry = table.decode[state];
+            assert!(entry.base_line + (1 << entry.num_bits) - 1 < table.decode.len());
+
+            let mut br = BitReaderReversed::new(&ones);
+            let mut decoder = FSEDecoder::new(&table);
+            decoder.state = state;
+            decoder.update_state(&mut br).unwrap();
+            assert!(decoder.state < table.decode.len());
+        }
+    }
+
+    // a failed build must not keep the old entries around with the accuracy log of the new header
+    table.build_from_probabilities(6, &[64]).unwrap();
+    assert!(table.build_decoder(&[0x0F, 0, 0, 0], 9).is_err());
+    assert_eq!(table.accuracy_log, 0);
+    let mut br = BitReaderReversed::new(&ones);
+    assert!(FSEDecoder::new(&table).init_state(&mut br).is_err());
+}
+
+#[test]
+fn test_huffman_table_index_extremes() {
+    use crate::decoding::bit_reader_reverse::BitReaderReversed;
+    use crate::huff0::{HuffmanDecoder, HuffmanTable};
+
+    let ones = [0xFFu8; 64];
+    let mut table = HuffmanTable::new();
+    // 11 direct weights 11, 10, .., 1 and an implied last weight of 1 make codes of every length up to the maximum of 11 bits
+    table
+        .build_decoder(&[127 + 11, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10])
+        .unwrap();
+    assert_eq!(table.max_num_bits, 11);
+
+    for state in 0..1u64 << table.max_num_bits {
+        let mut br = BitReaderReversed::new(&ones);
+        let mut decoder = HuffmanDecoder::new(&table);
+        decoder.state = state;
+        decoder.decode_symbol();
+        decoder.next_state(&mut br).unwrap();
+        assert!(decoder.state < 1 << table.max_num_bits);
+        decoder.decode_symbol();
+    }
+
+    // twelve weights of 11 need 14 bits, a failed build has to leave the table marked as uninitialized
+    assert!(table
+        .build_decoder(&[127 + 12, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB])
+        .is_err());
+    assert_eq!(table.max_num_bits, 0);
+}
+
+#[test]
+fn test_inspect_blocks_match_lengths_overflow() {
+    use crate::errors::{FrameDecoderError, InvalidDataError};
+
+    // 40000 sequences in rle mode with match length code 52 and all 16 extra bits set are matches of 131074 bytes
+    // each, which add up to more than u32::MAX
+    let num_sequences: u32 = 40_000;
+    let mut content = vec![0];
+    content.push(255);
+    content.extend_from_slice(&((num_sequences - 0x7F00) as u16).to_le_bytes());
+    content.extend_from_slice(&[0x54, 0, 0, 52]);
+    // the sentinel bit in the last byte, after the 16 bits of every sequence
+    content.resize(content.len() + num_sequences as usize * 2, 0xFF);
+    content.push(1);
+
+    let frame = test_frame(&[0, 7 << 3], &[TestBlock::Compressed(&content)]);
+
+    match crate::frame::inspect_blocks(&frame) {
+        Err(FrameDecoderError::InvalidData(InvalidDataError::BlockBody(m))) => {
+            assert!(m.contains("maximum block size"), "{}", m)
+        }
+        Err(e) => panic!("Expected an invalid block body, got: {}", e),
+        Ok(_) => panic!("Inspecting a block with overflowing match lengths did not fail"),
+    }
+}
+
+#[test]
+fn test_max_offset_used() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    // a compressed block with 10 raw literals and one sequence with rle codes: literal length 10,
+    // offset code 3 with the extra bits 2 (offset value 10, so an offset of 7) and match length 4
+    let mut content = vec![10 << 3];
+    content.extend_from_slice(b"abcdefghij");
+    content.extend_from_slice(&[1, 0x54, 10, 3, 1, 0b1010]);
+    let frame = test_frame(&[0, 0], &[TestBlock::Compressed(&content)]);
+
+    let mut frame_dec = FrameDecoder::new();
+    assert_eq!(frame_dec.max_offset_used(), 0);
+    let (output, _) = frame_dec.decode_frame(&frame).unwrap();
+    assert_eq!(output, b"abcdefghijdefg");
+    assert_eq!(frame_dec.max_offset_used(), 7);
+
+    // a real frame only reaches back a fraction of its 3.25MiB window
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    frame_dec.decode_frame(&content).unwrap();
+    let max_offset = frame_dec.max_offset_used();
+    assert!(max_offset > 0 && max_offset <= 1126, "{}", max_offset);
+
+    // z000019 has no sequences
+    let content = fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    frame_dec.decode_frame(&content).unwrap();
+    assert_eq!(frame_dec.max_offset_used(), 0);
+}
+
+#[test]
+fn test_entropy_stages_in_isolation() {
+    use crate::decoding::literals_section_decoder::decode_literals_section;
+    use crate::decoding::scratch::{FSEScratch, HuffmanScratch};
+    use crate::decoding::sequence_section_decoder::decode_sequences_section;
+    use std::fs;
+
+    // the first block of z000068 starts after the 6 byte frame header and its 3 byte block header
+    let content = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000068").unwrap();
+    let block = &content[9..9 + 491];
+
+    let mut huf = HuffmanScratch::new();
+    let (literals, literals_section_size) = decode_literals_section(block, &mut huf).unwrap();
+    let mut fse = FSEScratch::new();
+    let sequences = decode_sequences_section(&block[literals_section_size..], &mut fse).unwrap();
+
+    // the same as the block decoder finds
+    let inspection = &crate::frame::inspect_blocks(&content).unwrap()[0];
+    assert_eq!(literals.len() as u32, inspection.literals_length);
+    assert_eq!(sequences, inspection.sequences);
+
+    // the output starts with the literals of the first sequence
+    let first_literals = sequences[0].ll as usize;
+    assert_eq!(literals[..first_literals], original[..first_literals]);
+
+    // a section cut off in the middle is an error, not a panic
+    assert!(decode_literals_section(&block[..literals_section_size / 2], &mut huf).is_err());
+    assert!(decode_sequences_section(&block[literals_section_size..][..10], &mut fse).is_err());
+}
+
+#[test]
+fn test_skip_frame() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let first = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let second = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let mut input = first.clone();
+    let skippable = skippable_frame(0, b"abc");
+    input.extend_from_slice(&skippable);
+    input.extend_from_slice(&second);
+
+    let mut frame_dec = FrameDecoder::new();
+    let skipped = frame_dec.skip_frame(&input).unwrap();
+    assert_eq!(skipped, first.len());
+    // everything was decoded, but nothing of it is left to collect
+    assert!(frame_dec.is_finished());
+    assert_eq!(frame_dec.can_collect(), 0);
+    assert_eq!(
+        frame_dec.bytes_decoded(),
+        fs::read("./decodecorpus_files/z000088").unwrap().len() as u64
+    );
+
+    let rest = &input[skipped..];
+    assert_eq!(frame_dec.skip_frame(rest).unwrap(), skippable.len());
+    let (output, trailing) = frame_dec.decode_frame(&rest[skippable.len()..]).unwrap();
+    assert!(output == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert_eq!(trailing, 0);
+
+    // skipping still decodes, so broken frames are noticed
+    let mut corrupt = second.clone();
+    corrupt[499] = 0;
+    assert!(frame_dec.skip_frame(&corrupt).is_err());
+    assert!(frame_dec.skip_frame(&second[..300]).is_err());
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_header_accessors() {
+    use crate::frame_decoder::FrameDecoder;
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::fs;
+    use std::io::Read;
+
+    let frame_dec = FrameDecoder::new();
+    assert_eq!(frame_dec.window_size(), None);
+    assert_eq!(frame_dec.dictionary_id(), None);
+
+    // z000033 is a multi-segment frame whose header declares the content size
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000033").unwrap();
+    let (frame, _) = crate::frame::read_frame_header(content.as_slice()).unwrap();
+    assert!(!frame.header.descriptor.single_segment_flag());
+    let mut decoder = StreamingDecoder::new(content.as_slice()).unwrap();
+    let window_size = frame.header.window_size().unwrap();
+    assert_eq!(decoder.window_size(), Some(window_size));
+    assert_eq!(decoder.content_size(), Some(original.len() as u64));
+    assert_eq!(decoder.dictionary_id(), None);
+    // the values stay the same while decoding
+    let mut output = Vec::new();
+    decoder.read_to_end(&mut output).unwrap();
+    assert_eq!(output.len(), original.len());
+    assert_eq!(decoder.window_size(), Some(window_size));
+
+    // a single segment frame uses its content size as window
+    let frame = test_frame(&[0x20, 3], &[TestBlock::Raw(b"abc")]);
+    let decoder = StreamingDecoder::new(frame.as_slice()).unwrap();
+    assert_eq!(decoder.window_size(), Some(3));
+    assert_eq!(decoder.content_size(), Some(3));
+
+    // the id of the dictionary from its header, the frame can not be decoded without it but its header can be read
+    #[cfg(feature = "dictionary")]
+    {
+        let dict = fs::read("./dict_tests/dictionary").unwrap();
+        let dict_id = u32::from_le_bytes([dict[4], dict[5], dict[6], dict[7]]);
+        let content = fs::read("./dict_tests/files/ModemManager.service.zst").unwrap();
+        let mut frame_dec = FrameDecoder::new();
+        frame_dec.add_dict(&dict).unwrap();
+        let decoder = StreamingDecoder::new_with_decoder(content.as_slice(), frame_dec).unwrap();
+        assert_eq!(decoder.dictionary_id(), Some(dict_id));
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_streaming_peek() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::fs;
+    use std::io::Read;
+
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let original = fs::read("./decodecorpus_files/z000088").unwrap();
+    let mut stream = StreamingDecoder::new(content.as_slice()).unwrap();
+
+    assert_eq!(stream.peek(10).unwrap(), &original[..10]);
+    // peeking again does not consume anything
+    assert_eq!(stream.peek(100).unwrap(), &original[..100]);
+
+    let mut buf = vec![0; 50];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, &original[..50]);
+
+    // the rest of the peeked bytes and then new bytes
+    assert_eq!(stream.peek(1000).unwrap(), &original[50..1050]);
+    let mut buf = vec![0; 2000];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, &original[50..2050]);
+
+    // at the end of the frame less bytes are returned
+    let rest = original.len() - 2050;
+    assert_eq!(stream.peek(rest + 100).unwrap(), &original[2050..]);
+    let mut result = Vec::new();
+    stream.read_to_end(&mut result).unwrap();
+    assert!(result == original[2050..]);
+    assert!(stream.peek(10).unwrap().is_empty());
+
+    // chunks start with the peeked bytes
+    let mut stream = StreamingDecoder::new(content.as_slice()).unwrap();
+    assert_eq!(stream.peek(10).unwrap(), &original[..10]);
+    let mut result = Vec::new();
+    for chunk in stream.chunks() {
+        result.extend(chunk.unwrap());
+    }
+    assert!(result == original);
+}
+
+#[test]
+fn test_decoding_progress() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let original_len = fs::read("./decodecorpus_files/z000033").unwrap().len() as u64;
+    let num_blocks = crate::frame::inspect_blocks(&content).unwrap().len() as u64;
+    assert!(num_blocks > 2);
+
+    let mut frame_dec = FrameDecoder::new();
+    let mut source = content.as_slice();
+    frame_dec.reset(&mut source).unwrap();
+    assert_eq!(
+        (frame_dec.blocks_decoded(), frame_dec.bytes_decoded()),
+        (0, 0)
+    );
+
+    let mut last_blocks = 0;
+    let mut last_bytes = 0;
+    while !frame_dec.is_finished() {
+        frame_dec
+            .decode_blocks(&mut source, BlockDecodingStrategy::UptoBlocks(1))
+            .unwrap();
+        assert_eq!(frame_dec.blocks_decoded(), last_blocks + 1);
+        assert!(frame_dec.bytes_decoded() >= last_bytes);
+        last_blocks = frame_dec.blocks_decoded();
+        last_bytes = frame_dec.bytes_decoded();
+        // collecting does not change the counters
+        frame_dec.collect();
+        assert_eq!(frame_dec.bytes_decoded(), last_bytes);
+    }
+    assert_eq!(frame_dec.blocks_decoded(), num_blocks);
+    assert_eq!(frame_dec.bytes_decoded(), original_len);
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn test_decode_frames_parallel() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let mut input = Vec::new();
+    let mut expected = Vec::new();
+    for name in &[
+        "z000000", "z000033", "z000043", "z000068", "z000088", "z000019", "z000062", "z000001",
+    ] {
+        input.extend(fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap());
+        expected.extend(fs::read(format!("./decodecorpus_files/{}", name)).unwrap());
+        if *name == "z000043" {
+            // skippable frames do not contribute to the output
+            input.extend(skippable_frame(0, &[1, 2, 3]));
+        }
+    }
+
+    let parallel = crate::parallel::decode_frames_parallel(&input).unwrap();
+    assert!(parallel == expected);
+
+    // decode the same frames one after another
+    let mut sequential = Vec::new();
+    let mut frame_dec = FrameDecoder::new();
+    for range in crate::frame::scan_frames(&input).unwrap() {
+        frame_dec
+            .decode_append(&input[range], &mut sequential)
+            .unwrap();
+    }
+    assert!(parallel == sequential);
+
+    // a truncated last frame is reported before anything gets decoded
+    assert!(crate::parallel::decode_frames_parallel(&input[..input.len() - 1]).is_err());
+}
+
+#[test]
+fn test_frames_with_skippable() {
+    use crate::frame::{self, FrameOrSkippable, SkippableFrame};
+    use std::fs;
+
+    // an index frame written by some tool, between two data frames
+    let mut input = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    input.extend(skippable_frame(7, b"index: 0..589"));
+    input.extend(fs::read("./decodecorpus_files/z000019.zst").unwrap());
+    input.extend(skippable_frame(0, b""));
+
+    let frames: Vec<_> = frame::frames_with_skippable(&input)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        frames,
+        vec![
+            FrameOrSkippable::Frame(0..589),
+            FrameOrSkippable::Skippable(SkippableFrame {
+                magic: 0x184D_2A57,
+                data: b"index: 0..589".to_vec(),
+            }),
+            FrameOrSkippable::Frame(610..624),
+            FrameOrSkippable::Skippable(SkippableFrame {
+                magic: 0x184D_2A50,
+                data: vec![],
+            }),
+        ]
+    );
+    match &frames[1] {
+        FrameOrSkippable::Skippable(frame) => assert_eq!(frame.user_tag(), 7),
+        _ => unreachable!(),
+    }
+
+    // a truncated skippable frame ends the iteration with an error
+    let mut truncated = frame::frames_with_skippable(&input[..600]);
+    assert!(matches!(
+        truncated.next(),
+        Some(Ok(FrameOrSkippable::Frame(_)))
+    ));
+    assert!(matches!(
+        truncated.next(),
+        Some(Err(crate::errors::FrameHeaderError::UnexpectedEof {
+            bytes_missing: 10
+        }))
+    ));
+    assert!(truncated.next().is_none());
+}
+
+#[test]
+fn test_scan_frames() {
+    use crate::errors::FrameHeaderError;
+    use crate::frame;
+    use std::fs;
+
+    // z000068 and z000088 have multiple blocks and a checksum, z000019 is a tiny frame
+    let mut input = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    assert_eq!(input.len(), 589);
+    input.extend(skippable_frame(15, b"hello"));
+    input.extend(fs::read("./decodecorpus_files/z000019.zst").unwrap());
+    input.extend(skippable_frame(15, b""));
+    input.extend(fs::read("./decodecorpus_files/z000088.zst").unwrap());
+    assert_eq!(
+        frame::scan_frames(&input).unwrap(),
+        vec![0..589, 602..616, 624..624 + 101_615]
+    );
+
+    // every range can be decoded on its own
+    let mut frame_dec = crate::frame_decoder::FrameDecoder::new();
+    for (range, name) in frame::scan_frames(&input)
+        .unwrap()
+        .into_iter()
+        .zip(&["z000068", "z000019", "z000088"])
+    {
+        let (output, trailing) = frame_dec.decode_frame(&input[range]).unwrap();
+        assert_eq!(trailing, 0);
+        assert!(output == fs::read(format!("./decodecorpus_files/{}", name)).unwrap());
+    }
+
+    assert_eq!(frame::scan_frames(&[]).unwrap(), vec![]);
+    assert_eq!(
+        frame::scan_frames(&skippable_frame(15, b"abc")).unwrap(),
+        vec![]
+    );
+
+    // cut in the checksum of the last frame
+    assert!(matches!(
+        frame::scan_frames(&input[..input.len() - 1]),
+        Err(FrameHeaderError::UnexpectedEof { bytes_missing: 1 })
+    ));
+    // cut in the payload of a skippable frame
+    assert!(matches!(
+        frame::scan_frames(&input[..599]),
+        Err(FrameHeaderError::UnexpectedEof { bytes_missing: 3 })
+    ));
+    // garbage after the last frame
+    input.extend_from_slice(&[0, 1, 2, 3, 4, 5]);
+    assert!(matches!(
+        frame::scan_frames(&input),
+        Err(FrameHeaderError::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_decode_frames_skip_corrupt() {
+    use crate::frame_decoder::FrameDecoder;
+    use std::fs;
+
+    let first = fs::read("./decodecorpus_files/z000068.zst").unwrap();
+    let mut second = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    let third = fs::read("./decodecorpus_files/z000019.zst").unwrap();
+    // make the first block of the middle frame a reserved block
+    second[6] |= 0x6;
+
+    let mut input = first.clone();
+    input.extend_from_slice(&second);
+    input.extend(skippable_frame(0, &[0xAA, 0xBB]));
+    input.extend_from_slice(&third);
+
+    let mut frame_dec = FrameDecoder::new();
+    let results = frame_dec.decode_frames_skip_corrupt(&input);
+    assert_eq!(results.len(), 3);
+    assert!(*results[0].as_ref().unwrap() == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert!(matches!(
+        results[1],
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::BlockHeader(_)
+        ))
+    ));
+    assert!(*results[2].as_ref().unwrap() == fs::read("./decodecorpus_files/z000019").unwrap());
+
+    // a middle frame whose blocks decode fine but whose checksum is wrong
+    let mut second = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+    *second.last_mut().unwrap() ^= 1;
+    let mut input = first.clone();
+    input.extend_from_slice(&second);
+    input.extend_from_slice(&third);
+    let results = frame_dec.decode_frames_skip_corrupt(&input);
+    assert_eq!(results.len(), 3);
+    assert!(*results[0].as_ref().unwrap() == fs::read("./decodecorpus_files/z000068").unwrap());
+    assert!(matches!(
+        results[1],
+        Err(crate::errors::FrameDecoderError::InvalidData(
+            crate::errors::InvalidDataError::ChecksumMismatch { .. }
+        ))
+    ));
+    assert!(*results[2].as_ref().unwrap() == fs::read("./decodecorpus_files/z000019").unwrap());
+
+    // garbage without any magic number ends the decoding
+    let results = frame_dec.decode_frames_skip_corrupt(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+}
+
+#[test]
+fn test_truncation_policy() {
+    use crate::errors::FrameDecoderError;
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder, TruncationPolicy};
+    use std::fs;
+
+    // z000088 is cut in its second (compressed) block, z000043 in its first (raw) block
+    for (name, len) in &[("z000088", 30_000), ("z000043", 12 + 1000)] {
+        let content = fs::read(format!("./decodecorpus_files/{}.zst", name)).unwrap();
+        let original = fs::read(format!("./decodecorpus_files/{}", name)).unwrap();
+
+        let mut frame_dec = FrameDecoder::new();
+        let mut source = &content[..*len];
+        frame_dec.reset(&mut source).unwrap();
+        assert!(matches!(
+            frame_dec.decode_blocks(&mut source, BlockDecodingStrategy::All),
+            Err(FrameDecoderError::UnexpectedEof { .. })
+        ));
+
+        frame_dec.set_truncation_policy(TruncationPolicy::BestEffort);
+        let mut source = &content[..*len];
+        frame_dec.reset(&mut source).unwrap();
+        let bytes_recovered = match frame_dec.decode_blocks(&mut source, BlockDecodingStrategy::All)
+        {
+            Err(FrameDecoderError::Truncated { bytes_recovered }) => bytes_recovered,
+            r => panic!("Expected Truncated for {}, got: {:?}", name, r),
+        };
+        assert!(frame_dec.is_finished());
+
+        let result = frame_dec.collect().unwrap();
+        assert!(!result.is_empty());
+        assert_eq!(result.len() as u64, bytes_recovered);
+        assert!(
+            result[..] == original[..result.len()],
+            "Recovered bytes are not a prefix of the original for {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_computed_checksum() {
+    use crate::frame_decoder::{BlockDecodingStrategy, FrameDecoder};
+    use std::fs;
+    use std::hash::Hasher;
+
+    let mut files: Vec<_> = fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    let mut frame_dec = FrameDecoder::new();
+    for force in &[false, true] {
+        frame_dec.set_force_checksum(*force);
+        for path in &files {
+            let content = fs::read(path).unwrap();
+            let mut source = content.as_slice();
+            frame_dec.reset(&mut source).unwrap();
+            frame_dec
+                .decode_blocks(&mut source, BlockDecodingStrategy::All)
+                .unwrap();
+            let result = frame_dec.collect().unwrap();
+
+            let mut reference = twox_hash::XxHash64::with_seed(0);
+            reference.write(&result);
+            let reference = reference.finish() as u32;
+
+            if *force || frame_dec.get_checksum_from_data().is_some() {
+                assert_eq!(frame_dec.computed_checksum(), Some(reference), "{:?}", path);
+            } else {
+                assert_eq!(frame_dec.computed_checksum(), None, "{:?}", path);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_checksum_truncated() {
+    use crate::errors::{EofContext, FrameDecoderError};
+    use crate::frame_decoder;
+    use std::fs;
+
+    // this frame has the content checksum flag set
+    let content = fs::read("./decodecorpus_files/z000088.zst").unwrap();
+
+    for bytes_missing in 1..=4 {
+        let mut source = &content[..content.len() - bytes_missing];
+        let mut frame_dec = frame_decoder::FrameDecoder::new();
+        frame_dec.reset(&mut source).unwrap();
+        match frame_dec.decode_blocks(&mut source, frame_decoder::BlockDecodingStrategy::All) {
+            Err(FrameDecoderError::UnexpectedEof {
+                context: EofContext::Checksum,
+                bytes_missing: b,
+            }) => {
+                assert_eq!(b, bytes_missing)
+            }
+            Err(e) => panic!("Expected UnexpectedEof in the checksum, got: {}", e),
+            Ok(_) => panic!("Decoding a frame without its checksum did not fail"),
+        }
+        assert!(!frame_dec.is_finished());
+    }
+
+    // decode_from_to must not consume a partial checksum but wait for the rest of it
+    let mut frame_dec = frame_decoder::FrameDecoder::new();
+    let mut target = vec![0u8; 1024 * 1024];
+    let (read, _) = frame_dec
+        .decode_from_to(&content[..content.len() - 2], &mut target)
+        .unwrap();
+    assert_eq!(read, content.len() - 4);
+    assert!(!frame_dec.is_finished());
+
+    let (read, written) = frame_dec
+        .decode_from_to(&content[content.len() - 4..content.len() - 2], &mut target)
+        .unwrap();
+    assert_eq!((read, written), (0, 0));
+    assert!(!frame_dec.is_finished());
+
+    let (read, _) = frame_dec
+        .decode_from_to(&content[content.len() - 4..], &mut target)
+        .unwrap();
+    assert_eq!(read, 4);
+    assert!(frame_dec.is_finished());
+    assert_eq!(
+        frame_dec.get_checksum_from_data(),
+        frame_dec.get_calculated_checksum()
+    );
+}
+
 pub mod bit_reader;
 pub mod decode_corpus;
+#[cfg(feature = "dictionary")]
 pub mod dict_test;
 pub mod fuzz_regressions;
+pub mod golden;
+pub mod literals_section;
+pub mod release_corpus;
+pub mod sequence_execution;
+pub mod throughput;
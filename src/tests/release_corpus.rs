@@ -0,0 +1,105 @@
+//! Decodes the whole corpus through the StreamingDecoder and through decode_from_to with small output buffers.
+//! Many invariants of the RingBuffer and the bitreaders are only guarded by debug_asserts, so CI also runs
+//! these tests with `cargo test --release` to make sure the decoder is correct without them.
+
+#[cfg(test)]
+fn corpus_files() -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = std::fs::read_dir("./decodecorpus_files")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "zst").unwrap_or(false))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+fn original_for(path: &std::path::Path) -> Vec<u8> {
+    std::fs::read(path.with_extension("")).unwrap()
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn test_corpus_streaming_small_reads() {
+    use crate::streaming_decoder::StreamingDecoder;
+    use std::io::Read;
+
+    for path in corpus_files() {
+        let content = std::fs::read(&path).unwrap();
+        let mut stream = StreamingDecoder::new(content.as_slice()).unwrap();
+
+        // odd sized reads so the decodebuffer gets drained at arbitrary positions
+        let mut result = Vec::new();
+        let mut buf = [0u8; 1000];
+        loop {
+            let bytes_read = stream.read(&mut buf).unwrap();
+            if bytes_read == 0 {
+                break;
+            }
+            result.extend_from_slice(&buf[..bytes_read]);
+        }
+
+        assert!(
+            original_for(&path) == result,
+            "Result differs from original for {:?}",
+            path
+        );
+        assert_eq!(
+            stream.decoder.get_checksum_from_data(),
+            stream.decoder.get_calculated_checksum(),
+            "Checksum mismatch for {:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_corpus_decode_from_to() {
+    use crate::frame_decoder::FrameDecoder;
+
+    // enough for the biggest block + its header + the biggest frame header, see decode_from_to
+    const SOURCE_CHUNK: usize = 128 * 1024 + 3 + 18;
+
+    let mut frame_dec = FrameDecoder::new();
+    for path in corpus_files() {
+        let content = std::fs::read(&path).unwrap();
+        let mut result = Vec::new();
+        let mut target = vec![0u8; 4 * 1024];
+        let mut pos = 0;
+
+        // decode_from_to only initializes a decoder that was never used before, so reset it for every file
+        frame_dec.reset(&content[..]).unwrap();
+        pos += frame_dec.bytes_read_from_source() as usize;
+
+        loop {
+            let end = usize::min(content.len(), pos + SOURCE_CHUNK);
+            let (read, written) = frame_dec
+                .decode_from_to(&content[pos..end], &mut target)
+                .unwrap();
+            pos += read;
+            result.extend_from_slice(&target[..written]);
+
+            if frame_dec.is_finished() && frame_dec.can_collect() == 0 {
+                break;
+            }
+            assert!(
+                read > 0 || written > 0,
+                "decode_from_to made no progress for {:?}",
+                path
+            );
+        }
+
+        assert_eq!(pos, content.len(), "Wrong byte count for {:?}", path);
+        assert!(
+            original_for(&path) == result,
+            "Result differs from original for {:?}",
+            path
+        );
+        assert_eq!(
+            frame_dec.get_checksum_from_data(),
+            frame_dec.get_calculated_checksum(),
+            "Checksum mismatch for {:?}",
+            path
+        );
+    }
+}
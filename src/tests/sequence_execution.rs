@@ -0,0 +1,202 @@
+#[test]
+fn test_offset_one_runs() {
+    use crate::blocks::sequence_section::Sequence;
+    use crate::decoding::scratch::DecoderScratch;
+    use crate::decoding::sequence_execution::execute_sequences;
+
+    // offset values above 3 are new offsets (value - 3), so 4 is an offset of 1.
+    // With literals before it, offset value 1 repeats the last offset which is 1 again after the first sequence
+    let sequences = [
+        Sequence {
+            ll: 1,
+            ml: 100_000,
+            of: 4,
+        },
+        Sequence {
+            ll: 2,
+            ml: 3,
+            of: 1,
+        },
+        Sequence {
+            ll: 1,
+            ml: 70_000,
+            of: 1,
+        },
+        Sequence {
+            ll: 3,
+            ml: 4,
+            of: 5,
+        },
+        Sequence {
+            ll: 0,
+            ml: 129_000,
+            of: 4,
+        },
+    ];
+    let literals = b"abcdefgXYZ";
+
+    // the straightforward byte by byte execution
+    let mut expected = Vec::new();
+    let mut literals_used = 0;
+    let mut offset = 0;
+    for seq in &sequences {
+        expected.extend_from_slice(&literals[literals_used..literals_used + seq.ll as usize]);
+        literals_used += seq.ll as usize;
+        if seq.of > 3 {
+            offset = seq.of as usize - 3;
+        }
+        for _ in 0..seq.ml {
+            expected.push(expected[expected.len() - offset]);
+        }
+    }
+    expected.extend_from_slice(&literals[literals_used..]);
+
+    for single_segment in &[false, true] {
+        let mut scratch = DecoderScratch::new(512 * 1024, *single_segment);
+        // bytes from an earlier block, so the ringbuffer does not start at its beginning
+        scratch.buffer.push(b"earlier");
+        let _ = scratch.buffer.drain();
+
+        scratch.literals_buffer.extend_from_slice(literals);
+        scratch.sequences.extend_from_slice(&sequences);
+        execute_sequences(&mut scratch).unwrap();
+
+        assert!(
+            scratch.buffer.drain() == expected,
+            "Wrong output, single segment: {}",
+            single_segment
+        );
+    }
+}
+
+/// Offset_Value to offset and the repeat offset updates, written down like the format documentation describes them
+#[cfg(test)]
+fn reference_offset(offset_value: u32, literals_length: u32, reps: &mut [u32; 3]) -> u32 {
+    if offset_value > 3 {
+        let offset = offset_value - 3;
+        *reps = [offset, reps[0], reps[1]];
+        return offset;
+    }
+    // with a literals length of 0 the repeat offsets are shifted by one and the last one is Repeated_Offset1 - 1
+    let repeat = if literals_length == 0 {
+        offset_value
+    } else {
+        offset_value - 1
+    };
+    let offset = match repeat {
+        0 => reps[0],
+        1 => reps[1],
+        2 => reps[2],
+        _ => reps[0] - 1,
+    };
+    match repeat {
+        0 => {}
+        1 => *reps = [offset, reps[0], reps[2]],
+        _ => *reps = [offset, reps[0], reps[1]],
+    }
+    offset
+}
+
+/// A compressed block with raw literals and sequences that use rle for all three codes.
+/// The literal and match length codes must not have extra bits, every sequence has its own offset extra bits
+#[cfg(test)]
+fn rle_sequences_block(
+    literals: &[u8],
+    codes: (u8, u8, u8),
+    offset_extra_bits: &[u64],
+    last: bool,
+) -> Vec<u8> {
+    let (ll_code, of_code, ml_code) = codes;
+    // raw literals with the 3 byte header
+    let mut content = (3 << 2 | (literals.len() as u32) << 4).to_le_bytes()[..3].to_vec();
+    content.extend_from_slice(literals);
+    content.extend_from_slice(&[
+        offset_extra_bits.len() as u8,
+        0x54,
+        ll_code,
+        of_code,
+        ml_code,
+    ]);
+
+    // the sequences are read from the end of the bitstream, starting after the highest set bit
+    let mut bits: u128 = 1;
+    for &extra in offset_extra_bits {
+        bits = bits << of_code | u128::from(extra);
+    }
+    let num_bytes = (128 - bits.leading_zeros() as usize).div_ceil(8);
+    content.extend_from_slice(&bits.to_le_bytes()[..num_bytes]);
+
+    let block_header = u32::from(last) | 2 << 1 | (content.len() as u32) << 3;
+    let mut block = block_header.to_le_bytes()[..3].to_vec();
+    block.extend_from_slice(&content);
+    block
+}
+
+/// (literals, (ll code, offset code, ml code), offset extra bits)
+#[cfg(test)]
+type RleSequencesBlock<'a> = (&'a [u8], (u8, u8, u8), &'a [u64]);
+
+#[test]
+fn test_repeat_offsets() {
+    // Literal length codes below 16 are the length itself, match length codes below 32 the length minus 3.
+    // Offset code 0 is always Offset_Value 1, code 1 is 2 or 3 and code 2 are new offsets
+    let blocks: &[RleSequencesBlock<'_>] = &[
+        // new offsets 4, 1 and 3
+        (b"abcdefghijklmnopqrstuvwx", (4, 2, 1), &[3, 0, 2]),
+        // literals length 0: the second and third repeat offset, and the first one minus one
+        (b"", (0, 1, 2), &[0, 1, 1, 0, 1]),
+        // with literals: the second and third repeat offset
+        (b"ABCDEFGHIJ", (2, 1, 3), &[0, 1, 1, 0]),
+        // the first repeat offset with and without literals, which is the second one without them
+        (b"123456", (3, 0, 0), &[0, 0]),
+        (b"", (0, 0, 4), &[0, 0, 0]),
+        // new offsets again after all the repeats
+        (b"xyz", (1, 2, 0), &[1, 2, 3]),
+    ];
+
+    let mut frame = crate::tests::test_frame(&[0, 0], &[]);
+    let mut expected = Vec::new();
+    let mut reps = [1, 4, 8];
+    for (idx, &(literals, codes, offset_extra_bits)) in blocks.iter().enumerate() {
+        frame.extend(rle_sequences_block(
+            literals,
+            codes,
+            offset_extra_bits,
+            idx == blocks.len() - 1,
+        ));
+
+        let (ll, of_code, ml_code) = (u32::from(codes.0), codes.1, u32::from(codes.2) + 3);
+        let mut literals_used = 0;
+        for &extra in offset_extra_bits {
+            expected.extend_from_slice(&literals[literals_used..][..ll as usize]);
+            literals_used += ll as usize;
+            let offset_value = (1 << of_code) + extra as u32;
+            let offset = reference_offset(offset_value, ll, &mut reps) as usize;
+            assert!(
+                offset > 0 && offset <= expected.len(),
+                "Bad test data in block {}",
+                idx
+            );
+            for _ in 0..ml_code {
+                expected.push(expected[expected.len() - offset]);
+            }
+        }
+        expected.extend_from_slice(&literals[literals_used..]);
+    }
+
+    let output = crate::decompress(&frame).unwrap();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_trailing_literals() {
+    // three sequences with 2 literals and a match of 3 each use only 6 of the 12 literals.
+    // Offset code 2 with extra bits 1, 0 and 3 are the new offsets 2, 1 and 4
+    let literals = b"abcdefghTAIL";
+    let mut frame = crate::tests::test_frame(&[0, 0], &[]);
+    frame.extend(rle_sequences_block(literals, (2, 2, 0), &[1, 0, 3], true));
+
+    let output = crate::decompress(&frame).unwrap();
+    // literals then match for every sequence: "ab" "aba", "cd" "ddd", "ef" "dde", then the rest of the literals
+    assert_eq!(output, b"ababacddddefddeghTAIL");
+}
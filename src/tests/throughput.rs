@@ -0,0 +1,56 @@
+//! Guard against big performance regressions. This is ignored by default because timings are only meaningful in release
+//! builds on a quiet machine. Run it with:
+//!
+//! `cargo test --release -- --ignored test_decode_throughput --nocapture`
+//!
+//! The measured throughput is compared against benches/throughput_baseline.txt with a 2x margin to tolerate noisy CI machines.
+//! To update the baseline run the command above a few times on the CI machine (or the machine the baseline is meant for)
+//! and write the lowest reported MB/s into that file.
+
+#[test]
+#[ignore]
+fn test_decode_throughput() {
+    use crate::frame_decoder;
+    use std::fs;
+
+    const ROUNDS: usize = 30;
+    const MARGIN: f64 = 2.0;
+
+    if cfg!(debug_assertions) {
+        println!("Throughput is only checked in release builds, run with --release");
+        return;
+    }
+
+    let baseline: f64 = fs::read_to_string("./benches/throughput_baseline.txt")
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+
+    let content = fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let mut frame_dec = frame_decoder::FrameDecoder::new();
+    let mut bytes_decoded = 0;
+
+    let start_time = std::time::Instant::now();
+    for _ in 0..ROUNDS {
+        let mut source = content.as_slice();
+        frame_dec.reset(&mut source).unwrap();
+        frame_dec
+            .decode_blocks(&mut source, frame_decoder::BlockDecodingStrategy::All)
+            .unwrap();
+        bytes_decoded += frame_dec.collect().unwrap().len();
+    }
+    let elapsed = start_time.elapsed().as_secs_f64();
+
+    let throughput = bytes_decoded as f64 / elapsed / 1_000_000.0;
+    println!(
+        "Decoded {} bytes with {:.1} MB/s (baseline: {:.1} MB/s)",
+        bytes_decoded, throughput, baseline
+    );
+    assert!(
+        throughput * MARGIN >= baseline,
+        "Throughput regressed: {:.1} MB/s is less than half of the baseline {:.1} MB/s",
+        throughput,
+        baseline
+    );
+}
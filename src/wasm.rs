@@ -0,0 +1,21 @@
+//! Bindings for using the decoder from javascript through wasm-bindgen. The crate is a plain rlib, so build the
+//! wasm module as a cdylib and generate the javascript glue with the wasm-bindgen cli:
+//! ```text
+//! cargo rustc --release --lib --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/ruzstd.wasm
+//! ```
+//!
+//! ```js
+//! import init, { decompress } from "./pkg/ruzstd.js";
+//! await init();
+//! const decoded = decompress(compressed); // a Uint8Array, throws if the input is not a valid zstd frame
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+/// Decodes the first zstd frame in input, like crate::decompress. Bytes after that frame are ignored.
+/// If the input can not be decoded an Error with the message of the FrameDecoderError is thrown on the javascript side
+#[wasm_bindgen]
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, JsError> {
+    crate::decompress(input).map_err(|e| JsError::new(&e.to_string()))
+}
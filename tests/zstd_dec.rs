@@ -0,0 +1,100 @@
+//! Runs the zstd-dec binary on the files of the decodecorpus, only built with the cli feature
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const ZSTD_DEC: &str = env!("CARGO_BIN_EXE_zstd-dec");
+
+/// Two frames of the corpus with a skippable frame between them, and what they decode to
+fn multi_frame_input() -> (Vec<u8>, Vec<u8>) {
+    let frame = std::fs::read("./decodecorpus_files/z000033.zst").unwrap();
+    let content = std::fs::read("./decodecorpus_files/z000033").unwrap();
+
+    let mut input = frame.clone();
+    input.extend_from_slice(&0x184D_2A53u32.to_le_bytes());
+    input.extend_from_slice(&5u32.to_le_bytes());
+    input.extend_from_slice(b"skip!");
+    input.extend_from_slice(&frame);
+
+    let mut expected = content.clone();
+    expected.extend_from_slice(&content);
+    (input, expected)
+}
+
+fn run_with_stdin(args: &[&str], stdin: &[u8]) -> std::process::Output {
+    let mut child = Command::new(ZSTD_DEC)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // write from another thread so a full stdout pipe can not block the child while it is being fed
+    let mut child_stdin = child.stdin.take().unwrap();
+    let stdin = stdin.to_vec();
+    let writer = std::thread::spawn(move || child_stdin.write_all(&stdin));
+    let output = child.wait_with_output().unwrap();
+    // the child stops reading when it fails, so a broken pipe is expected then
+    let _ = writer.join().unwrap();
+    output
+}
+
+#[test]
+fn test_zstd_dec_file() {
+    let (input, expected) = multi_frame_input();
+    let path = std::env::temp_dir().join(format!("zstd_dec_test_{}.zst", std::process::id()));
+    std::fs::write(&path, &input).unwrap();
+
+    let output = Command::new(ZSTD_DEC)
+        .arg("-c")
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout == expected);
+}
+
+#[test]
+fn test_zstd_dec_stdin() {
+    let (input, expected) = multi_frame_input();
+
+    for args in [&[][..], &["-dc"], &["-c", "-"]] {
+        let output = run_with_stdin(args, &input);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(
+            output.stdout == expected,
+            "wrong output with args {:?}",
+            args
+        );
+    }
+}
+
+#[test]
+fn test_zstd_dec_errors() {
+    let (input, _) = multi_frame_input();
+
+    // the second frame is cut off
+    let output = run_with_stdin(&[], &input[..input.len() - 10]);
+    assert!(!output.status.success());
+    assert!(!output.stderr.is_empty());
+
+    let output = run_with_stdin(&["-x"], &input);
+    assert!(!output.status.success());
+
+    let output = Command::new(ZSTD_DEC)
+        .arg("./decodecorpus_files/does_not_exist.zst")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}